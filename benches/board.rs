@@ -0,0 +1,62 @@
+//! Throughput of the headless core: ticks per second with snakes of
+//! different lengths, and food spawns on nearly full boards
+//! (`cargo bench`).
+
+#[macro_use] extern crate criterion;
+extern crate snake;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput};
+
+use snake::board::{Board, Mode, Rules};
+use snake::grid::{Direction, Grid, GridPos};
+
+
+/// Creates a board where the snake, starting from the first cell and moving
+/// right, is at least as long as the given length.
+fn board_with_snake(grid: Grid, length: usize) -> Board {
+    let mut rules = Rules::new(Mode::Classic, grid);
+    rules.start_column = Some(0);
+    rules.start_row = Some(0);
+    rules.start_direction = Some(Direction::Right);
+    let mut board = Board::new(rules, 0);
+    board.snake.grow(length as i32 - 1);
+    while board.snake.segments().count() < length {
+        board.tick();
+    }
+    assert!(!board.over, "the snake crashed while growing");
+    board
+}
+
+/// Ticks per second with snakes of length 10, 100 and 1000, moving along a
+/// row long enough to never collide with themselves.
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    group.throughput(Throughput::Elements(1));
+    for length in [10, 100, 1000].iter().cloned() {
+        let board = board_with_snake(Grid::new(1024, 16), length);
+        group.bench_with_input(BenchmarkId::from_parameter(length), &board, |b, board| {
+            b.iter_batched(|| board.clone(), |mut board| board.tick(), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+/// Ticks eating the food, spawning the next one, on a board 50%, 90% and
+/// 99% full.
+fn food_spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("food-spawn");
+    let grid = Grid::new(1024, 1);
+    for percent in [50, 90, 99].iter().cloned() {
+        let mut board = board_with_snake(grid, 1024 * percent / 100);
+        // the food right in front of the snake head
+        let head = board.snake.head_position();
+        board.foods = vec![grid.wrap(GridPos::new(head.x + 1, head.y))];
+        group.bench_with_input(BenchmarkId::from_parameter(percent), &board, |b, board| {
+            b.iter_batched(|| board.clone(), |mut board| board.tick(), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tick, food_spawn);
+criterion_main!(benches);
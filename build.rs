@@ -0,0 +1,27 @@
+//! Generates the C header of the C API, when built with the `ffi` feature.
+
+#[cfg(feature = "ffi")]
+extern crate cbindgen;
+
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        // only the C API is exported, not the whole crate
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        let mut config = cbindgen::Config::default();
+        // the C enumerators are prefixed by their type (e.g. `SnakeCell_Food`)
+        config.enumeration.prefix_with_name = true;
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/ffi.rs")
+            // the cell codes are not part of any signature
+            .include_item("SnakeCell")
+            .with_language(cbindgen::Language::C)
+            .with_include_guard("SNAKE_H")
+            .with_header("/* Generated from src/ffi.rs when building with the `ffi` feature, do not edit. */")
+            .generate()
+            .expect("Unable to generate the C header")
+            .write_to_file("include/snake.h");
+    }
+}
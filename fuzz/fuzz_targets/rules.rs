@@ -0,0 +1,101 @@
+//! Plays runs with arbitrary rules, seeds and inputs, checking the
+//! invariants of the board after every tick (`cargo fuzz run rules`).
+
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate snake;
+
+use std::collections::HashSet;
+
+use snake::board::{Board, Input, Mode, Mutators, Rules, TickEvent};
+use snake::grid::{Direction, Grid, GridPos, Topology};
+use snake::replay::Replay;
+
+
+/// Directions of both the square and the hex grids.
+const DIRECTIONS: [Direction; 8] = [
+    Direction::Left, Direction::Up, Direction::Right, Direction::Down,
+    Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+];
+
+
+fuzz_target!(|data: &[u8]| {
+    // the first bytes give the rules and the seed, the others the input of
+    // each tick
+    if data.len() < 12 {
+        return;
+    }
+    let mode = [Mode::Classic, Mode::Tron, Mode::Versus, Mode::Coop, Mode::Survival, Mode::TimeAttack][data[0] as usize % 6];
+    let topology = if data[0] & 8 != 0 { Topology::Hex } else { Topology::Square };
+    let grid = Grid::with_topology(1 + i32::from(data[1] % 32), 1 + i32::from(data[2] % 32), topology);
+    let mut rules = Rules::new(mode, grid);
+    rules.food_count = 1 + u32::from(data[0] / 16 % 3);
+    rules.enemy_count = u32::from(data[3] % 4);
+    rules.portal_count = u32::from(data[3] / 4 % 3);
+    rules.poison_growth = i32::from(data[3] / 16 % 4) - 2;
+    rules.mutators = if data[0] & 4 != 0 { Mutators::TAIL_CUTTING } else { Mutators::default() };
+    let mut seed = [0; 8];
+    seed.copy_from_slice(&data[4..12]);
+    let seed = u64::from_le_bytes(seed);
+
+    let mut board = Board::new(rules.clone(), seed);
+    let mut inputs = Vec::new();
+    check(&board, false);
+    for byte in &data[12..] {
+        if board.over {
+            break;
+        }
+        // every direction is tried, the ones of the other topology being
+        // discarded
+        let input = match byte % 32 {
+            direction @ 0..=7 => Some(Input::Turn(DIRECTIONS[direction as usize])),
+            direction @ 8..=15 => Some(Input::PartnerTurn(DIRECTIONS[direction as usize - 8])),
+            16 => Some(Input::Stop),
+            _ => None,
+        };
+        if let Some(input) = input {
+            if board.apply(input) {
+                inputs.push((board.ticks, input));
+            }
+        }
+        let events = board.tick();
+        check(&board, events.contains(&TickEvent::Food));
+    }
+
+    // the run is simulated again identically from its replay, also once
+    // written and parsed back
+    let replay = Replay { rules, seed, inputs, ticks: board.ticks, score: board.score };
+    let parsed: Replay = replay.to_string().parse().expect("the replay cannot be parsed back");
+    assert_eq!(parsed, replay);
+    let simulated = replay.simulate();
+    assert_eq!(simulated.score, board.score, "score differs:\n{}", board.to_ascii());
+    assert_eq!(simulated.snake.head_position(), board.snake.head_position(), "head differs:\n{}", board.to_ascii());
+    assert_eq!(simulated.over, board.over);
+});
+
+
+/// Checks the invariants of the board, after a tick where food was eaten
+/// or not.
+fn check(board: &Board, eaten: bool) {
+    let grid = board.rules.grid;
+    let inside = |position: GridPos| position.x >= 0 && position.x < grid.columns && position.y >= 0 && position.y < grid.rows;
+    let snakes = Some(&board.snake).into_iter().chain(board.partner.as_ref()).chain(board.rival.as_ref());
+    for snake in snakes {
+        assert!(snake.segments().all(|segment| inside(*segment)), "segment outside the board:\n{}", board.to_ascii());
+        // the snakes only overlap themselves when crashing
+        if !board.over {
+            let cells: HashSet<_> = snake.segments().collect();
+            assert_eq!(cells.len(), snake.segments().count(), "duplicate occupied cells:\n{}", board.to_ascii());
+        }
+    }
+    assert!(board.foods.iter().all(|food| inside(*food)), "food outside the board:\n{}", board.to_ascii());
+    assert!(board.enemies.iter().all(|enemy| inside(enemy.position())), "enemy outside the board:\n{}", board.to_ascii());
+    // the snake grows at the tail by one segment per food, on the tick
+    // after eating it (only the player eats in classic mode, and neither
+    // poison nor cut tails change its length)
+    let classic = board.rules.mode == Mode::Classic && board.rules.poison_growth == 0;
+    if classic && !board.over {
+        let length = board.snake.segments().count() as u32;
+        assert_eq!(length + eaten as u32, 1 + board.food_count, "snake length does not match the foods eaten:\n{}", board.to_ascii());
+    }
+}
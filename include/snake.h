@@ -0,0 +1,111 @@
+/* Generated from src/ffi.rs when building with the `ffi` feature, do not edit. */
+
+#ifndef SNAKE_H
+#define SNAKE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Codes of the cells written by `snake_observe`, the ones of the `env`
+ * module (written as literals for the C header).
+ */
+enum SnakeCell {
+  SnakeCell_Empty = 0,
+  SnakeCell_Body = 1,
+  SnakeCell_Head = 2,
+  SnakeCell_Food = 3,
+  SnakeCell_Poison = 4,
+  SnakeCell_Enemy = 5,
+  SnakeCell_Portal = 6,
+  SnakeCell_Rival = 7,
+  SnakeCell_Partner = 8,
+};
+typedef uint8_t SnakeCell;
+
+/**
+ * Game played through the C API (opaque to C).
+ */
+typedef struct SnakeGame SnakeGame;
+
+/**
+ * Outcome of a step.
+ */
+typedef struct SnakeStep {
+  float reward;
+  bool done;
+} SnakeStep;
+
+/**
+ * State of the current run.
+ */
+typedef struct SnakeInfo {
+  int32_t columns;
+  int32_t rows;
+  uint32_t score;
+  uint32_t ticks;
+  uint32_t length;
+  bool over;
+} SnakeInfo;
+
+/**
+ * Creates a game on a board of the given size, in the given mode (0
+ * classic, 1 tron, 2 versus, 3 co-op, 4 survival, 5 time attack), starting
+ * the first run with the given seed. Returns null if the options are invalid
+ * (or the board has more than 1,048,576 cells).
+ */
+struct SnakeGame *snake_create(int32_t columns, int32_t rows, uint32_t mode, uint64_t seed);
+
+/**
+ * Starts a new run with the same options and the given seed.
+ *
+ * # Safety
+ *
+ * `game` must be a game created by `snake_create` and not freed yet.
+ */
+void snake_reset(struct SnakeGame *game, uint64_t seed);
+
+/**
+ * Turns the snake in the given direction (0 left, 1 up, 2 right, 3 down,
+ * any other value to keep going) and plays a tick.
+ *
+ * # Safety
+ *
+ * `game` must be a game created by `snake_create` and not freed yet.
+ */
+struct SnakeStep snake_step(struct SnakeGame *game, int32_t direction);
+
+/**
+ * Gets the state of the current run.
+ *
+ * # Safety
+ *
+ * `game` must be a game created by `snake_create` and not freed yet.
+ */
+struct SnakeInfo snake_info(const struct SnakeGame *game);
+
+/**
+ * Writes the code of each cell of the board in the given buffer, row by
+ * row (one of the `SnakeCell` codes). Returns the number of cells,
+ * nothing is written if the buffer is shorter.
+ *
+ * # Safety
+ *
+ * `game` must be a game created by `snake_create` and not freed yet, and
+ * `cells` must point to at least `length` writable bytes.
+ */
+uintptr_t snake_observe(const struct SnakeGame *game, uint8_t *cells, uintptr_t length);
+
+/**
+ * Frees a game.
+ *
+ * # Safety
+ *
+ * `game` must be a game created by `snake_create` and not freed yet (or
+ * null), it cannot be used anymore.
+ */
+void snake_free(struct SnakeGame *game);
+
+#endif  /* SNAKE_H */
@@ -0,0 +1,299 @@
+//! Controllers of the snakes driven by the computer: the rival racing the
+//! player for the food, and the bot playing the demo runs.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use board::{Board, Snake};
+use grid::{Direction, Grid, GridPos};
+
+
+/// Chooses the next direction of the given snake: the one getting closer
+/// to a food among those not leading onto a snake or an enemy. Keeps the
+/// current direction if every cell around the head is taken.
+pub fn steer(board: &Board, snake: &Snake) -> Option<Direction> {
+    let grid = board.rules.grid;
+    let head = snake.head_position();
+    let current = snake.direction();
+    let mut best: Option<(Direction, i32)> = None;
+    for direction in grid.directions() {
+        // a snake longer than one segment cannot go backwards
+        if snake.segments().len() > 1 && direction.is_opposite_to(&current) {
+            continue;
+        }
+        let cell = grid.step(head, *direction);
+        if board.is_blocked(cell) {
+            continue;
+        }
+        let distance = board.nearest_food(cell).map_or(0, |food| grid.distance(cell, food));
+        if best.is_none_or(|(_, shortest)| distance < shortest) {
+            best = Some((*direction, distance));
+        }
+    }
+    best.map(|(direction, _)| direction).or(current)
+}
+
+/// Chooses the next direction of the given snake going for the closest
+/// food: the first step of the shortest path to it (found with A*), as long as
+/// the snake can still reach its own tail once there, so that it never
+/// traps itself. Otherwise the snake takes the safe step closest to the
+/// food, or at least heads for the largest area it can reach. The other
+/// snakes are taken as they are now, the enemies as taking the cells around
+/// them too, and the portals are ignored.
+pub fn seek(board: &Board, snake: &Snake) -> Option<Direction> {
+    let grid = board.rules.grid;
+    let mut obstacles: HashSet<GridPos> = HashSet::new();
+    for enemy in &board.enemies {
+        obstacles.insert(enemy.position());
+        obstacles.extend(grid.directions().iter().map(|direction| grid.step(enemy.position(), *direction)));
+    }
+    for other in board.rival.iter().chain(board.partner.iter()) {
+        if other.head_position() != snake.head_position() {
+            obstacles.extend(other.segments());
+        }
+    }
+    if board.snake.head_position() != snake.head_position() {
+        obstacles.extend(board.snake.segments());
+    }
+    let body = Body {
+        cells: snake.segments().cloned().collect(),
+        growth: if board.rules.mode.variant().trail() { None } else { Some(snake.pending_growth().max(0) as usize) },
+    };
+    let planner = Planner { grid, obstacles };
+    // go for the food if the tail can be reached from there
+    let food = board.nearest_food(body.head());
+    if let Some(path) = food.and_then(|food| planner.shortest_path(&body, food)) {
+        let growth = board.rules.growth_per_food.max(0) as usize;
+        if planner.is_safe(&body.follow(&path, growth)) {
+            return grid.directions().iter().cloned().find(|direction| grid.step(body.head(), *direction) == path[0]);
+        }
+    }
+    let steps: Vec<(Direction, Body)> = grid.directions().iter().cloned()
+        .filter(|direction| !(body.cells.len() > 1 && direction.is_opposite_to(&snake.direction())))
+        .map(|direction| (direction, grid.step(body.head(), direction)))
+        .filter(|(_, cell)| planner.is_free(&body, *cell, 1))
+        .map(|(direction, cell)| (direction, body.follow(&[cell], 0)))
+        .collect();
+    // otherwise get closer to the food while staying safe, until it can be
+    // reached
+    let distance = |body: &Body| food.map_or(0, |food| grid.distance(body.head(), food));
+    let safe = steps.iter()
+        .filter(|(_, body)| planner.is_safe(body))
+        .min_by_key(|(_, body)| distance(body));
+    if let Some((direction, _)) = safe {
+        return Some(*direction);
+    }
+    // at least take the way with the most room
+    steps.iter()
+        .max_by_key(|(_, body)| planner.area(body))
+        .map(|(direction, _)| *direction)
+        .or(snake.direction())
+}
+
+
+/// Cells of a snake while planning its moves.
+#[derive(Clone, Debug)]
+struct Body {
+    cells: Vec<GridPos>,    // segments, from the head to the tail
+    growth: Option<usize>,  // segments still to be added at the tail (none if the snake leaves a trail)
+}
+
+impl Body {
+
+    /// Gets the cell of the head.
+    fn head(&self) -> GridPos {
+        self.cells[0]
+    }
+
+    /// Gets the cell of the tail.
+    fn tail(&self) -> GridPos {
+        self.cells[self.cells.len() - 1]
+    }
+
+    /// Gets the number of steps after which the segment on the given cell
+    /// leaves it, if any segment is there (`None` if it never leaves it).
+    fn leaves(&self, cell: GridPos) -> Option<Option<usize>> {
+        // the last segment on the cell is the one leaving it last
+        let index = self.cells.iter().rposition(|segment| *segment == cell)?;
+        Some(self.growth.map(|growth| self.cells.len() - index + growth))
+    }
+
+    /// Gets the body once the head has gone along the given path, growing
+    /// by the given number of segments once there (when eating the food).
+    fn follow(&self, path: &[GridPos], eaten: usize) -> Body {
+        let cells = path.iter().rev().chain(&self.cells).cloned();
+        match self.growth {
+            Some(pending) => {
+                // the tail stays in place while the snake grows
+                let grown = pending.min(path.len());
+                let cells = cells.take(self.cells.len() + grown).collect();
+                Body { cells, growth: Some(pending - grown + eaten) }
+            },
+            None => Body { cells: cells.collect(), growth: None },
+        }
+    }
+
+}
+
+
+/// Paths of a snake on the board, around the obstacles.
+struct Planner {
+    grid: Grid,                     // board size
+    obstacles: HashSet<GridPos>,    // cells taken by the other snakes and the enemies
+}
+
+impl Planner {
+
+    /// Returns true if the head of the given body can be on the given cell
+    /// after the given number of steps.
+    fn is_free(&self, body: &Body, cell: GridPos, steps: usize) -> bool {
+        if self.obstacles.contains(&cell) {
+            return false;
+        }
+        match body.leaves(cell) {
+            Some(Some(left)) => steps >= left,
+            Some(None) => false,
+            None => true,
+        }
+    }
+
+    /// Finds the shortest path of the head of the given body to the target
+    /// cell with A*, taking into account the segments leaving their cells
+    /// as the snake moves. Returns the cells of the path, from the first
+    /// step to the target.
+    fn shortest_path(&self, body: &Body, target: GridPos) -> Option<Vec<GridPos>> {
+        let distance = |cell: GridPos| {
+            self.grid.distance(cell, target) as usize
+        };
+        let head = body.head();
+        let mut open = BinaryHeap::new();
+        let mut steps: HashMap<GridPos, usize> = HashMap::new();
+        let mut previous: HashMap<GridPos, GridPos> = HashMap::new();
+        open.push(Reverse((distance(head), 0, head.x, head.y)));
+        steps.insert(head, 0);
+        while let Some(Reverse((_, count, x, y))) = open.pop() {
+            let cell = GridPos::new(x, y);
+            if cell == target && count > 0 {
+                let mut path = vec![cell];
+                while let Some(before) = previous.get(path.last().unwrap()) {
+                    if *before == head {
+                        break;
+                    }
+                    path.push(*before);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if steps.get(&cell).is_some_and(|best| *best < count) {
+                continue;
+            }
+            for direction in self.grid.directions() {
+                let next = self.grid.step(cell, *direction);
+                // a snake longer than one segment cannot go backwards
+                if count == 0 && body.cells.len() > 1 && next == body.cells[1] {
+                    continue;
+                }
+                if !self.is_free(body, next, count + 1) || steps.get(&next).is_some_and(|best| *best <= count + 1) {
+                    continue;
+                }
+                steps.insert(next, count + 1);
+                previous.insert(next, cell);
+                open.push(Reverse((count + 1 + distance(next), count + 1, next.x, next.y)));
+            }
+        }
+        None
+    }
+
+    /// Explores the cells the head of the given body can reach, returning
+    /// their number and whether the tail is among them.
+    fn explore(&self, body: &Body) -> (usize, bool) {
+        let mut visited: HashSet<GridPos> = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(body.head());
+        queue.push_back((body.head(), 0));
+        let mut tail = false;
+        while let Some((cell, count)) = queue.pop_front() {
+            for direction in self.grid.directions() {
+                let next = self.grid.step(cell, *direction);
+                if visited.contains(&next) || !self.is_free(body, next, count + 1) {
+                    continue;
+                }
+                tail |= body.cells.len() > 1 && next == body.tail();
+                visited.insert(next);
+                queue.push_back((next, count + 1));
+            }
+        }
+        (visited.len() - 1, tail)
+    }
+
+    /// Returns true if the snake with the given body can keep going: its
+    /// head can reach its tail, or at least as many cells as its length.
+    fn is_safe(&self, body: &Body) -> bool {
+        let (area, tail) = self.explore(body);
+        tail || area >= body.cells.len()
+    }
+
+    /// Gets the number of cells the head of the given body can reach.
+    fn area(&self, body: &Body) -> usize {
+        self.explore(body).0
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::{Input, Mode, Rules, TickEvent};
+
+    /// Pocket of three cells walled by the partner snake, with the food at
+    /// its end and the snake head at its opening.
+    const POCKET: &str = "
+        .......
+        .PPPP..
+        @..*P..
+        SpPPP..
+        SSSS...
+    ";
+
+    fn board(diagram: &str) -> Board {
+        Board::from_ascii(Rules::new(Mode::Classic, Grid::new(1, 1)), 0, diagram).unwrap()
+    }
+
+    #[test]
+    fn seek_avoids_dead_ends() {
+        // the snake longer than the pocket would be trapped eating the food
+        let board = board(POCKET);
+        let direction = seek(&board, &board.snake);
+        assert!(direction == Some(Direction::Up) || direction == Some(Direction::Left), "{:?}\n{}", direction, board.to_ascii());
+    }
+
+    #[test]
+    fn seek_enters_pockets_the_snake_fits_in() {
+        let board = board(&POCKET.replace('S', "."));
+        assert_eq!(seek(&board, &board.snake), Some(Direction::Right), "\n{}", board.to_ascii());
+    }
+
+    #[test]
+    fn seek_reaches_the_food_behind_its_body() {
+        // the snake cannot reverse, it goes around its body
+        let mut board = board("
+            ...............
+            ...............
+            .....<SSSSS.*..
+            ...............
+            ...............
+        ");
+        for _ in 0..15 {
+            if let Some(direction) = seek(&board, &board.snake) {
+                board.apply(Input::Turn(direction));
+            }
+            if board.tick().contains(&TickEvent::Food) {
+                return;
+            }
+            assert!(!board.over, "the snake crashed:\n{}", board.to_ascii());
+        }
+        panic!("the food was not reached:\n{}", board.to_ascii());
+    }
+
+}
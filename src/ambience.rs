@@ -0,0 +1,115 @@
+//! Ambiences of the board: variants of the palette following the time of
+//! day or the season, chosen automatically from the clock or set by the
+//! player.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Local hours from which the night ambience is chosen (until the morning).
+const NIGHT_HOURS: (u64, u64) = (20, 6);
+
+
+/// Variant of the palette.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Ambience {
+    #[default]
+    Off,        // the colors as configured
+    Auto,       // the night after dark, otherwise the season
+    Day,
+    Night,
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Ambience {
+
+    /// Parses the ambience from its name.
+    pub fn from_name(name: &str) -> Result<Ambience, &'static str> {
+        match name {
+            "off" => Ok(Ambience::Off),
+            "auto" => Ok(Ambience::Auto),
+            "day" => Ok(Ambience::Day),
+            "night" => Ok(Ambience::Night),
+            "spring" => Ok(Ambience::Spring),
+            "summer" => Ok(Ambience::Summer),
+            "autumn" => Ok(Ambience::Autumn),
+            "winter" => Ok(Ambience::Winter),
+            _ => Err("Invalid ambience: <off|auto|day|night|spring|summer|autumn|winter>")
+        }
+    }
+
+    /// Gets the name of the ambience.
+    pub fn name(self) -> &'static str {
+        match self {
+            Ambience::Off => "off",
+            Ambience::Auto => "auto",
+            Ambience::Day => "day",
+            Ambience::Night => "night",
+            Ambience::Spring => "spring",
+            Ambience::Summer => "summer",
+            Ambience::Autumn => "autumn",
+            Ambience::Winter => "winter",
+        }
+    }
+
+    /// Resolves the automatic ambience from the current time, given the
+    /// offset of the local time from UTC (in hours). Any other ambience is
+    /// returned as is.
+    pub fn resolve(self, utc_offset: i32) -> Ambience {
+        if self != Ambience::Auto {
+            return self;
+        }
+        let local = local_time(utc_offset);
+        let hour = local / 3600 % 24;
+        if hour >= NIGHT_HOURS.0 || hour < NIGHT_HOURS.1 {
+            return Ambience::Night;
+        }
+        // the meteorological seasons of the northern hemisphere
+        match date(local / 86_400).1 {
+            3..=5 => Ambience::Spring,
+            6..=8 => Ambience::Summer,
+            9..=11 => Ambience::Autumn,
+            _ => Ambience::Winter,
+        }
+    }
+
+    /// Gets the color the palette is tinted toward, and how much the
+    /// background is (the entities being tinted a third as much), if any.
+    pub fn tint(self) -> Option<([u8; 3], f32)> {
+        match self {
+            Ambience::Off | Ambience::Auto | Ambience::Day => None,
+            Ambience::Night => Some(([16, 16, 48], 0.7)),
+            Ambience::Spring => Some(([120, 200, 120], 0.4)),
+            Ambience::Summer => Some(([240, 210, 120], 0.3)),
+            Ambience::Autumn => Some(([200, 110, 40], 0.4)),
+            Ambience::Winter => Some(([220, 230, 255], 0.5)),
+        }
+    }
+
+}
+
+
+/// Gets the current local time (in seconds since the Unix epoch), given
+/// the offset of the local time from UTC (in hours).
+pub fn local_time(utc_offset: i32) -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+    (now as i64 + i64::from(utc_offset) * 3600).max(0) as u64
+}
+
+/// Gets the date (the year, the month from 1 to 12 and the day of the
+/// month) of the given day since the Unix epoch.
+pub fn date(days: u64) -> (u64, u64, u64) {
+    // the days of the 400 years eras, each year starting in March
+    let days = days + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    // January and February belong to the year starting the March before
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
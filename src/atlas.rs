@@ -0,0 +1,165 @@
+//! Texture atlas of the images of the entities, and their animations.
+
+use sfml::graphics::{IntRect, Texture};
+
+use std::collections::HashMap;
+
+
+/// Names of the regions drawn from the atlas, when it lists them. The
+/// entities without a region are drawn as colored squares.
+pub const REGIONS: [&str; 7] = ["snake-head", "snake-body", "food", "poison", "enemy", "portal", "pause"];
+
+
+/// Sequence of images of the atlas shown one after the other (a region with
+/// a single frame is a still image).
+#[derive(Clone, Debug)]
+pub struct Animation {
+    frames: Vec<IntRect>,   // region of each frame of the animation
+    frame_time: f32,        // seconds each frame is shown
+    looping: bool,          // whether it starts over after the last frame (stops on it otherwise)
+    elapsed: f32,           // seconds since the animation started
+}
+
+impl Animation {
+
+    /// Advances the animation by the given time.
+    pub fn update(&mut self, seconds: f32) {
+        let duration = self.frame_time * self.frames.len() as f32;
+        self.elapsed += seconds;
+        if self.looping && duration > 0.0 {
+            self.elapsed %= duration;
+        }
+    }
+
+    /// Starts the animation over from its first frame.
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Gets the region of the frame currently shown.
+    pub fn frame(&self) -> IntRect {
+        let index = if self.frame_time > 0.0 { (self.elapsed / self.frame_time) as usize } else { 0 };
+        self.frames[index.min(self.frames.len() - 1)]
+    }
+
+}
+
+
+/// Single texture holding the images of the game entities, each one in its
+/// own region (so that all the entities are drawn with a single texture).
+pub struct Atlas {
+    pub texture: Texture,                   // texture with all the images
+    regions: HashMap<String, Animation>,    // frames of each image, by name
+}
+
+impl Atlas {
+
+    /// Creates a new atlas from its texture and the description of its
+    /// regions, made of a section for each region with the position and
+    /// size in pixels of its first frame and, if animated, the number of
+    /// frames (placed side by side, from left to right), the seconds each
+    /// one is shown and whether the animation loops:
+    ///
+    /// ```toml
+    /// [snake-head]
+    /// x = 0
+    /// y = 0
+    /// width = 16
+    /// height = 16
+    /// frames = 4
+    /// frame-time = 0.1
+    /// loop = false
+    /// ```
+    pub fn new(texture: Texture, description: &str) -> Result<Atlas, String> {
+        let mut regions: HashMap<String, Animation> = HashMap::new();
+        let mut section: Option<(String, Region)> = None;
+        for (i, line) in description.lines().map(str::trim).enumerate() {
+            let error = |message: &str| format!("Atlas regions, line {}: {}", i + 1, message);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some((name, region)) = section.take() {
+                    regions.insert(name.clone(), region.animation(&name, &texture)?);
+                }
+                section = Some((name.trim().to_string(), Region::default()));
+                continue;
+            }
+            let (_, region) = section.as_mut().ok_or_else(|| error("Expected a [<region>] section"))?;
+            let (key, value) = line.split_once('=').ok_or_else(|| error("Invalid line: <key> = <value>"))?;
+            let value = value.trim();
+            let number = || value.parse().map_err(|_| error("Invalid number"));
+            match key.trim() {
+                "x" => region.rect.left = number()?,
+                "y" => region.rect.top = number()?,
+                "width" => region.rect.width = number()?,
+                "height" => region.rect.height = number()?,
+                "frames" => region.frames = number()?,
+                "frame-time" => region.frame_time = value.parse().map_err(|_| error("Invalid frame time"))?,
+                "loop" => region.looping = value.parse().map_err(|_| error("The loop key must be true or false"))?,
+                _ => return Err(error("Unknown key, expected x, y, width, height, frames, frame-time or loop"))
+            };
+        }
+        if let Some((name, region)) = section {
+            regions.insert(name.clone(), region.animation(&name, &texture)?);
+        }
+        for name in regions.keys().filter(|name| !REGIONS.contains(&name.as_str())) {
+            warn!("unused atlas region: {}", name);
+        }
+        Ok(Atlas { texture, regions })
+    }
+
+    /// Gets the region of the first frame of the image with the given name,
+    /// if any.
+    pub fn region(&self, name: &str) -> Option<IntRect> {
+        self.regions.get(name).map(|animation| animation.frames[0])
+    }
+
+    /// Gets the animations of all the images, by name, each one from its
+    /// first frame.
+    pub fn animations(&self) -> HashMap<String, Animation> {
+        self.regions.clone()
+    }
+
+}
+
+
+/// Region of the atlas as described, before being checked.
+struct Region {
+    rect: IntRect,      // first frame
+    frames: i32,        // number of frames, side by side
+    frame_time: f32,    // seconds each frame is shown
+    looping: bool,      // whether the animation loops
+}
+
+impl Default for Region {
+
+    /// Creates a still image region.
+    fn default() -> Region {
+        Region { rect: IntRect::default(), frames: 1, frame_time: 0.1, looping: true }
+    }
+
+}
+
+impl Region {
+
+    /// Checks that all the frames are within the given texture, and gets
+    /// the animation of the region.
+    fn animation(self, name: &str, texture: &Texture) -> Result<Animation, String> {
+        let Region { rect, frames, frame_time, looping } = self;
+        let size = texture.size();
+        let inside = rect.left >= 0 && rect.top >= 0 && rect.width > 0 && rect.height > 0 && frames > 0
+            && (rect.left + rect.width * frames) as u32 <= size.x && (rect.top + rect.height) as u32 <= size.y;
+        if !inside {
+            return Err(format!("The atlas region {} is outside the atlas image", name));
+        }
+        if frame_time < 0.0 {
+            return Err(format!("The frame time of the atlas region {} must not be negative", name));
+        }
+        let frames = (0..frames)
+            .map(|i| IntRect::new(rect.left + rect.width * i, rect.top, rect.width, rect.height))
+            .collect();
+        Ok(Animation { frames, frame_time, looping, elapsed: 0.0 })
+    }
+
+}
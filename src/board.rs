@@ -0,0 +1,1377 @@
+//! Game rules and state of a run, independent of any frontend.
+
+use rand::prelude::*;
+use rand::prng::XorShiftRng;
+
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::collections::vec_deque::Iter;
+use std::fmt;
+
+use ai;
+use diagram;
+use grid::{Direction, FreeCells, Grid, GridPos, Topology};
+use spawn::{FoodSpawn, SpawnPolicy, Uniform};
+use variant::{self, Variant};
+
+
+/// Minimum distance (in cells along a row or column) between the snake
+/// starting cell and the obstacles spawned at the beginning of a run.
+const SPAWN_DISTANCE: i32 = 3;
+
+thread_local! {
+    /// Number of cells checked for collisions with the snakes on this
+    /// thread, to profile the ticks (the environments being stepped on
+    /// many threads at once).
+    static COLLISION_CHECKS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Speed of a snake moving a cell every tick (the speeds being percentages
+/// of the tick rate of the board).
+pub const FULL_SPEED: u32 = 100;
+
+
+/// Enumerates all the game modes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Classic,    // eat food to grow and score points
+    Tron,       // the snake leaves a persistent trail and scores by survival time
+    Versus,     // a rival snake races the player for the food
+    Coop,       // two players, each with a snake, share the score
+    Survival,   // score by survival time too, while more and more enemies join
+    TimeAttack, // eat as much food as possible in a limited time
+}
+
+impl Mode {
+
+    /// Parses the game mode from its name.
+    pub fn from_name(name: &str) -> Result<Mode, &'static str> {
+        match name {
+            "classic" => Ok(Mode::Classic),
+            "tron" => Ok(Mode::Tron),
+            "versus" => Ok(Mode::Versus),
+            "coop" => Ok(Mode::Coop),
+            "survival" => Ok(Mode::Survival),
+            "time-attack" => Ok(Mode::TimeAttack),
+            _ => Err("Invalid game mode: <classic|tron|versus|coop|survival|time-attack>")
+        }
+    }
+
+    /// Gets the name of the game mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Tron => "tron",
+            Mode::Versus => "versus",
+            Mode::Coop => "coop",
+            Mode::Survival => "survival",
+            Mode::TimeAttack => "time-attack",
+        }
+    }
+
+    /// Gets the variant of the game played in the mode.
+    pub fn variant(self) -> &'static dyn Variant {
+        match self {
+            Mode::Classic => &variant::Classic,
+            Mode::Tron => &variant::Tron,
+            Mode::Versus => &variant::Versus,
+            Mode::Coop => &variant::Coop,
+            Mode::Survival => &variant::Survival,
+            Mode::TimeAttack => &variant::TimeAttack,
+        }
+    }
+
+}
+
+
+/// Rules of a run, they never change while playing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rules {
+    pub mode: Mode,                         // game mode
+    pub grid: Grid,                         // board size
+    pub food_count: u32,                    // number of foods on the board at once
+    pub growth_per_food: i32,               // number of segments gained eating food
+    pub poison_growth: i32,                 // number of segments gained eating poison (0 to disable)
+    pub magnet_seconds: u32,                // duration of the magnet power-up (0 to disable)
+    pub star_seconds: u32,                  // duration of the star power-up (0 to disable)
+    pub portal_count: u32,                  // number of portal pairs
+    pub enemy_count: u32,                   // number of enemies
+    pub enemy_period: u32,                  // number of ticks between two enemy moves
+    pub ticks_per_second: u32,              // snake speed
+    pub rival_speed: u32,                   // speed of the rival snake (percentage of the tick rate)
+    pub start_column: Option<u32>,          // column of the snake starting cell (random if none)
+    pub start_row: Option<u32>,             // row of the snake starting cell (random if none)
+    pub start_direction: Option<Direction>, // initial snake direction (none to wait for the player)
+    pub modifiers: Modifiers,               // fun modifiers (the run is not ranked if any)
+    pub mutators: Mutators,                 // variations of the rules, recorded with the score
+    pub food_spawn: FoodSpawn,              // policy choosing where the food spawns
+    pub scoring: Scoring,                   // points scored and lost (the preset of the mode by default)
+}
+
+impl Rules {
+
+    /// Creates the rules of a run of the given mode on the given grid, with
+    /// the default options (the ones of the game without arguments).
+    pub fn new(mode: Mode, grid: Grid) -> Rules {
+        Rules {
+            mode,
+            grid,
+            food_count: 1,
+            growth_per_food: 1,
+            poison_growth: 0,
+            magnet_seconds: 0,
+            star_seconds: 0,
+            portal_count: 0,
+            enemy_count: 0,
+            enemy_period: 2,
+            ticks_per_second: 10,
+            rival_speed: FULL_SPEED,
+            start_column: None,
+            start_row: None,
+            start_direction: None,
+            modifiers: Modifiers::default(),
+            mutators: Mutators::default(),
+            food_spawn: FoodSpawn::default(),
+            scoring: Scoring::new(mode),
+        }
+    }
+
+    /// Gets the number of ticks per second actually played, taking into
+    /// account the mutators.
+    pub fn tick_rate(&self) -> u32 {
+        if self.mutators.contains(Mutators::DOUBLE_SPEED) {
+            self.ticks_per_second * 2
+        } else {
+            self.ticks_per_second
+        }
+    }
+
+    /// Gets the factor applied to every score increase, rewarding faster
+    /// speeds (x1 up to 11 ticks per second, x2 from 12 to 18, and so on).
+    pub fn score_multiplier(&self) -> u32 {
+        ((self.tick_rate() as f32 / 7.5).round() as u32).max(1)
+    }
+
+}
+
+
+/// Points scored and lost during a run: the points scored are multiplied by
+/// the score multiplier, the ones lost are not.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scoring {
+    pub food: u32,      // points scored eating food
+    pub power_up: u32,  // points scored picking up a power-up (the magnet or the star)
+    pub second: u32,    // points scored for each second survived
+    pub tail_cut: u32,  // points scored for each segment cut from the tail of the other snake
+    pub poison: u32,    // points lost eating poison
+    pub boost: u32,     // points lost for each extra cell moved while boosting
+}
+
+impl Scoring {
+
+    /// All the points, with their names.
+    const NAMES: [&'static str; 6] = ["food", "power-up", "second", "tail-cut", "poison", "boost"];
+
+    /// Creates the preset of the given mode.
+    pub fn new(mode: Mode) -> Scoring {
+        let variant = mode.variant();
+        Scoring {
+            food: variant.food_points(),
+            power_up: 0,
+            second: variant.second_points(),
+            tail_cut: 5,
+            poison: 0,
+            boost: 1,
+        }
+    }
+
+    /// Gets all the points, in the order of their names.
+    fn points(self) -> [u32; 6] {
+        [self.food, self.power_up, self.second, self.tail_cut, self.poison, self.boost]
+    }
+
+    /// Gets the points of the given name.
+    fn points_mut(&mut self, name: &str) -> Option<&mut u32> {
+        match name {
+            "food" => Some(&mut self.food),
+            "power-up" => Some(&mut self.power_up),
+            "second" => Some(&mut self.second),
+            "tail-cut" => Some(&mut self.tail_cut),
+            "poison" => Some(&mut self.poison),
+            "boost" => Some(&mut self.boost),
+            _ => None
+        }
+    }
+
+    /// Overrides the points given as comma (or space) separated
+    /// `<name>=<points>` pairs, the others being left as they are.
+    pub fn with(mut self, overrides: &str) -> Result<Scoring, &'static str> {
+        for pair in overrides.split(|c: char| c == ',' || c.is_whitespace()).filter(|pair| !pair.is_empty()) {
+            let (name, points) = pair.split_once('=').ok_or("Invalid scoring: <name>=<points>")?;
+            let points = points.parse().map_err(|_| "The scoring points must be u32")?;
+            *self.points_mut(name).ok_or("Invalid scoring: <food|power-up|second|tail-cut|poison|boost>")? = points;
+        }
+        Ok(self)
+    }
+
+    /// Gets the `<name>=<points>` pairs of the points that differ from the
+    /// preset of the given mode.
+    pub fn overrides(self, mode: Mode) -> Vec<String> {
+        let preset = Scoring::new(mode).points();
+        Scoring::NAMES.iter().zip(self.points().iter().zip(preset.iter()))
+            .filter(|(_, (points, preset))| points != preset)
+            .map(|(name, (points, _))| format!("{}={}", name, points))
+            .collect()
+    }
+
+}
+
+
+/// Set of mutators of a run, chosen before it starts: each one is a
+/// variation of the rules (or of what is shown of the board), and the runs
+/// are ranked separately for each set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mutators(u8);
+
+impl Mutators {
+
+    pub const DOUBLE_SPEED: Mutators = Mutators(1);         // the snake moves twice as fast
+    pub const MIRRORED: Mutators = Mutators(1 << 1);        // the left and right turns are swapped
+    pub const INVISIBLE_TAIL: Mutators = Mutators(1 << 2);  // only the snake head is shown
+    pub const FOG_OF_WAR: Mutators = Mutators(1 << 3);      // only the cells around the snake head are shown
+    pub const INVERTED: Mutators = Mutators(1 << 4);        // the up and down turns are swapped
+    pub const TAIL_CUTTING: Mutators = Mutators(1 << 5);    // running into the tail of the rival cuts it (versus mode)
+
+    /// All the mutators, with their names.
+    const NAMES: [(Mutators, &'static str); 6] = [
+        (Mutators::DOUBLE_SPEED, "double-speed"),
+        (Mutators::MIRRORED, "mirrored"),
+        (Mutators::INVISIBLE_TAIL, "invisible-tail"),
+        (Mutators::FOG_OF_WAR, "fog-of-war"),
+        (Mutators::INVERTED, "inverted"),
+        (Mutators::TAIL_CUTTING, "tail-cutting"),
+    ];
+
+    /// Returns true if all the given mutators are in the set.
+    pub fn contains(self, mutators: Mutators) -> bool {
+        self.0 & mutators.0 == mutators.0
+    }
+
+    /// Adds the given mutators to the set.
+    pub fn insert(&mut self, mutators: Mutators) {
+        self.0 |= mutators.0;
+    }
+
+    /// Returns true if the set has no mutator.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Gets the direction the snake turns to when the given direction is
+    /// pressed, swapped by the mirrored and inverted mutators (swapping it
+    /// again gives back the direction to press).
+    pub fn swap(self, mut direction: Direction) -> Direction {
+        if self.contains(Mutators::MIRRORED) {
+            direction = direction.mirrored();
+        }
+        if self.contains(Mutators::INVERTED) {
+            direction = direction.inverted();
+        }
+        direction
+    }
+
+    /// Parses the set of mutators from their names, separated by commas or
+    /// whitespaces (e.g. `double-speed,fog-of-war`).
+    pub fn from_names(names: &str) -> Result<Mutators, &'static str> {
+        let mut mutators = Mutators::default();
+        for name in names.split(|c: char| c == ',' || c.is_whitespace()).filter(|name| !name.is_empty()) {
+            let (mutator, _) = Mutators::NAMES.iter().find(|(_, n)| *n == name)
+                .ok_or("Invalid mutator: <double-speed|mirrored|inverted|invisible-tail|fog-of-war|tail-cutting>")?;
+            mutators.insert(*mutator);
+        }
+        Ok(mutators)
+    }
+
+    /// Gets the names of the mutators in the set.
+    pub fn names(self) -> Vec<&'static str> {
+        Mutators::NAMES.iter().filter(|(mutator, _)| self.contains(*mutator)).map(|(_, name)| *name).collect()
+    }
+
+}
+
+
+/// Fun modifiers of a run, unlocked by cheat codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub rainbow: bool,      // each snake segment has its own color
+    pub big_head: bool,     // the snake head is drawn larger than its cell
+    pub invincible: bool,   // the snake survives hitting itself or the enemies
+}
+
+impl Modifiers {
+
+    /// Returns true if any modifier is enabled.
+    pub fn any(&self) -> bool {
+        self.rainbow || self.big_head || self.invincible
+    }
+
+    /// Parses the modifiers from their names, separated by whitespaces.
+    pub fn from_names(names: &str) -> Result<Modifiers, &'static str> {
+        let mut modifiers = Modifiers::default();
+        for name in names.split_whitespace() {
+            match name {
+                "rainbow" => modifiers.rainbow = true,
+                "big-head" => modifiers.big_head = true,
+                "invincible" => modifiers.invincible = true,
+                _ => return Err("Invalid modifier: <rainbow|big-head|invincible>")
+            };
+        }
+        Ok(modifiers)
+    }
+
+    /// Gets the names of the enabled modifiers.
+    pub fn names(&self) -> Vec<&'static str> {
+        let flags = [(self.rainbow, "rainbow"), (self.big_head, "big-head"), (self.invincible, "invincible")];
+        flags.iter().filter(|(enabled, _)| *enabled).map(|(_, name)| *name).collect()
+    }
+
+}
+
+
+/// Player inputs changing the board state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Turn(Direction),        // queue a direction change
+    PartnerTurn(Direction), // queue a direction change of the second player snake (in co-op mode)
+    Stop,                   // discard the pending direction changes (of both players)
+    Boost(bool),            // start or stop boosting the player snake
+}
+
+impl Input {
+
+    /// Parses the input from its name: a direction, a direction of the
+    /// second player prefixed by `partner-`, `stop`, `boost` or `coast`
+    /// (stop boosting).
+    pub fn from_name(name: &str) -> Result<Input, &'static str> {
+        match name {
+            "stop" => Ok(Input::Stop),
+            "boost" => Ok(Input::Boost(true)),
+            "coast" => Ok(Input::Boost(false)),
+            name => match name.strip_prefix("partner-") {
+                Some(name) => Ok(Input::PartnerTurn(Direction::from_name(name)?)),
+                None => Ok(Input::Turn(Direction::from_name(name)?)),
+            },
+        }
+    }
+
+    /// Gets the name of the input.
+    pub fn name(self) -> &'static str {
+        match self {
+            Input::Turn(direction) => direction.name(),
+            Input::PartnerTurn(Direction::Left) => "partner-left",
+            Input::PartnerTurn(Direction::Up) => "partner-up",
+            Input::PartnerTurn(Direction::Right) => "partner-right",
+            Input::PartnerTurn(Direction::Down) => "partner-down",
+            Input::PartnerTurn(Direction::UpLeft) => "partner-up-left",
+            Input::PartnerTurn(Direction::UpRight) => "partner-up-right",
+            Input::PartnerTurn(Direction::DownLeft) => "partner-down-left",
+            Input::PartnerTurn(Direction::DownRight) => "partner-down-right",
+            Input::Stop => "stop",
+            Input::Boost(true) => "boost",
+            Input::Boost(false) => "coast",
+        }
+    }
+
+}
+
+
+/// Enumerates what can happen during a single tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TickEvent {
+    Food,       // the snake ate the food
+    RivalFood,  // the rival snake ate the food
+    TailCut,    // a snake cut the tail of the other one
+    Poison,     // the snake ate the poison food
+    Magnet,     // the snake picked up the magnet power-up
+    Star,       // the snake picked up the star power-up
+    GameOver,   // the snake died
+    Victory,    // the snake filled the whole board
+}
+
+
+/// The snake.
+#[derive(Clone)]
+pub struct Snake {
+    segments: VecDeque<GridPos>,            // snake segments
+    occupancy: HashMap<GridPos, u32>,       // number of segments on each occupied cell
+    free_cells: FreeCells,                  // cells not occupied by any segment
+    direction: Option<Direction>,           // snake current direction
+    next_directions: VecDeque<Direction>,   // pending direction changes
+    pending_growth: i32,                    // segments still to be added (or removed if negative)
+    pub speed: u32,                         // cells moved per tick (percentage, see `FULL_SPEED`)
+    progress: u32,                          // progress toward the next cell (percentage)
+    distance: u32,                          // number of cells moved
+}
+
+impl Snake {
+
+    /// Creates a new snake with a single segment.
+    pub fn new(grid: Grid, position: GridPos) -> Snake {
+        let mut snake = Snake {
+            segments: VecDeque::new(),
+            occupancy: HashMap::new(),
+            free_cells: FreeCells::new(grid),
+            direction: None,
+            next_directions: VecDeque::new(),
+            pending_growth: 0,
+            speed: FULL_SPEED,
+            progress: 0,
+            distance: 0,
+        };
+        // create snake head
+        snake.push_front(position);
+        snake
+    }
+
+    /// Creates a snake with its head on the given cell and the given body,
+    /// from the neck to the tail. It moves in the given direction, or away
+    /// from its neck if none.
+    pub fn with_segments(grid: Grid, head: GridPos, body: &[GridPos], direction: Option<Direction>) -> Snake {
+        let mut snake = Snake::new(grid, *body.last().unwrap_or(&head));
+        for segment in body.iter().rev().skip(1).chain(Some(&head).filter(|_| !body.is_empty())) {
+            snake.push_front(*segment);
+        }
+        snake.direction = direction.or_else(|| {
+            body.first().and_then(|neck| grid.directions().iter().cloned().find(|direction| grid.step(*neck, *direction) == head))
+        });
+        snake
+    }
+
+    /// Adds a new head segment on the given cell.
+    fn push_front(&mut self, position: GridPos) {
+        self.segments.push_front(position);
+        *self.occupancy.entry(position).or_insert(0) += 1;
+        self.free_cells.remove(position);
+    }
+
+    /// Removes the tail segment.
+    fn pop_back(&mut self) {
+        if let Some(position) = self.segments.pop_back() {
+            if let Entry::Occupied(mut entry) = self.occupancy.entry(position) {
+                *entry.get_mut() -= 1;
+                if *entry.get() == 0 {
+                    entry.remove();
+                    self.free_cells.insert(position);
+                }
+            }
+        }
+    }
+
+    /// Gets an iterator over the segments, from the head to the tail.
+    pub fn segments(&self) -> Iter<'_, GridPos> {
+        self.segments.iter()
+    }
+
+    /// Gets the position of the snake head.
+    pub fn head_position(&self) -> GridPos {
+        // the snake has always at least 1 segment
+        *self.segments.front().unwrap()
+    }
+
+    /// Gets the snake current direction, if moving.
+    pub fn direction(&self) -> Option<Direction> {
+        self.direction
+    }
+
+    /// Returns true if the snake head collided with any
+    /// of its segments.
+    pub fn self_collision(&self) -> bool {
+        count_collision_check();
+        // the head shares its cell with another segment
+        self.occupancy.get(&self.head_position()).is_some_and(|count| *count > 1)
+    }
+
+    /// Returns true only if the given cell is occupied by any of the
+    /// snake segments.
+    pub fn collision(&self, position: GridPos) -> bool {
+        count_collision_check();
+        self.occupancy.contains_key(&position)
+    }
+
+    /// Gets the cells not occupied by any segment.
+    pub fn free_cells(&self) -> &FreeCells {
+        &self.free_cells
+    }
+
+    /// Gets the number of segments still to be added at the tail (or
+    /// removed if negative).
+    pub fn pending_growth(&self) -> i32 {
+        self.pending_growth
+    }
+
+    /// Gets the index of the segment on the given cell (0 for the head),
+    /// the one closest to the head if more than one.
+    pub fn segment_index(&self, position: GridPos) -> Option<usize> {
+        if !self.collision(position) {
+            return None;
+        }
+        self.segments.iter().position(|segment| *segment == position)
+    }
+
+    /// Cuts the snake removing the segments from the given index to the
+    /// tail (the head is never removed). Returns the number of segments
+    /// removed.
+    pub fn cut(&mut self, index: usize) -> usize {
+        let count = self.segments.len().saturating_sub(index.max(1));
+        for _ in 0..count {
+            self.pop_back();
+        }
+        count
+    }
+
+    /// Grows the snake by the given number of segments, or shrinks it if
+    /// the amount is negative. The change is applied at the tail, one segment
+    /// per step.
+    pub fn grow(&mut self, amount: i32) {
+        self.pending_growth += amount;
+    }
+
+    /// Queues a direction change, applied at one of the next steps.
+    /// Returns false if the change was discarded because the queue is full
+    /// or the snake would go backwards.
+    pub fn turn(&mut self, direction: Direction) -> bool {
+        // at most two changes are buffered, so that quick taps within
+        // the same frame are applied on consecutive steps
+        if self.next_directions.len() >= 2 {
+            return false;
+        }
+        // check against the direction the snake will have once the
+        // queued changes are applied
+        let last = self.next_directions.back().cloned().or(self.direction);
+        if last == Some(direction) && !self.next_directions.is_empty() {
+            return false;
+        }
+        if self.segments.len() > 1 && direction.is_opposite_to(&last) {
+            return false;
+        }
+        self.next_directions.push_back(direction);
+        true
+    }
+
+    /// Discards all the pending direction changes.
+    pub fn stop(&mut self) {
+        self.next_directions.clear();
+    }
+
+    /// Gets the number of cells the snake moves during the next tick at its
+    /// speed, the progress toward a further cell being carried over.
+    pub fn schedule(&mut self) -> u32 {
+        self.progress += self.speed;
+        let cells = self.progress / FULL_SPEED;
+        self.progress %= FULL_SPEED;
+        cells
+    }
+
+    /// Updates the snake position.
+    /// * `trail` - If true the tail is never removed and the snake leaves
+    ///   a persistent trail behind its head.
+    /// * `portals` - Portals teleporting the head to their pair.
+    pub fn advance(&mut self, grid: Grid, trail: bool, portals: &[Portal]) {
+        // update direction, discarding changes that would make the snake
+        // go backwards
+        while let Some(direction) = self.next_directions.pop_front() {
+            if self.segments.len() == 1 || !direction.is_opposite_to(&self.direction) {
+                self.direction = Some(direction);
+                break;
+            }
+        }
+        let position = match self.next_head_position(grid) {
+            Some(position) => position,
+            None => return
+        };
+        // entering a portal makes the head exit from its pair
+        let position = portals.iter()
+            .find_map(|portal| portal.exit(position))
+            .unwrap_or(position);
+        if trail || self.pending_growth > 0 {
+            // the old segments are left in place, the tail is not removed
+            // and the snake grows by one segment
+            if !trail {
+                self.pending_growth -= 1;
+            }
+        } else {
+            // the old tail is removed and a new head is added, gives the
+            // "illusion" of movement
+            self.pop_back();
+        }
+        // the new segment is now the first
+        self.push_front(position);
+        self.distance += 1;
+        // shrink by removing one segment from the tail
+        if self.pending_growth < 0 {
+            if self.segments.len() > 1 {
+                self.pop_back();
+            }
+            self.pending_growth += 1;
+        }
+    }
+
+    /// Gets the number of cells the snake moved since it was created.
+    pub fn distance(&self) -> u32 {
+        self.distance
+    }
+
+    /// Gets the position the head will occupy after moving one step
+    /// in the current direction, if any.
+    pub fn next_head_position(&self, grid: Grid) -> Option<GridPos> {
+        self.direction.map(|direction| grid.step(self.head_position(), direction))
+    }
+}
+
+
+/// A pair of linked portals, the snake head entering one of them
+/// exits from the other preserving its direction.
+#[derive(Clone)]
+pub struct Portal {
+    pub positions: [GridPos; 2],    // position of both ends
+}
+
+impl Portal {
+
+    /// Creates a new pair of portals.
+    pub fn new(first: GridPos, second: GridPos) -> Portal {
+        Portal { positions: [first, second] }
+    }
+
+    /// Returns true if one of the portal ends is on the given cell.
+    pub fn contains(&self, position: GridPos) -> bool {
+        self.positions.contains(&position)
+    }
+
+    /// Gets the position of the paired end if the given position is
+    /// on one of the portal ends.
+    pub fn exit(&self, position: GridPos) -> Option<GridPos> {
+        if position == self.positions[0] {
+            Some(self.positions[1])
+        } else if position == self.positions[1] {
+            Some(self.positions[0])
+        } else {
+            None
+        }
+    }
+
+}
+
+
+/// Enumerates the enemy movement policies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behavior {
+    Patrol,     // walks back and forth along a straight line
+    Chase,      // moves towards the snake head
+}
+
+
+/// An enemy moving on the grid, the snake dies when touching it.
+#[derive(Clone)]
+pub struct Enemy {
+    position: GridPos,      // cell occupied by the enemy
+    behavior: Behavior,     // movement policy
+    direction: Direction,   // current patrol direction
+    steps: u32,             // steps walked since the last turn
+}
+
+impl Enemy {
+
+    /// Creates a new enemy with the given movement policy.
+    pub fn new(position: GridPos, behavior: Behavior, direction: Direction) -> Enemy {
+        Enemy { position, behavior, direction, steps: 0 }
+    }
+
+    /// Gets the position of the enemy.
+    pub fn position(&self) -> GridPos {
+        self.position
+    }
+
+    /// Moves the enemy one step according to its movement policy.
+    /// * `target` - Position of the snake head.
+    pub fn advance(&mut self, target: GridPos, grid: Grid) {
+        let position = self.position;
+        let direction = match self.behavior {
+            Behavior::Patrol => {
+                // turn back after walking a few steps
+                if self.steps == 5 {
+                    self.steps = 0;
+                    self.direction = self.direction.opposite();
+                }
+                self.steps += 1;
+                self.direction
+            },
+            Behavior::Chase => match grid.topology {
+                // move along the axis with the longest distance
+                Topology::Square => {
+                    let (dx, dy) = grid.offset(position, target);
+                    if dx.abs() >= dy.abs() {
+                        if dx < 0 { Direction::Left } else { Direction::Right }
+                    } else if dy < 0 {
+                        Direction::Up
+                    } else {
+                        Direction::Down
+                    }
+                },
+                // move to the neighbour closest to the target
+                Topology::Diagonal | Topology::Hex => *grid.directions().iter()
+                    .min_by_key(|direction| grid.distance(grid.step(position, **direction), target))
+                    .unwrap_or(&self.direction),
+            },
+        };
+        self.position = grid.step(position, direction);
+    }
+
+}
+
+
+/// State of the board during a run: all the entities, the score and the
+/// random number generator. Runs are deterministic, the same rules, seed
+/// and inputs (given at the same ticks) always give the same run.
+#[derive(Clone)]
+pub struct Board {
+    pub rules: Rules,               // rules of the run
+    pub seed: u64,                  // seed of the random number generator
+    rng: XorShiftRng,               // generator used for every random choice
+    pub snake: Snake,               // the player
+    pub rival: Option<Snake>,       // snake controlled by the computer (only in versus mode)
+    pub partner: Option<Snake>,     // snake of the second player (only in co-op mode)
+    pub foods: Vec<GridPos>,        // food positions
+    pub poison: Option<GridPos>,    // poison food position (if any)
+    pub magnet: Option<GridPos>,    // magnet power-up position (if any)
+    pub magnet_ticks: u32,          // number of ticks left of the magnet picked up
+    pub star: Option<GridPos>,      // star power-up position (if any)
+    pub star_ticks: u32,            // number of ticks left of the star picked up
+    pub boosting: bool,             // whether the player snake moves twice as fast
+    pub enemies: Vec<Enemy>,        // enemies
+    pub portals: Vec<Portal>,       // portal pairs
+    pub score: u32,                 // current score
+    pub rival_score: u32,           // current score of the rival snake
+    pub food_count: u32,            // number of foods eaten
+    pub ticks: u32,                 // number of ticks since the beginning of the run
+    pub over: bool,                 // whether the run ended (game over or victory)
+    heat: Vec<u32>,                 // number of times the player snake head entered each cell (by row)
+}
+
+impl Board {
+
+    /// Creates a new board, placing all the entities with a random number
+    /// generator initialized with the given seed.
+    pub fn new(rules: Rules, seed: u64) -> Board {
+        let grid = rules.grid;
+        let mut board = Board {
+            rules,
+            seed,
+            rng: Board::seeded_rng(seed),
+            snake: Snake::new(grid, GridPos::new(0, 0)),
+            rival: None,
+            partner: None,
+            foods: Vec::new(),
+            poison: None,
+            magnet: None,
+            magnet_ticks: 0,
+            star: None,
+            star_ticks: 0,
+            boosting: false,
+            enemies: Vec::new(),
+            portals: Vec::new(),
+            score: 0,
+            rival_score: 0,
+            food_count: 0,
+            ticks: 0,
+            over: false,
+            heat: vec![0; grid.cell_count()],
+        };
+        // initialize the snake (on the configured cell, if any) and the food
+        let random_position = board.random_position();
+        let player_position = grid.wrap(GridPos::new(
+            board.rules.start_column.map_or(random_position.x, |x| x as i32),
+            board.rules.start_row.map_or(random_position.y, |y| y as i32)));
+        board.snake = Snake::new(grid, player_position);
+        board.snake.direction = board.rules.start_direction.filter(|direction| grid.directions().contains(direction));
+        for _ in 0..board.rules.food_count.max(1) {
+            if let Some(position) = board.spawn_food() {
+                board.foods.push(position);
+            }
+        }
+        let variant = board.rules.mode.variant();
+        if variant.has_rival() {
+            board.spawn_rival();
+        }
+        if variant.has_partner() {
+            board.partner = board.spawn_position().map(|position| Snake::new(grid, position));
+        }
+        // initialize the portals, the poison food and the enemies
+        board.spawn_portals();
+        if board.rules.poison_growth != 0 {
+            board.poison = board.free_position();
+        }
+        board.spawn_enemies();
+        if board.rules.magnet_seconds > 0 && variant.has_food() {
+            board.magnet = board.free_position();
+        }
+        if board.rules.star_seconds > 0 {
+            board.star = board.free_position();
+        }
+        board
+    }
+
+    /// Creates a random number generator initialized with the given seed.
+    fn seeded_rng(seed: u64) -> XorShiftRng {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        bytes[8..].copy_from_slice(&(seed ^ 0x9E37_79B9_7F4A_7C15).to_le_bytes());
+        XorShiftRng::from_seed(bytes)
+    }
+
+    /// Creates the board drawn by the given ASCII diagram (see
+    /// `diagram::encode`), as large as the diagram, with the given rules
+    /// and seed. The entities of the diagram replace the ones spawned.
+    pub fn from_ascii(mut rules: Rules, seed: u64, diagram: &str) -> Result<Board, &'static str> {
+        let (columns, rows) = diagram::size(diagram);
+        if columns == 0 || rows == 0 {
+            return Err("The board diagram is empty");
+        }
+        rules.grid.columns = columns;
+        rules.grid.rows = rows;
+        let mut board = Board::new(rules, seed);
+        diagram::decode(&mut board, diagram)?;
+        Ok(board)
+    }
+
+    /// Draws the board as an ASCII diagram, a line for each row.
+    pub fn to_ascii(&self) -> String {
+        diagram::encode(self)
+    }
+
+    /// Gets the number of whole seconds played.
+    pub fn seconds(&self) -> u32 {
+        self.ticks / self.rules.tick_rate().max(1)
+    }
+
+    /// Applies the given player input, returns false if it was discarded.
+    pub fn apply(&mut self, input: Input) -> bool {
+        // the controls are swapped before checking the turn, so that the
+        // snake never reverses whatever key is pressed
+        let mutators = self.rules.mutators;
+        let directions = self.rules.grid.directions();
+        match input {
+            // only the directions of the grid cells can be taken
+            Input::Turn(direction) | Input::PartnerTurn(direction) if !directions.contains(&mutators.swap(direction)) => false,
+            Input::Turn(direction) => self.snake.turn(mutators.swap(direction)),
+            Input::PartnerTurn(direction) => match self.partner.as_mut() {
+                Some(partner) => partner.turn(mutators.swap(direction)),
+                None => false
+            },
+            Input::Stop => {
+                self.snake.stop();
+                if let Some(partner) = self.partner.as_mut() {
+                    partner.stop();
+                }
+                true
+            },
+            Input::Boost(boosting) => {
+                let changed = self.boosting != boosting;
+                self.boosting = boosting;
+                self.snake.speed = if boosting { 2 * FULL_SPEED } else { FULL_SPEED };
+                changed
+            },
+        }
+    }
+
+    /// Advances the run by a single step.
+    pub fn tick(&mut self) -> Vec<TickEvent> {
+        let mut events = Vec::new();
+        if self.over {
+            return events;
+        }
+        // update the player position
+        let grid = self.rules.grid;
+        let variant = self.rules.mode.variant();
+        let trail = variant.trail();
+        // each snake moves as many cells as its speed allows: the player
+        // snake moves the extra cells on its own (e.g. while boosting), and
+        // the last one along with the other snakes
+        let mut cells = self.snake.schedule();
+        while cells > 1 {
+            self.step_ahead(&mut events);
+            if self.over {
+                return events;
+            }
+            cells -= 1;
+        }
+        if cells == 1 {
+            self.snake.advance(grid, trail, &self.portals);
+            self.visit();
+        }
+        if let Some(partner) = self.partner.as_mut() {
+            for _ in 0..partner.schedule() {
+                partner.advance(grid, trail, &self.portals);
+            }
+        }
+        // the rival moves right after the player (only checked for crashes
+        // once all its cells are moved)
+        if let Some(mut rival) = self.rival.take() {
+            for _ in 0..rival.schedule() {
+                if let Some(direction) = ai::steer(self, &rival) {
+                    rival.turn(direction);
+                }
+                rival.advance(grid, false, &self.portals);
+            }
+            self.rival = Some(rival);
+        }
+        // move the enemies every `enemy_period` ticks
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(self.rules.enemy_period.max(1)) {
+            let target = self.snake.head_position();
+            for enemy in &mut self.enemies {
+                enemy.advance(target, grid);
+            }
+        }
+        // what the moves lead to, the collisions and the food eaten are up
+        // to the variant played
+        variant.on_move(self, &mut events);
+        self.shine(&mut events);
+        let collision = variant.on_collision(self);
+        if collision && !self.phasing() {
+            self.over = true;
+            events.push(TickEvent::GameOver);
+        } else if let Some(event) = variant.outcome(self) {
+            self.over = true;
+            events.push(event);
+        } else {
+            // score every second survived
+            if self.ticks.is_multiple_of(self.rules.tick_rate().max(1)) {
+                self.score += self.rules.scoring.second * self.rules.score_multiplier();
+            }
+            self.attract(&mut events);
+            variant.on_eat(self, &mut events);
+            if self.over {
+                return events;
+            }
+            // more enemies may join as the time goes by
+            while (self.enemies.len() as u32) < variant.enemy_count(&self.rules, self.seconds()) {
+                if !self.spawn_enemy() {
+                    break;
+                }
+            }
+            if variant.is_time_up(self) {
+                info!("time is up: ticks = {}", self.ticks);
+                self.over = true;
+                events.push(TickEvent::GameOver);
+            }
+        }
+        events
+    }
+
+    /// Checks the collisions of the snake of the first player, or of the
+    /// second one, with the food and the poison food, growing the snake and
+    /// scoring (both players feed the same score). Returns false if the run
+    /// is over, the player winning once the board is too full for any food.
+    pub(crate) fn feed(&mut self, partner: bool, events: &mut Vec<TickEvent>) -> bool {
+        let head = match self.player_mut(partner) {
+            Some(snake) => snake.head_position(),
+            None => return true
+        };
+        if let Some(index) = self.foods.iter().position(|food| *food == head) {
+            // increase snake length
+            let growth = self.rules.growth_per_food;
+            if let Some(snake) = self.player_mut(partner) {
+                snake.grow(growth);
+            }
+            self.food_count += 1;
+            // increase score
+            self.score += self.rules.scoring.food * self.rules.score_multiplier();
+            events.push(TickEvent::Food);
+            // replace the food, the player wins once the board is too
+            // full for any food
+            self.replace_food(index);
+            if self.foods.is_empty() {
+                self.over = true;
+                events.push(TickEvent::Victory);
+                return false;
+            }
+        }
+        if self.poison == Some(head) {
+            // change snake length (usually shrinking it), and update the
+            // poison position (removing it if there is no room left)
+            let growth = self.rules.poison_growth;
+            if let Some(snake) = self.player_mut(partner) {
+                snake.grow(growth);
+            }
+            self.score = self.score.saturating_sub(self.rules.scoring.poison);
+            self.poison = self.free_position();
+            events.push(TickEvent::Poison);
+        }
+        true
+    }
+
+    /// Checks the collision of the rival snake with the food, growing it
+    /// and scoring if it reaches the food first.
+    pub(crate) fn feed_rival(&mut self, events: &mut Vec<TickEvent>) {
+        let rival_head = self.rival.as_ref().map(Snake::head_position);
+        if let Some(index) = self.foods.iter().position(|food| Some(*food) == rival_head) {
+            if let Some(rival) = self.rival.as_mut() {
+                rival.grow(self.rules.growth_per_food);
+            }
+            self.rival_score += self.rules.scoring.food * self.rules.score_multiplier();
+            events.push(TickEvent::RivalFood);
+            self.replace_food(index);
+        }
+    }
+
+    /// Picks up the star power-up if the snake head reached it, and counts
+    /// down the ticks left of the one picked up (a new star appearing once
+    /// it wears off).
+    fn shine(&mut self, events: &mut Vec<TickEvent>) {
+        self.pick_up_star(events);
+        if self.star_ticks > 0 {
+            self.star_ticks -= 1;
+            if self.star_ticks == 0 {
+                self.star = self.free_position();
+            }
+        }
+    }
+
+    /// Picks up the star power-up if the snake head reached it.
+    fn pick_up_star(&mut self, events: &mut Vec<TickEvent>) {
+        if self.star == Some(self.snake.head_position()) {
+            self.star = None;
+            // counted down right away, the tick picking up the star being
+            // the first phasing one
+            self.star_ticks = self.rules.star_seconds * self.rules.tick_rate() + 1;
+            self.score += self.rules.scoring.power_up * self.rules.score_multiplier();
+            events.push(TickEvent::Star);
+        }
+    }
+
+    /// Returns true if the snake phases through itself and the obstacles,
+    /// surviving any collision: while a star lasts, or always with the
+    /// invincible modifier.
+    pub fn phasing(&self) -> bool {
+        self.star_ticks > 0 || self.rules.modifiers.invincible
+    }
+
+    /// Picks up the magnet power-up if the snake head reached it, and pulls
+    /// the food closest to the snake head one cell toward it while the
+    /// magnet lasts (a new magnet appearing once it wears off).
+    fn attract(&mut self, events: &mut Vec<TickEvent>) {
+        let head = self.snake.head_position();
+        self.pick_up_magnet(events);
+        if self.magnet_ticks == 0 {
+            return;
+        }
+        self.magnet_ticks -= 1;
+        if self.magnet_ticks == 0 {
+            self.magnet = self.free_position();
+        }
+        let food = match self.nearest_food(head) {
+            Some(food) => food,
+            None => return
+        };
+        // the distance of the cells from the head, going around the other
+        // entities, up to the food
+        let grid = self.rules.grid;
+        let mut distances = HashMap::new();
+        distances.insert(head, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(head);
+        while let Some(cell) = queue.pop_front() {
+            if cell == food {
+                break;
+            }
+            let distance = distances[&cell];
+            for direction in grid.directions() {
+                let next = grid.step(cell, *direction);
+                if distances.contains_key(&next) || (next != food && self.is_taken(next)) {
+                    continue;
+                }
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+        // the food moves one step back along the path, into the head once
+        // next to it (where it is eaten right away)
+        let distance = match distances.get(&food) {
+            Some(distance) => *distance,
+            None => return
+        };
+        let closer = grid.directions().iter()
+            .map(|direction| grid.step(food, *direction))
+            .find(|cell| distances.get(cell) == Some(&(distance - 1)));
+        if let (Some(cell), Some(index)) = (closer, self.foods.iter().position(|position| *position == food)) {
+            self.foods[index] = cell;
+        }
+    }
+
+    /// Replaces the food eaten at the given index with a new one, or removes
+    /// it if there is no room left.
+    fn replace_food(&mut self, index: usize) {
+        // the eaten food is under the head, so it is taken until replaced
+        match self.spawn_food() {
+            Some(position) => self.foods[index] = position,
+            None => {
+                self.foods.remove(index);
+            },
+        }
+    }
+
+    /// Gets the food closest to the given cell, if there is any left.
+    pub fn nearest_food(&self, position: GridPos) -> Option<GridPos> {
+        let grid = self.rules.grid;
+        self.foods.iter().cloned().min_by_key(|food| grid.distance(position, *food))
+    }
+
+    /// Returns a random cell not taken by any entity and at least
+    /// `SPAWN_DISTANCE` cells away from the snake head, or any free cell
+    /// if no such cell is found after a few attempts.
+    fn spawn_position(&mut self) -> Option<GridPos> {
+        let head = self.snake.head_position();
+        let mut position = self.free_position()?;
+        for _ in 0..100 {
+            if self.rules.grid.ring(head, position) >= SPAWN_DISTANCE {
+                break;
+            }
+            position = self.free_position().unwrap_or(position);
+        }
+        Some(position)
+    }
+
+    /// Returns a random cell of the grid.
+    fn random_position(&mut self) -> GridPos {
+        let grid = self.rules.grid;
+        GridPos::new(self.rng.gen_range(0, grid.columns), self.rng.gen_range(0, grid.rows))
+    }
+
+    /// Returns true if the snake head is next to its own body or to an
+    /// enemy, without touching them.
+    pub fn near_miss(&self) -> bool {
+        let head = self.snake.head_position();
+        // the segment right behind the head is always next to it
+        let neck = self.snake.segments().nth(1).cloned();
+        self.rules.grid.directions().iter()
+            .map(|direction| self.rules.grid.step(head, *direction))
+            .filter(|cell| Some(*cell) != neck)
+            .any(|cell| self.snake.collision(cell) || self.enemies.iter().any(|e| e.position() == cell))
+    }
+
+    /// Gets the snake of the first player, or of the second one (only in
+    /// co-op mode).
+    fn player_mut(&mut self, partner: bool) -> Option<&mut Snake> {
+        if partner {
+            self.partner.as_mut()
+        } else {
+            Some(&mut self.snake)
+        }
+    }
+
+    /// Returns true if the given cell is occupied by a snake or an enemy,
+    /// which would end the run of the snake moving onto it.
+    pub fn is_blocked(&self, position: GridPos) -> bool {
+        self.snake.collision(position)
+            || self.rival.as_ref().is_some_and(|rival| rival.collision(position))
+            || self.partner.as_ref().is_some_and(|partner| partner.collision(position))
+            || self.enemies.iter().any(|e| e.position() == position)
+    }
+
+    /// Returns true if the given cell is occupied by any entity.
+    pub fn is_taken(&self, position: GridPos) -> bool {
+        self.snake.collision(position)
+            || self.rival.as_ref().is_some_and(|rival| rival.collision(position))
+            || self.partner.as_ref().is_some_and(|partner| partner.collision(position))
+            || self.foods.contains(&position)
+            || self.poison == Some(position)
+            || self.magnet == Some(position)
+            || self.star == Some(position)
+            || self.enemies.iter().any(|e| e.position() == position)
+            || self.portals.iter().any(|p| p.contains(position))
+    }
+
+    /// Returns a random position within the grid that doesn't collide
+    /// with the snake, the food or any of the enemies, if there is any
+    /// free cell left.
+    fn free_position(&mut self) -> Option<GridPos> {
+        let mut rng = self.rng.clone();
+        let position = Uniform.next_spawn(self, &mut rng);
+        self.rng = rng;
+        position
+    }
+
+    /// Returns the position of the next food, chosen by the food spawn
+    /// policy of the rules, if there is any free cell left.
+    fn spawn_food(&mut self) -> Option<GridPos> {
+        let mut rng = self.rng.clone();
+        let position = self.rules.food_spawn.next_spawn(self, &mut rng);
+        self.rng = rng;
+        position
+    }
+
+    /// Spawns the portal pairs at random positions.
+    fn spawn_portals(&mut self) {
+        for i in 0..self.rules.portal_count as usize {
+            let first = match self.spawn_position() {
+                Some(position) => position,
+                None => return
+            };
+            // the first end must be already taken when looking for the second one
+            self.portals.push(Portal::new(first, first));
+            match self.spawn_position() {
+                Some(second) => self.portals[i] = Portal::new(first, second),
+                None => {
+                    self.portals.pop();
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Spawns the enemies at random positions far enough from the snake head.
+    fn spawn_enemies(&mut self) {
+        for _ in 0..self.rules.enemy_count {
+            if !self.spawn_enemy() {
+                return;
+            }
+        }
+    }
+
+    /// Spawns a new enemy at a random position, returns false if there is
+    /// no room left.
+    fn spawn_enemy(&mut self) -> bool {
+        let position = match self.spawn_position() {
+            Some(position) => position,
+            None => return false
+        };
+        // alternate patrolling and chasing enemies
+        let behavior = if self.enemies.len().is_multiple_of(2) { Behavior::Patrol } else { Behavior::Chase };
+        let directions = self.rules.grid.directions();
+        let direction = directions[self.rng.gen_range(0, directions.len())];
+        self.enemies.push(Enemy::new(position, behavior, direction));
+        true
+    }
+
+    /// Spawns the rival snake (again, if it crashed) at a random position
+    /// far enough from the player.
+    pub(crate) fn spawn_rival(&mut self) {
+        self.rival = None;
+        let grid = self.rules.grid;
+        let speed = self.rules.rival_speed;
+        self.rival = self.spawn_position().map(|position| Snake { speed, ..Snake::new(grid, position) });
+    }
+
+    /// Cuts the tail of the snake that the head of the other one ran into
+    /// (in versus mode): the cut segments are lost, and the other snake
+    /// scores for each one of them.
+    pub(crate) fn cut_tails(&mut self, events: &mut Vec<TickEvent>) {
+        let points = self.rules.scoring.tail_cut * self.rules.score_multiplier();
+        let rival = match self.rival.as_mut() {
+            Some(rival) => rival,
+            None => return
+        };
+        // running into the head is still a crash
+        let head = self.snake.head_position();
+        if let Some(index) = rival.segment_index(head).filter(|index| *index > 0) {
+            self.score += rival.cut(index) as u32 * points;
+            events.push(TickEvent::TailCut);
+        }
+        let rival_head = rival.head_position();
+        if let Some(index) = self.snake.segment_index(rival_head).filter(|index| *index > 0) {
+            self.rival_score += self.snake.cut(index) as u32 * points;
+            events.push(TickEvent::TailCut);
+        }
+    }
+
+    /// Returns true if the head of the given rival snake hit itself, the
+    /// player or an enemy.
+    pub(crate) fn is_rival_crashed(&self, rival: &Snake) -> bool {
+        let head = rival.head_position();
+        rival.self_collision()
+            || self.snake.collision(head)
+            || self.enemies.iter().any(|enemy| enemy.position() == head)
+    }
+
+    /// Picks up the magnet power-up if the snake head reached it.
+    fn pick_up_magnet(&mut self, events: &mut Vec<TickEvent>) {
+        if self.magnet == Some(self.snake.head_position()) {
+            self.magnet = None;
+            self.magnet_ticks = self.rules.magnet_seconds * self.rules.tick_rate();
+            self.score += self.rules.scoring.power_up * self.rules.score_multiplier();
+            events.push(TickEvent::Magnet);
+        }
+    }
+
+    /// Moves the player snake one extra cell ahead of the others, losing
+    /// the boost points: the cell is checked for collisions, food and
+    /// power-ups like any other.
+    fn step_ahead(&mut self, events: &mut Vec<TickEvent>) {
+        let variant = self.rules.mode.variant();
+        self.snake.advance(self.rules.grid, variant.trail(), &self.portals);
+        self.visit();
+        self.score = self.score.saturating_sub(self.rules.scoring.boost);
+        self.pick_up_star(events);
+        let head = self.snake.head_position();
+        let collision = self.snake.self_collision()
+            || self.enemy_collision()
+            || self.rival.as_ref().is_some_and(|rival| rival.collision(head))
+            || self.partner.as_ref().is_some_and(|partner| partner.collision(head));
+        if collision && !self.phasing() {
+            self.over = true;
+            events.push(TickEvent::GameOver);
+        } else {
+            self.pick_up_magnet(events);
+            variant.on_eat(self, events);
+        }
+    }
+
+    /// Counts the visit of the cell the player snake head just entered (if
+    /// it moved at all).
+    fn visit(&mut self) {
+        if self.snake.direction().is_none() {
+            return;
+        }
+        let head = self.snake.head_position();
+        let index = (head.y * self.rules.grid.columns + head.x) as usize;
+        if let Some(visits) = self.heat.get_mut(index) {
+            *visits += 1;
+        }
+    }
+
+    /// Gets the number of times the player snake head entered the given
+    /// cell during the run.
+    pub fn visits(&self, cell: GridPos) -> u32 {
+        let index = (cell.y * self.rules.grid.columns + cell.x) as usize;
+        self.heat.get(index).cloned().unwrap_or(0)
+    }
+
+    /// Returns true if any enemy is touching the snake.
+    pub(crate) fn enemy_collision(&self) -> bool {
+        self.enemies.iter().any(|enemy| self.snake.collision(enemy.position()))
+    }
+
+}
+
+impl fmt::Display for Board {
+
+    /// Writes the board as an ASCII diagram.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_ascii())
+    }
+
+}
+
+
+/// Gets the number of cells checked for collisions with the snakes so far
+/// on the current thread.
+pub fn collision_checks() -> u64 {
+    COLLISION_CHECKS.with(Cell::get)
+}
+
+/// Counts a cell checked for collisions with a snake.
+fn count_collision_check() {
+    COLLISION_CHECKS.with(|checks| checks.set(checks.get() + 1));
+}
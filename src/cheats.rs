@@ -0,0 +1,48 @@
+//! Cheat codes typed during the game, unlocking the modifiers of the runs.
+
+use sfml::window::Key;
+
+use std::collections::VecDeque;
+
+
+/// Cheats, each one toggling a modifier of the next runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cheat {
+    Rainbow,    // each snake segment has its own color
+    BigHead,    // the snake head is drawn larger than its cell
+    Invincible, // the snake survives hitting itself or the enemies
+}
+
+/// Key sequences that unlock each cheat.
+const CODES: [(Cheat, &[Key]); 3] = [
+    (Cheat::Rainbow, &[Key::Up, Key::Right, Key::Down, Key::Left, Key::Up, Key::Right, Key::Down, Key::Left]),
+    (Cheat::BigHead, &[Key::B, Key::I, Key::G]),
+    // the Konami code
+    (Cheat::Invincible, &[Key::Up, Key::Up, Key::Down, Key::Down, Key::Left, Key::Right, Key::Left, Key::Right, Key::B, Key::A]),
+];
+
+
+/// Matches the keys pressed against the cheat codes.
+#[derive(Debug, Default)]
+pub struct CheatCodes {
+    recent: VecDeque<Key>,  // last keys pressed, as many as the longest code
+}
+
+impl CheatCodes {
+
+    /// Records the given key, returning the cheat whose code it completes
+    /// (if any).
+    pub fn press(&mut self, key: Key) -> Option<Cheat> {
+        let longest = CODES.iter().map(|(_, code)| code.len()).max().unwrap_or(0);
+        if self.recent.len() == longest {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(key);
+        let recent = &self.recent;
+        let (cheat, _) = CODES.iter().find(|(_, code)| recent.iter().rev().take(code.len()).eq(code.iter().rev()))?;
+        // a code is entered again from scratch
+        self.recent.clear();
+        Some(*cheat)
+    }
+
+}
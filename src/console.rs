@@ -0,0 +1,72 @@
+//! Commands of the debug console, parsed from the lines typed in game.
+
+use std::str::FromStr;
+
+use grid::GridPos;
+
+
+/// Commands accepted by the debug console.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    SpawnFood(GridPos),     // move the food to the given cell
+    SetScore(u32),          // change the score
+    Speed(u32),             // change the number of ticks per second
+    TimeScale(f32),         // slow down or speed up the game time
+    Seed(u64),              // restart the run with the given seed
+    State(StateName),       // change the game state
+    ExportBoard,            // write the diagram of the board to a file
+    ImportBoard,            // place the entities of the diagram in the file on the board
+}
+
+
+/// Game states that can be set from the console.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StateName {
+    Pause,
+    Play,
+    GameOver,
+    Victory,
+}
+
+
+impl FromStr for Command {
+    type Err = &'static str;
+
+    /// Parses a command made of whitespace separated words, for example
+    /// `spawn food 5 7`, `set score 500`, `speed 20`, `timescale 0.5`,
+    /// `seed 1234`, `state gameover`, `board export` or `board import`.
+    fn from_str(line: &str) -> Result<Command, &'static str> {
+        // parse a number, whatever its type
+        fn number<T: FromStr>(word: Option<&str>) -> Result<T, &'static str> {
+            word.ok_or("Missing number")?.parse().map_err(|_| "Invalid number")
+        }
+        let mut words = line.split_whitespace();
+        let command = match (words.next(), words.next()) {
+            (Some("spawn"), Some("food")) => {
+                let x = number(words.next())?;
+                let y = number(words.next())?;
+                Command::SpawnFood(GridPos::new(x, y))
+            },
+            (Some("set"), Some("score")) => Command::SetScore(number(words.next())?),
+            (Some("speed"), value) => Command::Speed(number(value)?),
+            (Some("timescale"), value) => Command::TimeScale(number(value)?),
+            (Some("seed"), value) => Command::Seed(number(value)?),
+            (Some("board"), Some("export")) => Command::ExportBoard,
+            (Some("board"), Some("import")) => Command::ImportBoard,
+            (Some("state"), Some(name)) => Command::State(match name {
+                "pause" => StateName::Pause,
+                "play" => StateName::Play,
+                "gameover" => StateName::GameOver,
+                "victory" => StateName::Victory,
+                _ => return Err("Invalid state: <pause|play|gameover|victory>")
+            }),
+            _ => return Err("Unknown command")
+        };
+        // trailing words are most likely a typo
+        if words.next().is_some() {
+            return Err("Too many arguments");
+        }
+        Ok(command)
+    }
+
+}
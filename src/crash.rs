@@ -0,0 +1,109 @@
+//! Crash reports written when the game panics, with the state of the run being played.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use board::{Input, Rules};
+use replay::Replay;
+
+
+/// Directory where the crash reports are written.
+const CRASH_DIRECTORY: &str = "crashes";
+
+/// Number of recent inputs listed in the crash reports.
+const RECENT_INPUTS: usize = 20;
+
+/// Run played when the game panics, saved in the crash report.
+static LAST_RUN: Mutex<Option<Replay>> = Mutex::new(None);
+
+
+/// Installs a panic hook that writes a crash report, with the run played
+/// and the given configuration, and shows a short message instead of the
+/// raw backtrace.
+pub fn install(config: String) {
+    panic::set_hook(Box::new(move |info| {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let path = PathBuf::from(CRASH_DIRECTORY).join(format!("crash-{}.txt", seconds));
+        // the panic may happen while recording the run
+        let run = match LAST_RUN.try_lock() {
+            Ok(run) => run.clone(),
+            Err(_) => None,
+        };
+        let mut report = report(info, &config, run.as_ref());
+        let mut written = fs::create_dir_all(CRASH_DIRECTORY).and_then(|_| fs::write(&path, &report));
+        // the last state is simulated once the report is safe, in case the
+        // simulation panics again
+        if let Some(replay) = &run {
+            report.push_str(&state(replay));
+            written = written.and_then(|_| fs::write(&path, &report));
+        }
+        error!("the game crashed: {}", info);
+        match written {
+            Ok(()) => eprintln!("Sorry, the game crashed! A crash report was saved to {}.", path.display()),
+            Err(_) => eprintln!("Sorry, the game crashed!\n\n{}", report),
+        };
+    }));
+}
+
+/// Records the start of a run with the given rules and seed.
+pub fn start(rules: &Rules, seed: u64) {
+    if let Ok(mut run) = LAST_RUN.lock() {
+        *run = Some(Replay { rules: rules.clone(), seed, inputs: Vec::new(), ticks: 0, score: 0 });
+    }
+}
+
+/// Records an input given to the run at the given tick.
+pub fn record_input(tick: u32, input: Input) {
+    if let Ok(mut run) = LAST_RUN.lock() {
+        if let Some(replay) = run.as_mut() {
+            replay.inputs.push((tick, input));
+        }
+    }
+}
+
+/// Records the number of ticks played and the score of the run.
+pub fn record_tick(ticks: u32, score: u32) {
+    if let Ok(mut run) = LAST_RUN.lock() {
+        if let Some(replay) = run.as_mut() {
+            replay.ticks = ticks;
+            replay.score = score;
+        }
+    }
+}
+
+/// Writes the crash report of the given panic, with the replay of the run
+/// played (if any) bringing the game back to its last state.
+fn report(info: &PanicHookInfo, config: &str, run: Option<&Replay>) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "# panic\n{}\n", info);
+    let _ = writeln!(report, "# backtrace\n{}\n", Backtrace::force_capture());
+    let _ = writeln!(report, "# config\n{}\n", config);
+    if let Some(replay) = run {
+        let _ = writeln!(report, "# replay\n{}", replay);
+        let _ = writeln!(report, "# recent inputs");
+        for (tick, input) in replay.inputs.iter().rev().take(RECENT_INPUTS).rev() {
+            let _ = writeln!(report, "{}: {:?}", tick, input);
+        }
+    }
+    report
+}
+
+/// Writes the last state of the run of the given replay, simulated again.
+fn state(replay: &Replay) -> String {
+    let board = replay.simulate();
+    let mut state = String::new();
+    let _ = writeln!(state, "\n# state");
+    let _ = writeln!(state, "tick = {}, score = {}, over = {}", board.ticks, board.score, board.over);
+    let _ = writeln!(state, "snake = {:?}", board.snake.segments().collect::<Vec<_>>());
+    let _ = writeln!(state, "direction = {:?}", board.snake.direction());
+    let _ = writeln!(state, "foods = {:?}, poison = {:?}", board.foods, board.poison);
+    let enemies: Vec<_> = board.enemies.iter().map(|enemy| enemy.position()).collect();
+    let _ = writeln!(state, "enemies = {:?}", enemies);
+    let _ = write!(state, "\n# board\n{}", board);
+    state
+}
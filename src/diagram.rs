@@ -0,0 +1,361 @@
+//! ASCII diagrams of the boards, to share the state of a run (e.g. to
+//! reproduce a bug): a line for each row of the board, a character for
+//! each cell.
+
+use std::collections::HashSet;
+
+use board::{Behavior, Board, Enemy, Portal, Snake};
+use grid::{Direction, GridPos};
+
+
+/// Characters of the cells, by what they hold.
+const EMPTY: char = '.';
+const HEAD: char = '@';
+const HEADINGS: [(char, Direction); 4] = [
+    ('<', Direction::Left), ('^', Direction::Up), ('>', Direction::Right), ('v', Direction::Down),
+];
+const SNAKE: char = 'S';
+const FOOD: char = '*';
+const POISON: char = '%';
+const MAGNET: char = 'M';
+const STAR: char = '+';
+const ENEMY: char = '#';
+const RIVAL_HEAD: char = 'r';
+const RIVAL: char = 'R';
+const PARTNER_HEAD: char = 'p';
+const PARTNER: char = 'P';
+
+/// Maximum number of cells tried following the body of a snake, before
+/// giving up on a body too tangled.
+const MAX_STEPS: u32 = 1_000_000;
+
+
+/// Draws the diagram of the given board: the snake head `@` (or `<`, `^`,
+/// `>`, `v` heading left, up, right or down) and its body `S`, the food `*`,
+/// the poison `%`, the magnet `M`, the star `+`, the enemies `#`, the
+/// portals (the two ends of each pair having the same digit, from `0`), the
+/// rival `r` and partner `p` heads and their bodies `R` and `P`, the empty
+/// cells being `.`.
+pub fn encode(board: &Board) -> String {
+    let grid = board.rules.grid;
+    let mut cells = vec![vec![EMPTY; grid.columns.max(0) as usize]; grid.rows.max(0) as usize];
+    let mut draw = |cell: GridPos, symbol: char| {
+        if let Some(row) = cells.get_mut(cell.y as usize) {
+            if let Some(c) = row.get_mut(cell.x as usize) {
+                *c = symbol;
+            }
+        }
+    };
+    // the entities drawn later are on top of the others
+    board.foods.iter().for_each(|cell| draw(*cell, FOOD));
+    board.poison.into_iter().for_each(|cell| draw(cell, POISON));
+    board.magnet.into_iter().for_each(|cell| draw(cell, MAGNET));
+    board.star.into_iter().for_each(|cell| draw(cell, STAR));
+    board.enemies.iter().for_each(|enemy| draw(enemy.position(), ENEMY));
+    let others: Vec<(&Snake, char, char)> = board.rival.iter().map(|rival| (rival, RIVAL_HEAD, RIVAL))
+        .chain(board.partner.iter().map(|partner| (partner, PARTNER_HEAD, PARTNER)))
+        .collect();
+    for (snake, _, body) in &others {
+        snake.segments().for_each(|cell| draw(*cell, *body));
+    }
+    board.snake.segments().for_each(|cell| draw(*cell, SNAKE));
+    // the portals are shown even with a snake exiting them, but under the
+    // heads
+    for (i, portal) in board.portals.iter().enumerate() {
+        let digit = std::char::from_digit(i as u32 % 10, 10).unwrap_or('0');
+        portal.positions.iter().for_each(|cell| draw(*cell, digit));
+    }
+    for (snake, head, _) in &others {
+        draw(snake.head_position(), *head);
+    }
+    let heading = HEADINGS.iter().find(|(_, direction)| board.snake.direction() == Some(*direction));
+    draw(board.snake.head_position(), heading.map_or(HEAD, |(symbol, _)| *symbol));
+    cells.into_iter().map(|row| row.into_iter().collect::<String>() + "\n").collect()
+}
+
+/// Places the entities of the given diagram on the board, which must be as
+/// large, replacing all of its own. The enemies alternate patrolling and
+/// chasing like when spawned.
+pub fn decode(board: &mut Board, diagram: &str) -> Result<(), &'static str> {
+    let grid = board.rules.grid;
+    let rows = rows(diagram);
+    if rows.len() != grid.rows as usize || rows.iter().any(|row| row.chars().count() != grid.columns as usize) {
+        return Err("The board diagram does not match the board size");
+    }
+    let (mut head, mut direction, mut body, mut foods) = (None, None, HashSet::new(), Vec::new());
+    let (mut poison, mut magnet, mut star) = (None, None, None);
+    let (mut enemies, mut ends) = (Vec::new(), vec![Vec::new(); 10]);
+    let (mut rival_head, mut rival_body, mut partner_head, mut partner_body) = (None, HashSet::new(), None, HashSet::new());
+    for (y, row) in rows.iter().enumerate() {
+        for (x, symbol) in row.chars().enumerate() {
+            let cell = GridPos::new(x as i32, y as i32);
+            let heading = HEADINGS.iter().find(|(head, _)| *head == symbol).map(|(_, direction)| *direction);
+            match symbol {
+                _ if heading.is_some() || symbol == HEAD => {
+                    if head.is_some() {
+                        return Err("The board diagram must have a single snake head");
+                    }
+                    head = Some(cell);
+                    direction = heading;
+                },
+                SNAKE => {
+                    body.insert(cell);
+                },
+                FOOD => foods.push(cell),
+                POISON => poison = Some(cell),
+                MAGNET => magnet = Some(cell),
+                STAR => star = Some(cell),
+                ENEMY => {
+                    let behavior = if enemies.len() % 2 == 0 { Behavior::Patrol } else { Behavior::Chase };
+                    enemies.push(Enemy::new(cell, behavior, grid.directions()[0]));
+                },
+                _ if symbol.is_ascii_digit() => ends[symbol as usize - '0' as usize].push(cell),
+                RIVAL_HEAD if rival_head.is_some() => return Err("The board diagram must have a single rival head"),
+                RIVAL_HEAD => rival_head = Some(cell),
+                RIVAL => {
+                    rival_body.insert(cell);
+                },
+                PARTNER_HEAD if partner_head.is_some() => return Err("The board diagram must have a single partner head"),
+                PARTNER_HEAD => partner_head = Some(cell),
+                PARTNER => {
+                    partner_body.insert(cell);
+                },
+                EMPTY => (),
+                _ => return Err("Invalid character in the board diagram")
+            };
+        }
+    }
+    if ends.iter().any(|cells| !cells.is_empty() && cells.len() != 2) {
+        return Err("The portals of the board diagram must be in pairs");
+    }
+    // the snakes are followed through the portals of the diagram
+    board.portals = ends.iter().filter(|cells| cells.len() == 2).map(|cells| Portal { positions: [cells[0], cells[1]] }).collect();
+    let portals: Vec<GridPos> = ends.into_iter().flatten().collect();
+    let head = head.ok_or("The board diagram must have a single snake head")?;
+    let segments = follow(board, head, direction, body, portals.iter().cloned().collect())?;
+    board.snake = Snake::with_segments(grid, head, &segments, direction);
+    // the other snakes keep their speed
+    let speeds = (board.rival.as_ref().map(|rival| rival.speed), board.partner.as_ref().map(|partner| partner.speed));
+    board.rival = other_snake(board, rival_head, rival_body, speeds.0, &portals)?;
+    board.partner = other_snake(board, partner_head, partner_body, speeds.1, &portals)?;
+    board.foods = foods;
+    board.poison = poison;
+    board.magnet = magnet;
+    board.star = star;
+    board.enemies = enemies;
+    Ok(())
+}
+
+/// Gets the size of the board of the given diagram: its number of columns
+/// and rows.
+pub fn size(diagram: &str) -> (i32, i32) {
+    let rows = rows(diagram);
+    let columns = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+    (columns as i32, rows.len() as i32)
+}
+
+/// Gets the rows of the given diagram, without the blank lines.
+fn rows(diagram: &str) -> Vec<&str> {
+    diagram.lines().map(str::trim).filter(|line| !line.is_empty()).collect()
+}
+
+/// Creates the rival or partner snake with the given head and body, if
+/// any, at the given speed (the default one if none).
+fn other_snake(board: &Board, head: Option<GridPos>, body: HashSet<GridPos>, speed: Option<u32>, portals: &[GridPos])
+    -> Result<Option<Snake>, &'static str>
+{
+    let head = match head {
+        Some(head) => head,
+        None if body.is_empty() => return Ok(None),
+        None => return Err("The snakes of the board diagram must have a head"),
+    };
+    let segments = follow(board, head, None, body, portals.iter().cloned().collect())?;
+    let mut snake = Snake::with_segments(board.rules.grid, head, &segments, None);
+    snake.speed = speed.unwrap_or(snake.speed);
+    Ok(Some(snake))
+}
+
+/// Follows the body of the snake from the head to the tail, through the
+/// adjacent cells (or the portals), backtracking until every cell of the
+/// body is part of it; the body may also go through the given portal
+/// cells, the snakes exiting a portal being on its cell. The first body
+/// found is taken if there are many, trying first the neck behind the head
+/// going in the given direction.
+fn follow(board: &Board, head: GridPos, direction: Option<Direction>, mut body: HashSet<GridPos>, mut portals: HashSet<GridPos>)
+    -> Result<Vec<GridPos>, &'static str>
+{
+    let grid = board.rules.grid;
+    let exit = |cell: GridPos| board.portals.iter().find_map(|portal| portal.exit(cell));
+    // the cell reached moving from the given one, through the portals
+    let advance = move |cell: GridPos, direction: Direction| {
+        let next = grid.step(cell, direction);
+        exit(next).unwrap_or(next)
+    };
+    // the cells of the body (or of the portals) the given segment may come
+    // from, those next to the entrance of the portal it is the exit of
+    let behind = |cell: GridPos, body: &HashSet<GridPos>, portals: &HashSet<GridPos>| -> Vec<GridPos> {
+        let entrance = exit(cell).unwrap_or(cell);
+        grid.directions().iter()
+            .map(|direction| grid.step(entrance, *direction))
+            .filter(|previous| body.contains(previous) || portals.contains(previous))
+            .collect()
+    };
+    // the cells of the body (or of the portals) next to the given one, the
+    // segment coming from them or going to them
+    let links = |cell: GridPos, body: &HashSet<GridPos>, portals: &HashSet<GridPos>| -> usize {
+        let mut cells: HashSet<GridPos> = behind(cell, body, portals).into_iter().collect();
+        cells.extend(grid.directions().iter()
+            .map(|direction| advance(cell, *direction))
+            .filter(|next| body.contains(next) || portals.contains(next)));
+        cells.len()
+    };
+    // the cells with the fewest links are tried first (popped from the
+    // end), the body most likely going through them right away
+    let next_cells = |cell: GridPos, body: &HashSet<GridPos>, portals: &HashSet<GridPos>| -> Vec<GridPos> {
+        let mut cells = behind(cell, body, portals);
+        cells.sort_by_key(|next| std::cmp::Reverse(links(*next, body, portals)));
+        cells
+    };
+    // the body left can still be followed from the given end only if it is
+    // all reached from the cells right behind the end, and has at most a
+    // dead end besides them (the tail being the other one)
+    let viable = |end: GridPos, body: &HashSet<GridPos>, portals: &HashSet<GridPos>| -> bool {
+        let next = behind(end, body, portals);
+        let dead_ends: Vec<GridPos> = body.iter().cloned().filter(|cell| links(*cell, body, portals) <= 1).collect();
+        if dead_ends.len() > 2 || dead_ends.iter().filter(|cell| !next.contains(cell)).count() > 1 {
+            return false;
+        }
+        let mut reached: HashSet<GridPos> = next.iter().cloned().collect();
+        let mut queue = next;
+        while let Some(cell) = queue.pop() {
+            for previous in behind(cell, body, portals) {
+                if reached.insert(previous) {
+                    queue.push(previous);
+                }
+            }
+        }
+        body.iter().all(|cell| reached.contains(cell))
+    };
+    let mut segments = Vec::new();
+    // the cells left to try after each segment (the last one first), the
+    // head first
+    let mut necks = next_cells(head, &body, &portals);
+    if let Some(direction) = direction {
+        // stable, keeping the order of the other necks
+        necks.sort_by_key(|neck| advance(*neck, direction) == head);
+    }
+    let mut candidates = vec![necks];
+    let mut steps = 0;
+    while let Some(next) = candidates.last_mut() {
+        if body.is_empty() {
+            return Ok(segments);
+        }
+        steps += 1;
+        if steps > MAX_STEPS {
+            return Err("The snake of the board diagram is too tangled");
+        }
+        match next.pop() {
+            Some(cell) => {
+                let portal = portals.remove(&cell);
+                body.remove(&cell);
+                if !viable(cell, &body, &portals) {
+                    if portal {
+                        portals.insert(cell);
+                    } else {
+                        body.insert(cell);
+                    }
+                    continue;
+                }
+                segments.push(cell);
+                candidates.push(next_cells(cell, &body, &portals));
+            },
+            // none left, back to the previous segment
+            None => {
+                candidates.pop();
+                if let Some(cell) = segments.pop() {
+                    if board.portals.iter().any(|portal| portal.contains(cell)) {
+                        portals.insert(cell);
+                    } else {
+                        body.insert(cell);
+                    }
+                }
+            },
+        }
+    }
+    Err("The snake of the board diagram must be in one piece")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::{Mode, Rules};
+    use grid::Grid;
+
+    /// Creates the board of the given diagram, in co-op mode.
+    fn board(diagram: &str) -> Board {
+        Board::from_ascii(Rules::new(Mode::Coop, Grid::new(1, 1)), 0, diagram).unwrap()
+    }
+
+    /// Gets the rows of the given diagram, as encoded.
+    fn trimmed(diagram: &str) -> String {
+        rows(diagram).iter().map(|row| row.to_string() + "\n").collect()
+    }
+
+    #[test]
+    fn empty_diagrams_are_refused() {
+        for diagram in &["", "\n", "  \n   \n"] {
+            let board = Board::from_ascii(Rules::new(Mode::Classic, Grid::new(1, 1)), 0, diagram);
+            assert_eq!(board.err(), Some("The board diagram is empty"), "{:?}", diagram);
+        }
+    }
+
+    #[test]
+    fn headings_round_trip() {
+        let diagrams = [
+            (Some(Direction::Left), ".<SS\n....\n"),
+            (Some(Direction::Up), ".^..\n.S..\n"),
+            (Some(Direction::Right), "SS>.\n....\n"),
+            (Some(Direction::Down), ".S..\n.v..\n"),
+            (None, ".@..\n....\n"),
+        ];
+        for (direction, diagram) in diagrams.iter() {
+            let board = board(diagram);
+            assert_eq!(board.snake.direction(), *direction, "\n{}", diagram);
+            assert_eq!(board.to_ascii(), *diagram);
+        }
+    }
+
+    #[test]
+    fn other_snakes_round_trip() {
+        let diagram = "
+            SSS>....
+            ........
+            rRR.PPPp
+            ..R.P...
+        ";
+        let board = board(diagram);
+        assert_eq!(board.rival.as_ref().map(|rival| rival.segments().count()), Some(4));
+        assert_eq!(board.partner.as_ref().map(|partner| partner.segments().count()), Some(5));
+        assert_eq!(board.to_ascii(), trimmed(diagram));
+    }
+
+    #[test]
+    fn portals_round_trip() {
+        // the snake went through the first pair of portals, its segment on
+        // the exit being drawn as the portal
+        let diagram = "
+            ......1.
+            SS0.....
+            ....0>..
+            .1....*.
+        ";
+        let board = board(diagram);
+        let segments: Vec<GridPos> = board.snake.segments().cloned().collect();
+        let cells = [(5, 2), (4, 2), (1, 1), (0, 1)];
+        assert_eq!(segments, cells.iter().map(|(x, y)| GridPos::new(*x, *y)).collect::<Vec<_>>());
+        assert_eq!(board.portals.len(), 2);
+        assert_eq!(board.to_ascii(), trimmed(diagram));
+    }
+
+}
@@ -0,0 +1,85 @@
+//! Adaptive difficulty: the speed and the number of enemies of the next run
+//! follow the performance of the player in the last ones, keeping the runs
+//! neither too short nor too easy.
+
+use board::{Board, Rules};
+
+
+/// Weight of the last run in the averages of the performance.
+const SMOOTHING: f32 = 0.3;
+
+/// Target band of the average number of seconds between two foods.
+const FOOD_INTERVAL_BAND: (f32, f32) = (3.0, 8.0);
+
+/// Target band of the average length of the runs (in seconds).
+const RUN_SECONDS_BAND: (f32, f32) = (30.0, 120.0);
+
+/// Highest level of the difficulty, either way.
+pub const MAX_LEVEL: i32 = 5;
+
+/// Lowest speed the difficulty slows the snake down to (in ticks per
+/// second), unless already slower.
+const MIN_TICKS_PER_SECOND: i32 = 4;
+
+
+/// Controller of the difficulty, fed by the outcome of every run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    pub level: i32,             // adjustment of the rules, from -MAX_LEVEL (easier) to MAX_LEVEL (harder)
+    pub food_interval: f32,     // average number of seconds between two foods
+    pub run_seconds: f32,       // average length of the runs (in seconds)
+}
+
+impl Default for Difficulty {
+
+    /// Creates the difficulty of a player who never played, the averages
+    /// starting in the middle of their target band.
+    fn default() -> Difficulty {
+        Difficulty {
+            level: 0,
+            food_interval: (FOOD_INTERVAL_BAND.0 + FOOD_INTERVAL_BAND.1) / 2.0,
+            run_seconds: (RUN_SECONDS_BAND.0 + RUN_SECONDS_BAND.1) / 2.0,
+        }
+    }
+
+}
+
+impl Difficulty {
+
+    /// Records the run that just ended (won if the snake filled the board),
+    /// nudging the level up if the runs are long and the food is eaten
+    /// quickly, or down if the runs are short or the food is eaten slowly.
+    /// Returns the change of the level.
+    pub fn record(&mut self, board: &Board, victory: bool) -> i32 {
+        let seconds = board.ticks as f32 / board.rules.tick_rate().max(1) as f32;
+        self.run_seconds += SMOOTHING * (seconds - self.run_seconds);
+        // the modes without food are only judged by the length of the runs
+        let has_food = board.rules.mode.variant().has_food();
+        if has_food {
+            let interval = seconds / board.food_count.max(1) as f32;
+            self.food_interval += SMOOTHING * (interval - self.food_interval);
+        }
+        let fast = !has_food || self.food_interval < FOOD_INTERVAL_BAND.0;
+        let slow = has_food && self.food_interval > FOOD_INTERVAL_BAND.1;
+        let change = if victory || (fast && self.run_seconds > RUN_SECONDS_BAND.1) {
+            1
+        } else if slow || self.run_seconds < RUN_SECONDS_BAND.0 {
+            -1
+        } else {
+            0
+        };
+        let level = (self.level + change).clamp(-MAX_LEVEL, MAX_LEVEL);
+        let change = level - self.level;
+        self.level = level;
+        change
+    }
+
+    /// Adjusts the given rules to the level: each level changes the speed by
+    /// a tick per second, and every other level adds (or removes) an enemy.
+    pub fn apply(&self, rules: &mut Rules) {
+        let speed = rules.ticks_per_second as i32;
+        rules.ticks_per_second = (speed + self.level).max(speed.min(MIN_TICKS_PER_SECOND)) as u32;
+        rules.enemy_count = (rules.enemy_count as i32 + self.level / 2).max(0) as u32;
+    }
+
+}
@@ -0,0 +1,482 @@
+//! Step by step environment over the board, to train agents (e.g. with
+//! reinforcement learning) against the exact rules of the game.
+
+use std::thread;
+
+use board::{Board, Input, Rules, TickEvent};
+use grid::{Direction, GridPos};
+use hooks::{self, GameHooks};
+
+
+/// Codes of the cells in the observations of the board.
+pub const EMPTY: u8 = 0;
+pub const BODY: u8 = 1;
+pub const HEAD: u8 = 2;
+pub const FOOD: u8 = 3;
+pub const POISON: u8 = 4;
+pub const ENEMY: u8 = 5;
+pub const PORTAL: u8 = 6;
+pub const RIVAL: u8 = 7;
+pub const PARTNER: u8 = 8;
+
+/// Number of cell codes (the planes of the one-hot observations).
+pub const CODE_COUNT: usize = 9;
+
+/// Number of values of the feature observations.
+pub const FEATURE_COUNT: usize = 12;
+
+/// Minimum number of environments stepped by each thread of a `VecEnv`,
+/// so that the threads are worth starting.
+const ENVS_PER_THREAD: usize = 64;
+
+
+/// Formats of the observations given by `Env::encode`, all made of `f32`
+/// values in row-major order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    /// Code of each cell, shaped (rows, columns).
+    Grid,
+    /// One plane per cell code, 1 where the cell has that code and 0
+    /// elsewhere, shaped (`CODE_COUNT`, rows, columns).
+    OneHot,
+    /// Codes of the cells within the given distance from the snake head,
+    /// wrapping around the edges like the snake, shaped (2 × distance + 1,
+    /// 2 × distance + 1) with the head at the center.
+    Window(u32),
+    /// For each direction (left, up, right and down), the number of steps
+    /// going straight to reach the food, the nearest obstacle (a snake
+    /// segment or an enemy) and the board edge, shaped (`FEATURE_COUNT`):
+    /// the three distances left first, then up, right and down. The food
+    /// and the obstacles are looked for over a single lap of the board,
+    /// the distance being 0 if none is met.
+    Features,
+}
+
+impl Encoding {
+
+    /// Parses the encoding from its name (`grid`, `one-hot`, `window` or
+    /// `features`), the window having the given distance.
+    pub fn from_name(name: &str, distance: u32) -> Result<Encoding, &'static str> {
+        match name {
+            "grid" => Ok(Encoding::Grid),
+            "one-hot" => Ok(Encoding::OneHot),
+            "window" => Ok(Encoding::Window(distance)),
+            "features" => Ok(Encoding::Features),
+            _ => Err("Invalid encoding: <grid|one-hot|window|features>")
+        }
+    }
+
+    /// Gets the shape of the observations on the given board.
+    pub fn shape(self, columns: i32, rows: i32) -> Vec<usize> {
+        match self {
+            Encoding::Grid => vec![rows as usize, columns as usize],
+            Encoding::OneHot => vec![CODE_COUNT, rows as usize, columns as usize],
+            Encoding::Window(distance) => vec![2 * distance as usize + 1; 2],
+            Encoding::Features => vec![FEATURE_COUNT],
+        }
+    }
+
+}
+
+
+/// Outcome of a step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub reward: f32,    // sum of the reward terms of the step
+    pub done: bool,     // whether the run is over
+}
+
+
+/// Terms of the reward of each step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RewardConfig {
+    pub food: f32,      // reward for each food eaten
+    pub poison: f32,    // reward for each poison eaten
+    pub step: f32,      // reward for each step the run goes on (usually a small penalty)
+    pub approach: f32,  // reward for each cell the head gets closer to the food (negative when moving away)
+    pub death: f32,     // reward for the game over
+    pub victory: f32,   // reward for the victory (the snake filling the board)
+}
+
+impl Default for RewardConfig {
+
+    /// Rewards of the food and of the end of the run only: 1 per food, -1
+    /// per poison, -1 for a game over and 10 for a victory.
+    fn default() -> RewardConfig {
+        RewardConfig { food: 1.0, poison: -1.0, step: 0.0, approach: 0.0, death: -1.0, victory: 10.0 }
+    }
+
+}
+
+impl RewardConfig {
+
+    /// Only the food is rewarded, 1 each.
+    pub fn sparse() -> RewardConfig {
+        RewardConfig { food: 1.0, poison: 0.0, step: 0.0, approach: 0.0, death: 0.0, victory: 0.0 }
+    }
+
+    /// The rewards of many DQN setups: 10 per food, -10 per poison and for a
+    /// game over, 100 for a victory.
+    pub fn scaled() -> RewardConfig {
+        RewardConfig { food: 10.0, poison: -10.0, step: 0.0, approach: 0.0, death: -10.0, victory: 100.0 }
+    }
+
+    /// The default rewards, along with a living penalty of -0.01 per step
+    /// and 0.1 per cell closer to the food, so that wandering is not free
+    /// and the first food is found sooner.
+    pub fn shaped() -> RewardConfig {
+        RewardConfig { step: -0.01, approach: 0.1, ..RewardConfig::default() }
+    }
+
+    /// Gets the preset with the given name (`default`, `sparse`, `scaled`
+    /// or `shaped`).
+    pub fn from_name(name: &str) -> Result<RewardConfig, &'static str> {
+        match name {
+            "default" => Ok(RewardConfig::default()),
+            "sparse" => Ok(RewardConfig::sparse()),
+            "scaled" => Ok(RewardConfig::scaled()),
+            "shaped" => Ok(RewardConfig::shaped()),
+            _ => Err("Invalid rewards: <default|sparse|scaled|shaped>")
+        }
+    }
+
+}
+
+
+/// Environment playing a run one tick per step.
+#[derive(Clone)]
+pub struct Env {
+    pub board: Board,           // board of the current run
+    pub rewards: RewardConfig,  // terms of the reward of each step
+    pub encoding: Encoding,     // format of the observations given by `encode`
+}
+
+impl Env {
+
+    /// Creates the environment of a run with the given rules, seed and
+    /// rewards, encoding the observations as a grid of cell codes.
+    pub fn new(rules: Rules, seed: u64, rewards: RewardConfig) -> Env {
+        Env { board: Board::new(rules, seed), rewards, encoding: Encoding::Grid }
+    }
+
+    /// Starts a new run with the same rules and the given seed.
+    pub fn reset(&mut self, seed: u64) {
+        self.board = Board::new(self.board.rules.clone(), seed);
+    }
+
+    /// Turns the snake in the given direction (if any, otherwise it keeps
+    /// going) and advances the board by a tick. A run over stays as is.
+    pub fn step(&mut self, direction: Option<Direction>) -> Step {
+        self.step_with_hooks(direction, &mut [])
+    }
+
+    /// Steps the environment like `step`, notifying the given hooks of the
+    /// tick.
+    pub fn step_with_hooks(&mut self, direction: Option<Direction>, hooks: &mut [Box<dyn GameHooks>]) -> Step {
+        if self.board.over {
+            return Step { reward: 0.0, done: true };
+        }
+        if let Some(direction) = direction {
+            self.board.apply(Input::Turn(direction));
+        }
+        let distance = self.food_distance();
+        let events = hooks::tick(&mut self.board, hooks);
+        let rewards = self.rewards;
+        let mut reward: f32 = events.iter()
+            .map(|event| match event {
+                TickEvent::Food => rewards.food,
+                TickEvent::Poison => rewards.poison,
+                TickEvent::GameOver => rewards.death,
+                TickEvent::Victory => rewards.victory,
+                TickEvent::RivalFood | TickEvent::TailCut | TickEvent::Magnet | TickEvent::Star => 0.0,
+            })
+            .sum();
+        if !self.board.over {
+            reward += rewards.step;
+            // the food moves once eaten (by either snake), the distance is
+            // only compared when it stays in place
+            if !events.contains(&TickEvent::Food) && !events.contains(&TickEvent::RivalFood) {
+                reward += rewards.approach * (distance - self.food_distance()) as f32;
+            }
+        }
+        Step { reward, done: self.board.over }
+    }
+
+    /// Gets the number of cells between the snake head and the closest
+    /// food, going around the edges if shorter.
+    fn food_distance(&self) -> i32 {
+        let head = self.board.snake.head_position();
+        self.board.nearest_food(head).map_or(0, |food| self.board.rules.grid.distance(head, food))
+    }
+
+    /// Writes the code of each cell of the board in the given buffer, row
+    /// by row, which must have as many elements as the cells.
+    pub fn observe(&self, cells: &mut [u8]) {
+        for cell in cells.iter_mut() {
+            *cell = EMPTY;
+        }
+        let grid = self.board.rules.grid;
+        let mut set = |position: GridPos, code: u8| {
+            cells[(position.y * grid.columns + position.x) as usize] = code;
+        };
+        for portal in &self.board.portals {
+            for position in &portal.positions {
+                set(*position, PORTAL);
+            }
+        }
+        // there is no food in some modes (e.g. Tron)
+        if self.board.rules.mode.variant().has_food() {
+            for food in &self.board.foods {
+                set(*food, FOOD);
+            }
+            if let Some(poison) = self.board.poison {
+                set(poison, POISON);
+            }
+        }
+        for enemy in &self.board.enemies {
+            set(enemy.position(), ENEMY);
+        }
+        for (snake, code) in self.board.rival.iter().map(|rival| (rival, RIVAL))
+            .chain(self.board.partner.iter().map(|partner| (partner, PARTNER)))
+        {
+            for segment in snake.segments() {
+                set(*segment, code);
+            }
+        }
+        for segment in self.board.snake.segments() {
+            set(*segment, BODY);
+        }
+        set(self.board.snake.head_position(), HEAD);
+    }
+
+    /// Gets the code of each cell of the board, row by row.
+    pub fn observation(&self) -> Vec<u8> {
+        let mut cells = vec![EMPTY; self.board.rules.grid.cell_count()];
+        self.observe(&mut cells);
+        cells
+    }
+
+    /// Gets the observation of the board in the format of the environment
+    /// encoding.
+    pub fn encode(&self) -> Vec<f32> {
+        let grid = self.board.rules.grid;
+        let cells = self.observation();
+        let code = |position: GridPos| cells[(position.y * grid.columns + position.x) as usize];
+        let head = self.board.snake.head_position();
+        match self.encoding {
+            Encoding::Grid => cells.iter().map(|code| f32::from(*code)).collect(),
+            Encoding::OneHot => {
+                let mut planes = vec![0.0; CODE_COUNT * cells.len()];
+                for (index, code) in cells.iter().enumerate() {
+                    planes[*code as usize * cells.len() + index] = 1.0;
+                }
+                planes
+            },
+            Encoding::Window(distance) => {
+                let distance = distance as i32;
+                let mut window = Vec::with_capacity(((2 * distance + 1) * (2 * distance + 1)) as usize);
+                for y in -distance..=distance {
+                    for x in -distance..=distance {
+                        window.push(f32::from(code(grid.wrap(GridPos::new(head.x + x, head.y + y)))));
+                    }
+                }
+                window
+            },
+            Encoding::Features => {
+                let mut features = Vec::with_capacity(FEATURE_COUNT);
+                // the cells ahead until back to the head, and the edge
+                let directions = [
+                    (Direction::Left, grid.columns, head.x + 1),
+                    (Direction::Up, grid.rows, head.y + 1),
+                    (Direction::Right, grid.columns, grid.columns - head.x),
+                    (Direction::Down, grid.rows, grid.rows - head.y),
+                ];
+                for (direction, lap, edge) in directions.iter().cloned() {
+                    let (mut food, mut obstacle) = (0, 0);
+                    let mut position = head;
+                    for steps in 1..=lap {
+                        position = grid.step(position, direction);
+                        match code(position) {
+                            FOOD if food == 0 => food = steps,
+                            BODY | ENEMY | RIVAL | PARTNER if obstacle == 0 => obstacle = steps,
+                            _ => ()
+                        };
+                    }
+                    features.extend_from_slice(&[food as f32, obstacle as f32, edge as f32]);
+                }
+                features
+            },
+        }
+    }
+
+}
+
+
+/// Independent environments stepped together, in parallel on the available
+/// cores. Each environment starts a new run as soon as one is over, with
+/// its seed increased by the number of environments (so that the seeds of
+/// the environments never overlap when the first ones are consecutive).
+#[derive(Clone)]
+pub struct VecEnv {
+    pub envs: Vec<Env>, // environments, stepped together
+}
+
+impl VecEnv {
+
+    /// Creates an environment for each of the given seeds, with the same
+    /// rules, rewards and encoding.
+    pub fn new(rules: Rules, seeds: &[u64], rewards: RewardConfig, encoding: Encoding) -> VecEnv {
+        let envs = seeds.iter()
+            .map(|seed| Env { encoding, ..Env::new(rules.clone(), *seed, rewards) })
+            .collect();
+        VecEnv { envs }
+    }
+
+    /// Starts a new run in every environment, with the given seeds.
+    pub fn reset(&mut self, seeds: &[u64]) {
+        for (env, seed) in self.envs.iter_mut().zip(seeds) {
+            env.reset(*seed);
+        }
+    }
+
+    /// Steps every environment with its direction (one for each of them),
+    /// returning the outcomes. The environments whose run is over then
+    /// start a new one, the outcome of the last step being the one of the
+    /// run over.
+    pub fn step(&mut self, directions: &[Option<Direction>]) -> Vec<Step> {
+        assert_eq!(directions.len(), self.envs.len(), "a direction is needed for each environment");
+        let count = self.envs.len() as u64;
+        let step = |envs: &mut [Env], directions: &[Option<Direction>]| -> Vec<Step> {
+            envs.iter_mut().zip(directions)
+                .map(|(env, direction)| {
+                    let step = env.step(*direction);
+                    if step.done {
+                        let seed = env.board.seed.wrapping_add(count);
+                        env.reset(seed);
+                    }
+                    step
+                })
+                .collect()
+        };
+        let size = self.chunk_size();
+        if size >= self.envs.len() {
+            return step(&mut self.envs, directions);
+        }
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.envs.chunks_mut(size).zip(directions.chunks(size))
+                .map(|(envs, directions)| scope.spawn(move || step(envs, directions)))
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+
+    /// Gets the observations of all the environments, one after the other
+    /// (shaped like the ones of a single environment, with the environments
+    /// as the first dimension).
+    pub fn encode(&self) -> Vec<f32> {
+        let size = self.chunk_size();
+        if size >= self.envs.len() {
+            return self.envs.iter().flat_map(Env::encode).collect();
+        }
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.envs.chunks(size)
+                .map(|envs| scope.spawn(move || envs.iter().flat_map(Env::encode).collect::<Vec<f32>>()))
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+
+    /// Gets the number of environments handled by each thread.
+    fn chunk_size(&self) -> usize {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let threads = threads.min(self.envs.len() / ENVS_PER_THREAD).max(1);
+        self.envs.len().div_ceil(threads)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::Mode;
+    use grid::Grid;
+
+    /// Board with every entity: the snake heading right with its body on
+    /// the left, the food, the poison, an enemy, a pair of portals, and the
+    /// rival and partner snakes.
+    const DIAGRAM: &str = "
+        .*...0
+        SS>...
+        #.%.0.
+        rR.Pp.
+    ";
+
+    fn env(encoding: Encoding) -> Env {
+        let board = Board::from_ascii(Rules::new(Mode::Classic, Grid::new(6, 4)), 0, DIAGRAM).unwrap();
+        Env { board, rewards: RewardConfig::default(), encoding }
+    }
+
+    #[test]
+    fn observations_have_the_shape_of_their_encoding() {
+        let shapes = [
+            (Encoding::Grid, vec![4, 6]),
+            (Encoding::OneHot, vec![CODE_COUNT, 4, 6]),
+            (Encoding::Window(2), vec![5, 5]),
+            (Encoding::Features, vec![FEATURE_COUNT]),
+        ];
+        for (encoding, shape) in shapes.iter().cloned() {
+            assert_eq!(encoding.shape(6, 4), shape, "{:?}", encoding);
+            assert_eq!(env(encoding).encode().len(), shape.iter().product::<usize>(), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn entities_are_encoded_in_their_channel() {
+        let codes = [
+            (1, 0, FOOD), (5, 0, PORTAL),
+            (0, 1, BODY), (1, 1, BODY), (2, 1, HEAD),
+            (0, 2, ENEMY), (2, 2, POISON), (4, 2, PORTAL),
+            (0, 3, RIVAL), (1, 3, RIVAL), (3, 3, PARTNER), (4, 3, PARTNER),
+        ];
+        let grid = env(Encoding::Grid).encode();
+        let planes = env(Encoding::OneHot).encode();
+        let cells = 6 * 4;
+        for y in 0..4 {
+            for x in 0..6 {
+                let code = codes.iter().find(|(column, row, _)| (*column, *row) == (x, y)).map_or(EMPTY, |(_, _, code)| *code);
+                let index = y * 6 + x;
+                assert_eq!(grid[index], f32::from(code), "cell ({}, {})", x, y);
+                // a single plane is set for each cell, the one of its code
+                for plane in 0..CODE_COUNT {
+                    let expected = if plane == code as usize { 1.0 } else { 0.0 };
+                    assert_eq!(planes[plane * cells + index], expected, "cell ({}, {}), plane {}", x, y, plane);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn features_are_ordered_by_direction() {
+        // left, up, right and down: the food, the nearest obstacle and the
+        // edge, the poison and the portals being neither
+        let mut env = env(Encoding::Features);
+        assert_eq!(env.encode(), [0.0, 1.0, 3.0, 0.0, 0.0, 2.0, 0.0, 4.0, 4.0, 0.0, 0.0, 3.0]);
+        // the food is met going either way around the row
+        env.board.foods = vec![GridPos::new(4, 1)];
+        assert_eq!(env.encode(), [4.0, 1.0, 3.0, 0.0, 0.0, 2.0, 2.0, 4.0, 4.0, 0.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn windows_are_centered_on_the_head() {
+        let window = env(Encoding::Window(1)).encode();
+        let codes = [FOOD, EMPTY, EMPTY, BODY, HEAD, EMPTY, EMPTY, POISON, EMPTY];
+        assert_eq!(window, codes.iter().map(|code| f32::from(*code)).collect::<Vec<f32>>());
+        // the window wraps around the edges like the snake, its first row
+        // being the last one of the board
+        let window = env(Encoding::Window(2)).encode();
+        let codes = [RIVAL, RIVAL, EMPTY, PARTNER, PARTNER];
+        assert_eq!(window[..5], codes.iter().map(|code| f32::from(*code)).collect::<Vec<f32>>()[..]);
+    }
+
+}
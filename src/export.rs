@@ -0,0 +1,153 @@
+//! Export of the replays as animated GIFs, rendered without a window.
+
+use gif::{Encoder, Frame, Repeat};
+use sfml::graphics::Color;
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use board::Board;
+use grid::{GridPos, Topology};
+use replay::Replay;
+
+
+/// Size of each cell in the exported animation (in pixels).
+const CELL_SIZE: usize = 8;
+
+/// Indices of the colors in the animation palette.
+const BACKGROUND: u8 = 0;
+const OUTLINE: u8 = 1;
+const SNAKE: u8 = 2;
+const FOOD: u8 = 3;
+const POISON: u8 = 4;
+const ENEMY: u8 = 5;
+const PORTAL: u8 = 6;
+const RIVAL: u8 = 10;
+const PARTNER: u8 = 11;
+
+/// Colors of the animation palette, the same of the game window.
+const PALETTE: [Color; 12] = [
+    Color { r: 122, g: 122, b: 122, a: 255 },
+    Color::BLACK,
+    Color::GREEN,
+    Color::RED,
+    Color { r: 128, g: 0, b: 128, a: 255 },
+    Color::BLUE,
+    Color::CYAN,
+    Color::MAGENTA,
+    Color::YELLOW,
+    Color::WHITE,
+    Color { r: 255, g: 128, b: 0, a: 255 },
+    Color { r: 0, g: 160, b: 255, a: 255 },
+];
+
+
+/// Single frame of the animation, each pixel is a palette index.
+struct Canvas {
+    pixels: Vec<u8>,    // palette index of each pixel, row by row
+    width: usize,       // width in pixels
+    hex: bool,          // whether the odd rows are shifted right by half a cell (hex boards)
+}
+
+impl Canvas {
+
+    /// Fills the given cell with a thin outline.
+    fn fill_cell(&mut self, cell: GridPos, color: u8) {
+        self.fill_square(cell, 0, OUTLINE);
+        self.fill_square(cell, 1, color);
+    }
+
+    /// Draws a square ring on the given cell.
+    fn ring_cell(&mut self, cell: GridPos, color: u8) {
+        self.fill_square(cell, 0, color);
+        self.fill_square(cell, CELL_SIZE / 4, OUTLINE);
+    }
+
+    /// Fills the given cell, leaving `inset` pixels on each side.
+    fn fill_square(&mut self, cell: GridPos, inset: usize, color: u8) {
+        let shift = if self.hex && cell.y % 2 == 1 { CELL_SIZE / 2 } else { 0 };
+        let left = cell.x as usize * CELL_SIZE + shift;
+        let top = cell.y as usize * CELL_SIZE;
+        for y in top + inset..top + CELL_SIZE - inset {
+            let row = y * self.width;
+            for pixel in &mut self.pixels[row + left + inset..row + left + CELL_SIZE - inset] {
+                *pixel = color;
+            }
+        }
+    }
+
+    /// Draws all the entities of the board, like the game window.
+    fn draw(&mut self, board: &Board) {
+        for pixel in &mut self.pixels {
+            *pixel = BACKGROUND;
+        }
+        for (i, portal) in board.portals.iter().enumerate() {
+            for position in &portal.positions {
+                self.ring_cell(*position, PORTAL + (i % 4) as u8);
+            }
+        }
+        if board.rules.mode.variant().has_food() {
+            for food in &board.foods {
+                self.fill_cell(*food, FOOD);
+            }
+            if let Some(poison) = board.poison {
+                self.fill_cell(poison, POISON);
+            }
+        }
+        for enemy in &board.enemies {
+            self.fill_cell(enemy.position(), ENEMY);
+        }
+        if let Some(rival) = &board.rival {
+            for segment in rival.segments() {
+                self.fill_cell(*segment, RIVAL);
+            }
+        }
+        if let Some(partner) = &board.partner {
+            for segment in partner.segments() {
+                self.fill_cell(*segment, PARTNER);
+            }
+        }
+        for segment in board.snake.segments() {
+            self.fill_cell(*segment, SNAKE);
+        }
+    }
+
+}
+
+
+/// Renders the whole run of the given replay as an animated GIF, one frame
+/// per tick, without opening any window.
+pub fn write_gif(replay: &Replay, path: &Path) -> Result<(), Box<dyn Error>> {
+    let grid = replay.rules.grid;
+    // the hex cells are drawn as squares, the odd rows shifted like the
+    // game ones
+    let hex = grid.topology == Topology::Hex;
+    let width = grid.columns as usize * CELL_SIZE + if hex { CELL_SIZE / 2 } else { 0 };
+    let height = grid.rows as usize * CELL_SIZE;
+    if width > usize::from(u16::MAX) || height > usize::from(u16::MAX) {
+        return Err("The board is too large to be exported".into());
+    }
+    let palette: Vec<u8> = PALETTE.iter().flat_map(|color| vec![color.r, color.g, color.b]).collect();
+    let mut encoder = Encoder::new(File::create(path)?, width as u16, height as u16, &palette)?;
+    encoder.set_repeat(Repeat::Infinite)?;
+    // each frame lasts a tick (in hundredths of a second)
+    let delay = (100 / replay.rules.tick_rate().max(1)).max(1) as u16;
+    let mut canvas = Canvas { pixels: vec![BACKGROUND; width * height], width, hex };
+    let mut result = Ok(());
+    replay.simulate_with(|board| {
+        if result.is_ok() {
+            canvas.draw(board);
+            let frame = Frame {
+                width: width as u16,
+                height: height as u16,
+                delay,
+                buffer: Cow::Borrowed(&canvas.pixels),
+                ..Frame::default()
+            };
+            result = encoder.write_frame(&frame);
+        }
+    });
+    Ok(result?)
+}
@@ -0,0 +1,185 @@
+//! C API of the step by step environment, for frontends written in other
+//! languages (the header is generated in `include/snake.h`).
+//!
+//! A game is created with `snake_create`, played with `snake_step` and
+//! freed with `snake_free`; the board is read with `snake_info` and
+//! `snake_observe`, which writes the code of each cell row by row.
+
+use std::convert::TryFrom;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use board::{Mode, Rules};
+use env::{Env, RewardConfig};
+use grid::{Direction, Grid};
+use replay::MAX_CELLS;
+
+
+/// Game played through the C API (opaque to C).
+pub struct SnakeGame {
+    env: Env,   // environment of the current run
+}
+
+
+/// Codes of the cells written by `snake_observe`, the ones of the `env`
+/// module (written as literals for the C header).
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnakeCell {
+    Empty = 0,
+    Body = 1,
+    Head = 2,
+    Food = 3,
+    Poison = 4,
+    Enemy = 5,
+    Portal = 6,
+    Rival = 7,
+    Partner = 8,
+}
+
+
+/// Outcome of a step.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnakeStep {
+    pub reward: f32,    // 1 per food eaten, -1 per poison eaten, -1 for a game over, 10 for a victory
+    pub done: bool,     // whether the run is over
+}
+
+
+/// State of the current run.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnakeInfo {
+    pub columns: i32,   // number of board columns
+    pub rows: i32,      // number of board rows
+    pub score: u32,     // current score
+    pub ticks: u32,     // number of ticks played
+    pub length: u32,    // number of snake segments
+    pub over: bool,     // whether the run is over
+}
+
+
+/// Creates a game on a board of the given size, in the given mode (0
+/// classic, 1 tron, 2 versus, 3 co-op, 4 survival, 5 time attack), starting
+/// the first run with the given seed. Returns null if the options are invalid
+/// (or the board has more than 1,048,576 cells).
+#[no_mangle]
+pub extern "C" fn snake_create(columns: i32, rows: i32, mode: u32, seed: u64) -> *mut SnakeGame {
+    guard(ptr::null_mut(), || create(columns, rows, mode, seed))
+}
+
+/// Creates a game, as `snake_create` does.
+fn create(columns: i32, rows: i32, mode: u32, seed: u64) -> *mut SnakeGame {
+    let mode = match mode {
+        0 => Mode::Classic,
+        1 => Mode::Tron,
+        2 => Mode::Versus,
+        3 => Mode::Coop,
+        4 => Mode::Survival,
+        5 => Mode::TimeAttack,
+        _ => return ptr::null_mut()
+    };
+    if columns <= 0 || rows <= 0 || i64::from(columns) * i64::from(rows) > MAX_CELLS {
+        return ptr::null_mut();
+    }
+    let env = Env::new(Rules::new(mode, Grid::new(columns, rows)), seed, RewardConfig::default());
+    Box::into_raw(Box::new(SnakeGame { env }))
+}
+
+/// Starts a new run with the same options and the given seed.
+///
+/// # Safety
+///
+/// `game` must be a game created by `snake_create` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn snake_reset(game: *mut SnakeGame, seed: u64) {
+    if let Some(game) = game.as_mut() {
+        guard((), || game.env.reset(seed));
+    }
+}
+
+/// Turns the snake in the given direction (0 left, 1 up, 2 right, 3 down,
+/// any other value to keep going) and plays a tick.
+///
+/// # Safety
+///
+/// `game` must be a game created by `snake_create` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn snake_step(game: *mut SnakeGame, direction: i32) -> SnakeStep {
+    match game.as_mut() {
+        Some(game) => {
+            let direction = usize::try_from(direction).ok().and_then(|index| Direction::all().get(index).cloned());
+            let over = SnakeStep { reward: 0.0, done: true };
+            guard(over, || {
+                let step = game.env.step(direction);
+                SnakeStep { reward: step.reward, done: step.done }
+            })
+        },
+        None => SnakeStep { reward: 0.0, done: true },
+    }
+}
+
+/// Gets the state of the current run.
+///
+/// # Safety
+///
+/// `game` must be a game created by `snake_create` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn snake_info(game: *const SnakeGame) -> SnakeInfo {
+    match game.as_ref() {
+        Some(game) => {
+            let board = &game.env.board;
+            SnakeInfo {
+                columns: board.rules.grid.columns,
+                rows: board.rules.grid.rows,
+                score: board.score,
+                ticks: board.ticks,
+                length: board.snake.segments().len() as u32,
+                over: board.over,
+            }
+        },
+        None => SnakeInfo { columns: 0, rows: 0, score: 0, ticks: 0, length: 0, over: true },
+    }
+}
+
+/// Writes the code of each cell of the board in the given buffer, row by
+/// row (one of the `SnakeCell` codes). Returns the number of cells,
+/// nothing is written if the buffer is shorter.
+///
+/// # Safety
+///
+/// `game` must be a game created by `snake_create` and not freed yet, and
+/// `cells` must point to at least `length` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn snake_observe(game: *const SnakeGame, cells: *mut u8, length: usize) -> usize {
+    let game = match game.as_ref() {
+        Some(game) => game,
+        None => return 0
+    };
+    let count = game.env.board.rules.grid.cell_count();
+    if !cells.is_null() && length >= count {
+        let cells = std::slice::from_raw_parts_mut(cells, count);
+        guard((), || game.env.observe(cells));
+    }
+    count
+}
+
+/// Frees a game.
+///
+/// # Safety
+///
+/// `game` must be a game created by `snake_create` and not freed yet (or
+/// null), it cannot be used anymore.
+#[no_mangle]
+pub unsafe extern "C" fn snake_free(game: *mut SnakeGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Calls the given function, returning the fallback value instead if it
+/// panics: the panics cannot unwind into the frames of the C caller.
+fn guard<T, F: FnOnce() -> T>(fallback: T, function: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(function)).unwrap_or(fallback)
+}
@@ -0,0 +1,379 @@
+//! Cells, directions and wrapping of the game grid, made of square or
+//! hexagonal cells.
+
+use std::collections::HashMap;
+use std::slice::Iter;
+
+
+/// A cell of the game grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GridPos {
+    pub x: i32,     // column index
+    pub y: i32,     // row index
+}
+
+impl GridPos {
+
+    /// Creates a new grid position.
+    pub fn new(x: i32, y: i32) -> GridPos {
+        GridPos { x, y }
+    }
+
+}
+
+
+/// Shape of the cells of the grid, which gives the directions a snake can
+/// move in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Topology {
+    #[default]
+    Square,     // square cells, moving in 4 directions
+    Diagonal,   // square cells, moving in 8 directions (the diagonal ones too)
+    Hex,        // hexagonal cells (pointy side up, odd rows shifted right by half a cell), moving in 6 directions
+}
+
+impl Topology {
+
+    /// Parses a topology from its name.
+    pub fn from_name(name: &str) -> Result<Topology, &'static str> {
+        match name {
+            "square" => Ok(Topology::Square),
+            "diagonal" => Ok(Topology::Diagonal),
+            "hex" => Ok(Topology::Hex),
+            _ => Err("Invalid topology: <square|diagonal|hex>")
+        }
+    }
+
+    /// Gets the name of the topology.
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Square => "square",
+            Topology::Diagonal => "diagonal",
+            Topology::Hex => "hex",
+        }
+    }
+
+}
+
+
+/// The game grid, implemented as a Toroid: leaving one edge of the grid
+/// brings back to the opposite edge.
+/// https://en.wikipedia.org/wiki/Toroid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub columns: i32,           // number of columns
+    pub rows: i32,              // number of rows (even on hex grids)
+    pub topology: Topology,     // shape of the cells
+}
+
+impl Grid {
+
+    /// Creates a new grid of square cells with the given number of columns
+    /// and rows.
+    pub fn new(columns: i32, rows: i32) -> Grid {
+        Grid::with_topology(columns, rows, Topology::Square)
+    }
+
+    /// Creates a new grid of cells of the given shape. The rows of a hex
+    /// grid are rounded up to an even number, for the shifted rows to line
+    /// up across the top and bottom edges.
+    pub fn with_topology(columns: i32, rows: i32, topology: Topology) -> Grid {
+        let rows = match topology {
+            Topology::Square | Topology::Diagonal => rows,
+            Topology::Hex => rows + rows.rem_euclid(2),
+        };
+        Grid { columns, rows, topology }
+    }
+
+    /// Gets the directions a snake can move in.
+    pub fn directions(&self) -> &'static [Direction] {
+        match self.topology {
+            Topology::Square => &SQUARE_DIRECTIONS,
+            Topology::Diagonal => &DIAGONAL_DIRECTIONS,
+            Topology::Hex => &HEX_DIRECTIONS,
+        }
+    }
+
+    /// Gets the total number of cells.
+    pub fn cell_count(&self) -> usize {
+        self.columns.max(0) as usize * self.rows.max(0) as usize
+    }
+
+    /// Wraps the given position around the grid edges.
+    pub fn wrap(&self, position: GridPos) -> GridPos {
+        GridPos::new(position.x.rem_euclid(self.columns), position.y.rem_euclid(self.rows))
+    }
+
+    /// Gets the position one step away from the given one in the given direction.
+    pub fn step(&self, position: GridPos, direction: Direction) -> GridPos {
+        let (dx, dy) = match self.topology {
+            Topology::Square | Topology::Diagonal => direction.delta(),
+            // the diagonal neighbours of the odd rows are half a cell further right
+            Topology::Hex => {
+                let odd = position.y & 1;
+                match direction {
+                    Direction::UpLeft => (odd - 1, -1),
+                    Direction::UpRight => (odd, -1),
+                    Direction::DownLeft => (odd - 1, 1),
+                    Direction::DownRight => (odd, 1),
+                    direction => direction.delta(),
+                }
+            },
+        };
+        self.wrap(GridPos::new(position.x + dx, position.y + dy))
+    }
+
+    /// Gets the shortest signed offset (columns, rows) to go from a position
+    /// to another one, taking into account the wrap around the edges.
+    pub fn offset(&self, from: GridPos, to: GridPos) -> (i32, i32) {
+        let shortest = |delta: i32, length: i32| {
+            let delta = delta.rem_euclid(length);
+            if delta > length / 2 { delta - length } else { delta }
+        };
+        (shortest(to.x - from.x, self.columns), shortest(to.y - from.y, self.rows))
+    }
+
+    /// Gets the smallest number of steps to go from a position to another
+    /// one, taking into account the wrap around the edges.
+    pub fn distance(&self, from: GridPos, to: GridPos) -> i32 {
+        match self.topology {
+            Topology::Square => {
+                let (dx, dy) = self.offset(from, to);
+                dx.abs() + dy.abs()
+            },
+            // a diagonal step changes both the column and the row
+            Topology::Diagonal => self.ring(from, to),
+            Topology::Hex => self.hex_distance(from, to),
+        }
+    }
+
+    /// Gets the index of the ring of cells around a position containing
+    /// another one, taking into account the wrap around the edges: the
+    /// largest offset on square grids, the distance on hex grids.
+    pub fn ring(&self, from: GridPos, to: GridPos) -> i32 {
+        match self.topology {
+            Topology::Square | Topology::Diagonal => {
+                let (dx, dy) = self.offset(from, to);
+                dx.abs().max(dy.abs())
+            },
+            Topology::Hex => self.hex_distance(from, to),
+        }
+    }
+
+    /// Gets the distance between two cells of a hex grid, the shortest one
+    /// to any copy of the target around the board.
+    /// https://www.redblobgames.com/grids/hexagons/#distances
+    fn hex_distance(&self, from: GridPos, to: GridPos) -> i32 {
+        // axial coordinates, where the columns are slanted along the rows
+        let axial = |position: GridPos| (position.x - (position.y - (position.y & 1)) / 2, position.y);
+        let (q, r) = axial(from);
+        let mut shortest = i32::MAX;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // the rows being even, the copies keep the parity of the row
+                let (tq, tr) = axial(GridPos::new(to.x + dx * self.columns, to.y + dy * self.rows));
+                let (dq, dr) = (tq - q, tr - r);
+                shortest = shortest.min((dq.abs() + dr.abs() + (dq + dr).abs()) / 2);
+            }
+        }
+        shortest
+    }
+
+}
+
+
+/// Enumerates all possible snake directions: the first four on square
+/// grids, all of them when moving diagonally too, left, right and the
+/// diagonal ones on hex grids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+/// Directions of the snakes on square grids.
+const SQUARE_DIRECTIONS: [Direction; 4] = [Direction::Left, Direction::Up, Direction::Right, Direction::Down];
+
+/// Directions of the snakes on square grids moving diagonally too,
+/// clockwise from the left.
+const DIAGONAL_DIRECTIONS: [Direction; 8] = [
+    Direction::Left, Direction::UpLeft, Direction::Up, Direction::UpRight,
+    Direction::Right, Direction::DownRight, Direction::Down, Direction::DownLeft,
+];
+
+/// Directions of the snakes on hex grids, clockwise from the left.
+const HEX_DIRECTIONS: [Direction; 6] = [
+    Direction::Left, Direction::UpLeft, Direction::UpRight, Direction::Right, Direction::DownRight, Direction::DownLeft,
+];
+
+impl Direction {
+
+    /// Parses a direction from its name.
+    pub fn from_name(name: &str) -> Result<Direction, &'static str> {
+        match name {
+            "left" => Ok(Direction::Left),
+            "up" => Ok(Direction::Up),
+            "right" => Ok(Direction::Right),
+            "down" => Ok(Direction::Down),
+            "up-left" => Ok(Direction::UpLeft),
+            "up-right" => Ok(Direction::UpRight),
+            "down-left" => Ok(Direction::DownLeft),
+            "down-right" => Ok(Direction::DownRight),
+            _ => Err("Invalid direction: <left|up|right|down|up-left|up-right|down-left|down-right>")
+        }
+    }
+
+    /// Gets the name of the direction.
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Left => "left",
+            Direction::Up => "up",
+            Direction::Right => "right",
+            Direction::Down => "down",
+            Direction::UpLeft => "up-left",
+            Direction::UpRight => "up-right",
+            Direction::DownLeft => "down-left",
+            Direction::DownRight => "down-right",
+        }
+    }
+
+    /// Gets all the directions of the square grids.
+    pub fn all() -> [Direction; 4] {
+        SQUARE_DIRECTIONS
+    }
+
+    /// Gets the opposite direction.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+
+    /// Gets the (columns, rows) offset of a single step in this direction on
+    /// a square grid (see `Grid::step` for the hex grids).
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+
+    /// Gets the direction mirrored horizontally (left and right swapped).
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::UpLeft => Direction::UpRight,
+            Direction::UpRight => Direction::UpLeft,
+            Direction::DownLeft => Direction::DownRight,
+            Direction::DownRight => Direction::DownLeft,
+            direction => direction,
+        }
+    }
+
+    /// Gets the direction mirrored vertically (up and down swapped).
+    pub fn inverted(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::UpLeft => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpLeft,
+            Direction::UpRight => Direction::DownRight,
+            Direction::DownRight => Direction::UpRight,
+            direction => direction,
+        }
+    }
+
+    /// Returns true only if the self direction is opposite to
+    /// the give one.
+    pub fn is_opposite_to(&self, other: &Option<Direction>) -> bool {
+        match other {
+            Some(direction) => self.opposite() == *direction,
+            None => false
+        }
+    }
+
+}
+
+
+/// Set of grid cells supporting constant time insertion, removal and
+/// uniform sampling.
+#[derive(Clone)]
+pub struct FreeCells {
+    cells: Vec<GridPos>,                // cells in the set
+    indices: HashMap<GridPos, usize>,   // index of each cell in `cells`
+}
+
+impl FreeCells {
+
+    /// Creates a new set containing all the cells of the given grid.
+    pub fn new(grid: Grid) -> FreeCells {
+        let mut cells = Vec::with_capacity(grid.cell_count());
+        for y in 0..grid.rows {
+            for x in 0..grid.columns {
+                cells.push(GridPos::new(x, y));
+            }
+        }
+        let indices = cells.iter().enumerate().map(|(i, cell)| (*cell, i)).collect();
+        FreeCells { cells, indices }
+    }
+
+    /// Gets the number of cells in the set.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns true if the set contains no cells.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Gets the cell at the given index.
+    pub fn get(&self, index: usize) -> GridPos {
+        self.cells[index]
+    }
+
+    /// Gets an iterator over the cells of the set.
+    pub fn iter(&self) -> Iter<'_, GridPos> {
+        self.cells.iter()
+    }
+
+    /// Adds the given cell to the set.
+    pub fn insert(&mut self, cell: GridPos) {
+        if !self.indices.contains_key(&cell) {
+            self.indices.insert(cell, self.cells.len());
+            self.cells.push(cell);
+        }
+    }
+
+    /// Removes the given cell from the set.
+    pub fn remove(&mut self, cell: GridPos) {
+        if let Some(index) = self.indices.remove(&cell) {
+            // move the last cell in place of the removed one
+            self.cells.swap_remove(index);
+            if let Some(moved) = self.cells.get(index) {
+                self.indices.insert(*moved, index);
+            }
+        }
+    }
+
+}
@@ -0,0 +1,107 @@
+//! Hooks of the game: callbacks of the applications embedding it, to react
+//! to what happens during the runs without changing the game loop.
+
+use board::{Board, TickEvent};
+
+
+/// Callbacks notified of the ticks of the runs, each one doing nothing
+/// unless overridden. They are called once the board has ticked, in the
+/// order of the events, `on_tick` last.
+pub trait GameHooks {
+
+    /// Called after every tick of the board.
+    fn on_tick(&mut self, _board: &Board) {}
+
+    /// Called when the snake of a player eats the food (`TickEvent::Food`)
+    /// or the poison (`TickEvent::Poison`).
+    fn on_eat(&mut self, _board: &Board, _event: TickEvent) {}
+
+    /// Called when the snake of the player dies, ending the run.
+    fn on_death(&mut self, _board: &Board) {}
+
+    /// Called when the snake of the player fills the whole board, winning
+    /// the run.
+    fn on_victory(&mut self, _board: &Board) {}
+
+    /// Called when the score of the player changes during a tick, given the
+    /// score before it.
+    fn on_score_change(&mut self, _board: &Board, _previous: u32) {}
+
+}
+
+
+/// Plays a tick of the board, notifying the given hooks of it. Returns the
+/// events of the tick.
+pub fn tick(board: &mut Board, hooks: &mut [Box<dyn GameHooks>]) -> Vec<TickEvent> {
+    let score = board.score;
+    let events = board.tick();
+    notify(hooks, board, &events, score);
+    events
+}
+
+/// Notifies the given hooks of the tick the board just played, given the
+/// events of the tick and the score before it.
+pub fn notify(hooks: &mut [Box<dyn GameHooks>], board: &Board, events: &[TickEvent], previous_score: u32) {
+    for hook in hooks {
+        for event in events {
+            match event {
+                TickEvent::Food | TickEvent::Poison => hook.on_eat(board, *event),
+                TickEvent::GameOver => hook.on_death(board),
+                TickEvent::Victory => hook.on_victory(board),
+                _ => ()
+            };
+        }
+        if board.score != previous_score {
+            hook.on_score_change(board, previous_score);
+        }
+        hook.on_tick(board);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use board::{Mode, Rules};
+    use grid::Grid;
+
+    /// Hook recording the callbacks called, by name.
+    struct Recorder(Rc<RefCell<Vec<&'static str>>>);
+
+    impl GameHooks for Recorder {
+        fn on_tick(&mut self, _board: &Board) {
+            self.0.borrow_mut().push("tick");
+        }
+
+        fn on_eat(&mut self, _board: &Board, _event: TickEvent) {
+            self.0.borrow_mut().push("eat");
+        }
+
+        fn on_death(&mut self, _board: &Board) {
+            self.0.borrow_mut().push("death");
+        }
+
+        fn on_victory(&mut self, _board: &Board) {
+            self.0.borrow_mut().push("victory");
+        }
+
+        fn on_score_change(&mut self, _board: &Board, _previous: u32) {
+            self.0.borrow_mut().push("score");
+        }
+    }
+
+    #[test]
+    fn ticks_are_notified_in_order() {
+        // the snake eats the food, then fills the board
+        let mut board = Board::from_ascii(Rules::new(Mode::Classic, Grid::new(1, 1)), 0, "S>*").unwrap();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let mut hooks: Vec<Box<dyn GameHooks>> = vec![Box::new(Recorder(calls.clone()))];
+        while !board.over {
+            tick(&mut board, &mut hooks);
+        }
+        assert_eq!(*calls.borrow(), ["eat", "score", "tick", "victory", "tick"]);
+    }
+
+}
@@ -0,0 +1,145 @@
+//! Layouts of the movement keys, and the keyboards they are read from.
+
+use sfml::window::Key;
+
+use grid::Direction;
+
+
+/// Directions of the keys of each layout, in the same order.
+const DIRECTIONS: [Direction; 8] = [
+    Direction::Left, Direction::Up, Direction::Right, Direction::Down,
+    Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+];
+
+
+/// Presets of the keys moving the snake of the first player, with the keys
+/// of the diagonal directions (only used on the boards that have them).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum KeyLayout {
+    #[default]
+    Wasd,   // W A S D, diagonals Q E Z X
+    Hjkl,   // H J K L like vim, diagonals Y U B N like the roguelikes
+    Numpad, // numeric keypad 8 4 6 2, diagonals 7 9 1 3
+    Esdf,   // E S D F, diagonals W R X V
+}
+
+impl KeyLayout {
+
+    /// Parses the layout from its name.
+    pub fn from_name(name: &str) -> Result<KeyLayout, &'static str> {
+        match name {
+            "wasd" => Ok(KeyLayout::Wasd),
+            "hjkl" => Ok(KeyLayout::Hjkl),
+            "numpad" => Ok(KeyLayout::Numpad),
+            "esdf" => Ok(KeyLayout::Esdf),
+            _ => Err("Invalid key layout: <wasd|hjkl|numpad|esdf>")
+        }
+    }
+
+    /// Gets the name of the layout.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyLayout::Wasd => "wasd",
+            KeyLayout::Hjkl => "hjkl",
+            KeyLayout::Numpad => "numpad",
+            KeyLayout::Esdf => "esdf",
+        }
+    }
+
+    /// Gets the keys of the directions, in the order left, up, right, down,
+    /// up-left, up-right, down-left and down-right.
+    fn keys(self) -> [Key; 8] {
+        match self {
+            KeyLayout::Wasd => [Key::A, Key::W, Key::D, Key::S, Key::Q, Key::E, Key::Z, Key::X],
+            KeyLayout::Hjkl => [Key::H, Key::K, Key::L, Key::J, Key::Y, Key::U, Key::B, Key::N],
+            KeyLayout::Numpad => [Key::Numpad4, Key::Numpad8, Key::Numpad6, Key::Numpad2, Key::Numpad7, Key::Numpad9, Key::Numpad1, Key::Numpad3],
+            KeyLayout::Esdf => [Key::S, Key::E, Key::F, Key::D, Key::W, Key::R, Key::X, Key::V],
+        }
+    }
+
+    /// Gets the direction the given key moves the snake of the first player
+    /// to, if any (the diagonal ones only if the board has them).
+    pub fn direction(self, key: Key, diagonals: bool) -> Option<Direction> {
+        let index = self.keys().iter().position(|k| *k == key)?;
+        if index >= 4 && !diagonals {
+            return None;
+        }
+        Some(DIRECTIONS[index])
+    }
+
+    /// Gets the direction the given key moves the snake of the second
+    /// player to in co-op mode, if any: the arrow keys, or the numeric
+    /// keypad on the boards with diagonal directions (unless the first
+    /// player uses it, the diagonals being then left out).
+    pub fn partner_direction(self, key: Key, diagonals: bool) -> Option<Direction> {
+        let keys = if diagonals && self != KeyLayout::Numpad {
+            KeyLayout::Numpad.keys()
+        } else {
+            [Key::Left, Key::Up, Key::Right, Key::Down, Key::Unknown, Key::Unknown, Key::Unknown, Key::Unknown]
+        };
+        let index = keys.iter().position(|k| *k == key && *k != Key::Unknown)?;
+        Some(DIRECTIONS[index])
+    }
+
+}
+
+
+/// Layouts of the keyboard of the system, for the movement keys to be found
+/// by their place on the keyboard rather than by their label.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Keyboard {
+    #[default]
+    Qwerty,     // the keys taken by their label
+    Azerty,
+    Qwertz,
+    Dvorak,
+}
+
+impl Keyboard {
+
+    /// Parses the keyboard layout from its name.
+    pub fn from_name(name: &str) -> Result<Keyboard, &'static str> {
+        match name {
+            "qwerty" => Ok(Keyboard::Qwerty),
+            "azerty" => Ok(Keyboard::Azerty),
+            "qwertz" => Ok(Keyboard::Qwertz),
+            "dvorak" => Ok(Keyboard::Dvorak),
+            _ => Err("Invalid keyboard: <qwerty|azerty|qwertz|dvorak>")
+        }
+    }
+
+    /// Gets the name of the keyboard layout.
+    pub fn name(self) -> &'static str {
+        match self {
+            Keyboard::Qwerty => "qwerty",
+            Keyboard::Azerty => "azerty",
+            Keyboard::Qwertz => "qwertz",
+            Keyboard::Dvorak => "dvorak",
+        }
+    }
+
+    /// Gets the key found at the same place on a QWERTY keyboard as the
+    /// given key on this one (the keys not moved being returned as is).
+    pub fn position(self, key: Key) -> Key {
+        let moved: &[(Key, Key)] = match self {
+            Keyboard::Qwerty => &[],
+            Keyboard::Azerty => &[
+                (Key::A, Key::Q), (Key::Z, Key::W), (Key::Q, Key::A), (Key::W, Key::Z),
+                (Key::M, Key::SemiColon), (Key::Comma, Key::M),
+            ],
+            Keyboard::Qwertz => &[(Key::Z, Key::Y), (Key::Y, Key::Z)],
+            Keyboard::Dvorak => &[
+                (Key::Quote, Key::Q), (Key::Comma, Key::W), (Key::Period, Key::E), (Key::P, Key::R),
+                (Key::Y, Key::T), (Key::F, Key::Y), (Key::G, Key::U), (Key::C, Key::I),
+                (Key::R, Key::O), (Key::L, Key::P), (Key::O, Key::S), (Key::E, Key::D),
+                (Key::U, Key::F), (Key::I, Key::G), (Key::D, Key::H), (Key::H, Key::J),
+                (Key::T, Key::K), (Key::N, Key::L), (Key::S, Key::SemiColon), (Key::SemiColon, Key::Z),
+                (Key::Q, Key::X), (Key::J, Key::C), (Key::K, Key::V), (Key::X, Key::B),
+                (Key::B, Key::N),
+            ],
+        };
+        moved.iter().find(|(label, _)| *label == key).map_or(key, |(_, position)| *position)
+    }
+
+}
+
@@ -1,603 +1,4658 @@
+//! Snake game built on SFML.
+//!
+//! Besides the game itself (`Config` and `run`), the crate exposes its core
+//! so that the game logic can be embedded elsewhere (e.g. a screensaver, a
+//! terminal frontend or a bot) without the SFML frontend: the `board`,
+//! `grid`, `replay`, `env`, `protocol`, `lobby` and `net` modules do not
+//! depend on SFML. A run is played by
+//! creating a `Board` with the game `Rules` and a seed, then applying the
+//! player `Input`s and calling `Board::tick` at the pace of
+//! `Rules::tick_rate`.
+
+extern crate env_logger;
+extern crate gif;
+#[macro_use]
+extern crate log;
+// the code generated by PyO3 refers to `::core`
+#[cfg(feature = "snake_py")]
+extern crate core;
+#[cfg(feature = "snake_py")]
+extern crate numpy;
+#[cfg(feature = "snake_py")]
+extern crate pyo3;
 extern crate rand;
 extern crate sfml;
+#[cfg(feature = "online")]
+extern crate ureq;
 
+use env_logger::{Builder, Env, Target};
+use log::LevelFilter;
 use rand::prelude::*;
-use sfml::graphics::{FloatRect, RectangleShape, RenderTarget, RenderWindow, Shape, Transformable};
-use sfml::graphics::{Color, Font, Sprite, Text, Texture};
-use sfml::audio::{Sound, SoundBuffer};
-use sfml::system::{Clock, Time, Vector2f, Vector2u};
-use sfml::window::{Event, Key, Style};
+use sfml::graphics::{FloatRect, PrimitiveType, RectangleShape, RenderStates, RenderTarget, RenderTexture, RenderWindow, Shader, Shape, Transformable};
+use sfml::graphics::{Color, Font, Image, IntRect, Sprite, Text, Texture, Vertex, VertexArray, View};
+use sfml::audio::{Sound, SoundBuffer, SoundSource, SoundStatus};
+use sfml::system::{self, Clock, Time, Vector2f, Vector2i, Vector2u};
+use sfml::window::{mouse, Event, Key, Style, VideoMode};
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod ambience;
+mod atlas;
+pub mod ai;
+pub mod board;
+mod cheats;
+mod console;
+mod crash;
+pub mod diagram;
+mod difficulty;
+mod keys;
+pub mod env;
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod grid;
+pub mod hooks;
+pub mod lobby;
+mod manifest;
+mod monitors;
+pub mod net;
+#[cfg(feature = "online")]
+mod online;
+#[cfg(feature = "discord")]
+mod presence;
+pub mod protocol;
+#[cfg(feature = "snake_py")]
+mod python;
+mod rating;
+pub mod replay;
+mod settings;
+pub mod spawn;
+mod swipe;
+pub mod variant;
+
+use atlas::{Animation, Atlas};
+use board::{Enemy, Portal};
+use cheats::{Cheat, CheatCodes};
+use console::{Command, StateName};
+#[cfg(feature = "online")]
+use online::Leaderboard;
+#[cfg(feature = "discord")]
+use presence::{Activity, Presence};
+use manifest::{Asset, Manifest};
+use monitors::Monitor;
+use net::{Server, Session, Update};
+use protocol::Message;
+use settings::Settings;
+use swipe::Swipe;
+
+pub use ambience::Ambience;
+pub use board::{Board, Input, Mode, Modifiers, Mutators, Rules, Scoring, Snake, TickEvent};
+pub use grid::{Direction, Grid, GridPos, Topology};
+pub use hooks::GameHooks;
+pub use keys::{Keyboard, KeyLayout};
+pub use replay::Replay;
+pub use spawn::FoodSpawn;
+
+
+/// Directory where the replays of the best runs are saved.
+const REPLAY_DIRECTORY: &str = "replays";
+
+/// File the diagram of the board is exported to and imported from by the
+/// console.
+const BOARD_FILE: &str = "board.txt";
+
+/// Directory where the summary cards of the runs are saved.
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+/// Duration of the slow motion after a near miss (in seconds): half of it
+/// at half speed, the other half ramping back to the normal speed.
+const SLOW_MOTION_SECONDS: f32 = 1.0;
+
+/// Time slept between two frames while the game is idle (in milliseconds).
+const IDLE_FRAME_TIME: i32 = 50;
+
+/// Duration of the splash screen shown at startup (in seconds).
+const SPLASH_SECONDS: f32 = 2.0;
+
+/// Duration of the screens fade in and fade out (in seconds).
+const FADE_SECONDS: f32 = 0.4;
+
+/// Distance (in cells along a row or column, or in steps on hex boards)
+/// from the snake head up to which the cells are shown in fog of war.
+const FOG_RADIUS: i32 = 4;
+
+/// Height of a row of hexagons, in cell widths (the rows overlapping by a
+/// quarter of a hexagon): the square root of 3 halved.
+const HEX_ROW_HEIGHT: f32 = 0.866_025_4;
+
+/// Number of seconds the chat lines are shown (fading out at the end).
+const CHAT_SECONDS: f32 = 8.0;
+
+/// Number of seconds the cells left by the snake tail keep fading out.
+const TRAIL_SECONDS: f32 = 0.3;
+
+/// Number of seconds the tongue of the drawn snake head stays out after
+/// eating.
+const TONGUE_SECONDS: f32 = 0.25;
+
+/// Maximum number of chat lines shown at once.
+const CHAT_LINES: usize = 5;
+
+/// Maximum number of characters of a chat line.
+const CHAT_LENGTH: usize = 60;
+
+/// Number of seconds that can be rewound in practice mode.
+const REWIND_SECONDS: u32 = 5;
+
+/// Number of seconds before the next demo run starts.
+const DEMO_RESTART_SECONDS: u32 = 3;
+
+/// Minimum size (columns, rows) of the board of the headless benchmark.
+const BENCH_COLUMNS: i32 = 64;
+const BENCH_ROWS: i32 = 16;
+
+/// Longest snake of the headless benchmark.
+const MAX_BENCH_SNAKE_LENGTH: u32 = 1 << 20;
+
+/// Size (columns, rows) of the board of the runs played on the server run
+/// without the game.
+const SERVER_COLUMNS: i32 = 30;
+const SERVER_ROWS: i32 = 20;
+
+/// Names of the music stems of the asset pack, layered in this order: the
+/// first one is always heard, the next ones as the intensity rises.
+const MUSIC_STEMS: [&str; 4] = ["music-1", "music-2", "music-3", "music-4"];
+
+/// Scores at which the intensity of a run rises, layering another music
+/// stem and tinting the background further.
+const INTENSITY_SCORES: [u32; 3] = [100, 300, 600];
+
+/// Color the background is tinted toward as the intensity rises (halfway
+/// at the highest intensity).
+const INTENSITY_COLOR: Color = Color { r: 160, g: 32, b: 48, a: 255 };
+
+/// Fragment shader blurring the board behind the overlays: the average of
+/// the pixels around each one, `pixel` apart.
+const BLUR_SHADER: &str = "
+uniform sampler2D texture;
+uniform vec2 pixel;
+void main() {
+    vec4 color = vec4(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            color += texture2D(texture, gl_TexCoord[0].xy + vec2(x, y) * pixel);
+        }
+    }
+    gl_FragColor = gl_Color * color / 25.0;
+}";
+
+/// Window icon, embedded in the executable.
+const ICON: &[u8] = include_bytes!("../resources/icon.png");
+
+/// Default font, embedded in the executable (used if no other font loads).
+const DEFAULT_FONT: &[u8] = include_bytes!("../resources/joystix.ttf");
+
+/// Colors of the portal pairs.
+const PORTAL_COLORS: [Color; 4] = [Color::CYAN, Color::MAGENTA, Color::YELLOW, Color::WHITE];
+
+/// Color of the second player snake in co-op mode.
+const PARTNER_COLOR: Color = Color { r: 0, g: 160, b: 255, a: 255 };
+
+/// Color of the rival snake, and of its score, in versus mode.
+const RIVAL_COLOR: Color = Color { r: 255, g: 128, b: 0, a: 255 };
+
+/// Color of the magnet power-up, and of its link to the food it pulls.
+const MAGNET_COLOR: Color = Color { r: 255, g: 64, b: 64, a: 255 };
+
+/// Color of the star power-up, and of the snake flashing while it lasts.
+const STAR_COLOR: Color = Color { r: 255, g: 215, b: 0, a: 255 };
+
+
+/// Look of the colored cells (e.g. thick outlines and gaps for a classic
+/// LCD, rounded corners for a flat modern one).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellStyle {
+    pub outline: f32,           // outline thickness (in pixels, 0 for none)
+    pub outline_color: Color,   // outline color
+    pub gap: f32,               // empty space between adjacent cells (in pixels)
+    pub corner_radius: f32,     // radius of the rounded corners, in cell sizes (up to 0.5 for circles)
+}
+
+impl Default for CellStyle {
+
+    /// Creates the classic style: a thin black outline around square
+    /// corners, without gaps.
+    fn default() -> CellStyle {
+        CellStyle {
+            outline: 1.0,
+            outline_color: Color::BLACK,
+            gap: 0.0,
+            corner_radius: 0.0,
+        }
+    }
+
+}
 
 
 /// Game configuration.
+#[derive(Debug)]
 pub struct Config {
-    window_size: Vector2u,  // window size (width, height)
-    entity_size: u32,       // entity size (all entities are square)
-    fps: u32,               // frames per second
-    text_size: u32,         // score character size
-    text_color: Color,      // score text color
-    snake_color: Color,     // snake color
-    food_color: Color,      // snake food color
-    back_color: Color,      // window background color
+    mode: Option<Mode>,                 // game mode (the last one played if none)
+    window_size: Vector2u,              // window size (width, height)
+    entity_size: u32,                   // default cell size and HUD margin (all entities are square)
+    fps: u32,                           // frames per second
+    rival_speed: u32,                   // speed of the rival snake (percentage of the player one)
+    scoring: String,                    // points overriding the preset of the mode (<name>=<points> pairs)
+    text_size: u32,                     // score character size
+    text_color: Color,                  // score text color
+    snake_color: Color,                 // snake color
+    food_color: Color,                  // snake food color
+    back_color: Color,                  // window background color
+    cell_style: CellStyle,              // look of the colored cells
+    ambience: Ambience,                 // variant of the palette (following the clock if auto)
+    utc_offset: i32,                    // offset of the local time from UTC (in hours), for the auto ambience
+    enemy_count: u32,                   // number of enemies
+    enemy_period: u32,                  // number of frames between two enemy moves
+    enemy_color: Color,                 // enemy color
+    portal_count: u32,                  // number of portal pairs
+    food_count: u32,                    // number of foods on the board at once
+    growth_per_food: i32,               // number of segments gained eating food
+    poison_growth: i32,                 // number of segments gained eating poison (0 to disable)
+    poison_color: Color,                // poison food color
+    magnet_seconds: u32,                // duration of the magnet power-up (0 to disable)
+    star_seconds: u32,                  // duration of the star power-up (0 to disable)
+    board_columns: Option<u32>,         // number of board columns (derived from the window size if none)
+    board_rows: Option<u32>,            // number of board rows (derived from the window size if none)
+    topology: Topology,                 // shape of the board cells
+    minimap: bool,                      // show the whole board in a corner of the window
+    heatmap: bool,                      // show how often the snake visited each cell once the run is over
+    summary_card: bool,                 // save a picture of the board and the score of every run over
+    trail_fade: bool,                   // tint the cells just left by the snake tail, fading out
+    blur: bool,                         // blur the board behind the overlays (if shaders are available)
+    key_layout: KeyLayout,              // keys moving the snake of the first player
+    keyboard: Keyboard,                 // layout of the keyboard, the movement keys being found by their place
+    swipe: u32,                         // length of the mouse or touch swipes turning the snake (in pixels, 0 to disable)
+    cell_size: Option<u32>,             // on-screen cell size (derived from the window and board size if none)
+    ui_scale: f32,                      // scale factor of the HUD (e.g. 2 for high DPI displays)
+    borderless: bool,                   // open a borderless window as large as the desktop
+    monitor: Option<u32>,               // index of the monitor the window is opened on (last position if none, only the main one without xrandr)
+    vsync: bool,                        // synchronize the frames with the monitor refresh rate
+    frame_limit: u32,                   // maximum number of frames rendered per second (0 for no limit)
+    auto_pause: bool,                   // pause the game when the window loses focus
+    practice: bool,                     // allow rewinding (the runs are not ranked)
+    demo: bool,                         // let the bot play the runs (which are not ranked)
+    adaptive: bool,                     // adjust the speed and enemies of each run to the last ones
+    slow_motion: bool,                  // slow down the game for a moment after a near miss
+    start_column: Option<u32>,          // column of the snake starting cell (random if none)
+    start_row: Option<u32>,             // row of the snake starting cell (random if none)
+    start_direction: Option<Direction>, // initial snake direction (none to wait for the player)
+    mutators: Mutators,                 // variations of the rules of every run
+    food_spawn: FoodSpawn,              // policy choosing where the food spawns
+    rounds: u32,                        // number of rounds of the versus matches (best of)
+    log_level: Option<LevelFilter>,     // maximum level of the logged events (RUST_LOG if none)
+    log_file: Option<String>,           // file the events are logged to (stderr if none)
+    resources: String,                  // directory of the resources (the asset pack)
+    font: Option<String>,               // font file of the texts (the asset pack one if none)
+    score_font: Option<String>,         // font file of the score (the texts one if none)
+    #[cfg(feature = "online")]
+    leaderboard: Option<String>,        // URL of the online leaderboard (offline if none)
+    player: String,                     // player name shown on the online leaderboard and to the other players online
+    connect: Option<String>,            // address (host:port) of the server of the networked play (offline if none)
+    host: Option<u16>,                  // port of the server hosted by the game for the networked play (none to join one)
+    #[cfg(feature = "discord")]
+    discord: Option<String>,            // ID of the Discord application the activity is published to (none to disable)
 }
 
 impl Config {
 
     /// Initializes the game configuration.
     pub fn new(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 3 {
-            return Err("Invalid number of arguments: <width> <height>");
+        // split the positional arguments from the `--name value` options
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = iter.next().ok_or("Missing option value: --<name> <value>")?;
+                options.push((name, value.as_str()));
+            } else {
+                positional.push(arg.as_str());
+            }
+        }
+        if positional.len() < 2 {
+            return Err("Invalid number of arguments: <width> <height> [mode] [--<name> <value>]");
+        }
+        let width = positional[0].parse().map_err(|_| "The window width must be a u32")?;
+        let height = positional[1].parse().map_err(|_| "The window height must be a u32")?;
+        let mut builder = Config::builder().window_size(width, height);
+        if let Some(name) = positional.get(2) {
+            builder = builder.mode(Mode::from_name(name)?);
+        }
+        // override the defaults with the configuration file (if any) and
+        // then with the given options
+        if let Some((_, path)) = options.iter().find(|(name, _)| *name == "config") {
+            builder = builder.file(path)?;
         }
-        let width = args[1].parse::<u32>().expect("The window with must be a u32");
-        let height = args[2].parse::<u32>().expect("The window height must be a u32");
-        Ok(Config {
-            window_size: Vector2u::new(width, height),
+        for (name, value) in options {
+            if name != "config" {
+                builder = builder.option(name, value)?;
+            }
+        }
+        builder.build()
+    }
+
+    /// Creates a builder of the game configuration, starting from the
+    /// default options.
+    pub fn builder() -> ConfigBuilder {
+        let config = Config {
+            mode: None,
+            window_size: Vector2u::new(720, 720),
             entity_size: 40,
             fps: 10,
+            rival_speed: board::FULL_SPEED,
+            scoring: String::new(),
             text_size: 50,
             text_color: Color::BLACK,
             snake_color: Color::GREEN,
             food_color: Color::RED,
             back_color: Color::rgb(122, 122, 122),
-        })
+            cell_style: CellStyle::default(),
+            ambience: Ambience::default(),
+            utc_offset: 0,
+            enemy_count: 0,
+            enemy_period: 2,
+            enemy_color: Color::BLUE,
+            portal_count: 0,
+            food_count: 1,
+            growth_per_food: 1,
+            poison_growth: 0,
+            poison_color: Color::rgb(128, 0, 128),
+            magnet_seconds: 0,
+            star_seconds: 0,
+            board_columns: None,
+            board_rows: None,
+            topology: Topology::default(),
+            minimap: false,
+            heatmap: false,
+            summary_card: false,
+            trail_fade: true,
+            blur: false,
+            key_layout: KeyLayout::default(),
+            keyboard: Keyboard::default(),
+            swipe: 0,
+            cell_size: None,
+            ui_scale: 1.0,
+            borderless: false,
+            monitor: None,
+            vsync: false,
+            frame_limit: 60,
+            auto_pause: true,
+            practice: false,
+            demo: false,
+            adaptive: false,
+            slow_motion: true,
+            start_column: None,
+            start_row: None,
+            start_direction: None,
+            mutators: Mutators::default(),
+            food_spawn: FoodSpawn::default(),
+            rounds: 1,
+            log_level: None,
+            log_file: None,
+            resources: String::from("resources"),
+            font: None,
+            score_font: None,
+            #[cfg(feature = "online")]
+            leaderboard: None,
+            player: String::from("player"),
+            connect: None,
+            host: None,
+            #[cfg(feature = "discord")]
+            discord: None,
+        };
+        ConfigBuilder { config }
     }
 
-}
+    /// Loads the options from a configuration file, made of `key = value`
+    /// lines where the key is the name of an option (e.g. `board_columns = 20`).
+    /// Empty lines and lines starting with `#` are ignored.
+    fn load(&mut self, path: &str) -> Result<(), &'static str> {
+        let content = fs::read_to_string(path).map_err(|_| "Unable to read the configuration file")?;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or("Invalid configuration line: <key> = <value>")?;
+            // keys use underscores where the options use hyphens
+            let name = key.trim().replace('_', "-");
+            let value = value.trim().trim_matches('"');
+            self.set(&name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the value of the option with the given name.
+    fn set(&mut self, name: &str, value: &str) -> Result<(), &'static str> {
+        match name {
+            "enemies" => {
+                self.enemy_count = value.parse().map_err(|_| "The number of enemies must be a u32")?;
+            },
+            "portals" => {
+                self.portal_count = value.parse().map_err(|_| "The number of portals must be a u32")?;
+            },
+            "foods" => {
+                let count = value.parse().ok().filter(|n| *n > 0);
+                self.food_count = count.ok_or("The number of foods must be a positive u32")?;
+            },
+            "growth" => {
+                self.growth_per_food = value.parse().map_err(|_| "The growth per food must be an i32")?;
+            },
+            "poison" => {
+                self.poison_growth = value.parse().map_err(|_| "The poison growth must be an i32")?;
+            },
+            "magnet" => {
+                self.magnet_seconds = value.parse().map_err(|_| "The magnet duration must be a u32")?;
+            },
+            "star" => {
+                self.star_seconds = value.parse().map_err(|_| "The star duration must be a u32")?;
+            },
+            "board-columns" => {
+                let columns = value.parse().ok().filter(|n| *n > 0);
+                self.board_columns = Some(columns.ok_or("The number of board columns must be a positive u32")?);
+            },
+            "board-rows" => {
+                let rows = value.parse().ok().filter(|n| *n > 0);
+                self.board_rows = Some(rows.ok_or("The number of board rows must be a positive u32")?);
+            },
+            "topology" => {
+                self.topology = Topology::from_name(value)?;
+            },
+            "summary-card" => {
+                self.summary_card = value.parse().map_err(|_| "The summary card option must be true or false")?;
+            },
+            "heatmap" => {
+                self.heatmap = value.parse().map_err(|_| "The heatmap option must be true or false")?;
+            },
+            "minimap" => {
+                self.minimap = value.parse().map_err(|_| "The minimap option must be true or false")?;
+            },
+            "keys" => self.key_layout = KeyLayout::from_name(value)?,
+            "keyboard" => self.keyboard = Keyboard::from_name(value)?,
+            "swipe" => {
+                self.swipe = value.parse().map_err(|_| "The swipe length must be a u32")?;
+            },
+            "blur" => {
+                self.blur = value.parse().map_err(|_| "The blur option must be true or false")?;
+            },
+            "trail-fade" => {
+                self.trail_fade = value.parse().map_err(|_| "The trail fade option must be true or false")?;
+            },
+            "ui-scale" => {
+                let scale = value.parse().ok().filter(|scale: &f32| *scale > 0.0);
+                self.ui_scale = scale.ok_or("The UI scale must be a positive number")?;
+            },
+            "borderless" => {
+                self.borderless = value.parse().map_err(|_| "The borderless option must be true or false")?;
+            },
+            "monitor" => {
+                self.monitor = Some(value.parse().map_err(|_| "The monitor must be a u32 (0 for the main one)")?);
+            },
+            "vsync" => {
+                self.vsync = value.parse().map_err(|_| "The vsync option must be true or false")?;
+            },
+            "frame-limit" => {
+                self.frame_limit = value.parse().map_err(|_| "The frame limit must be a u32 (0 for no limit)")?;
+            },
+            "auto-pause" => {
+                self.auto_pause = value.parse().map_err(|_| "The auto-pause option must be true or false")?;
+            },
+            "slow-motion" => {
+                self.slow_motion = value.parse().map_err(|_| "The slow motion option must be true or false")?;
+            },
+            "practice" => {
+                self.practice = value.parse().map_err(|_| "The practice option must be true or false")?;
+            },
+            "demo" => {
+                self.demo = value.parse().map_err(|_| "The demo option must be true or false")?;
+            },
+            "adaptive" => {
+                self.adaptive = value.parse().map_err(|_| "The adaptive option must be true or false")?;
+            },
+            "start-column" => {
+                self.start_column = Some(value.parse().map_err(|_| "The start column must be a u32")?);
+            },
+            "start-row" => {
+                self.start_row = Some(value.parse().map_err(|_| "The start row must be a u32")?);
+            },
+            "start-direction" => {
+                self.start_direction = Some(Direction::from_name(value)?);
+            },
+            "mutators" => {
+                self.mutators = Mutators::from_names(value)?;
+            },
+            "food-spawn" => {
+                self.food_spawn = FoodSpawn::from_name(value)?;
+            },
+            "rounds" => {
+                let rounds = value.parse().ok().filter(|n| *n > 0);
+                self.rounds = rounds.ok_or("The number of rounds must be a positive u32")?;
+            },
+            "speed" => {
+                let speed = value.parse().ok().filter(|n| *n > 0);
+                self.fps = speed.ok_or("The speed must be a positive u32 (steps per second)")?;
+            },
+            "rival-speed" => {
+                let speed = value.parse().ok().filter(|n| *n > 0);
+                self.rival_speed = speed.ok_or("The rival speed must be a positive u32 (percentage)")?;
+            },
+            "scoring" => {
+                // checked right away, the preset of the mode being known
+                // only once the game starts
+                Scoring::new(Mode::Classic).with(value)?;
+                self.scoring = value.to_string();
+            },
+            "log-level" => {
+                let level = value.parse().map_err(|_| "The log level must be off, error, warn, info, debug or trace")?;
+                self.log_level = Some(level);
+            },
+            "log-file" => {
+                self.log_file = Some(value.to_string());
+            },
+            "resources" => {
+                self.resources = value.to_string();
+            },
+            "font" => {
+                self.font = Some(value.to_string());
+            },
+            "score-font" => {
+                self.score_font = Some(value.to_string());
+            },
+            #[cfg(feature = "online")]
+            "leaderboard" => {
+                self.leaderboard = Some(value.to_string());
+            },
+            "player" => {
+                self.player = value.to_string();
+            },
+            "connect" => {
+                self.connect = Some(value.to_string());
+            },
+            "host" => {
+                let port = value.parse().ok().filter(|port| *port > 0);
+                self.host = Some(port.ok_or("The port must be a u16 greater than 0")?);
+            },
+            #[cfg(feature = "discord")]
+            "discord" => {
+                self.discord = Some(value.to_string());
+            },
+            "cell-size" => {
+                let size = value.parse().ok().filter(|n| *n > 0);
+                self.cell_size = Some(size.ok_or("The cell size must be a positive u32")?);
+            },
+            "outline" => {
+                let outline = value.parse().ok().filter(|n: &f32| *n >= 0.0);
+                self.cell_style.outline = outline.ok_or("The outline thickness must be a non-negative f32")?;
+            },
+            "outline-color" => self.cell_style.outline_color = parse_color(value)?,
+            "cell-gap" => {
+                let gap = value.parse().ok().filter(|n: &f32| *n >= 0.0);
+                self.cell_style.gap = gap.ok_or("The cell gap must be a non-negative f32")?;
+            },
+            "corner-radius" => {
+                let radius = value.parse().ok().filter(|n: &f32| (0.0..=0.5).contains(n));
+                self.cell_style.corner_radius = radius.ok_or("The corner radius must be between 0 and 0.5 cells")?;
+            },
+            "ambience" => self.ambience = Ambience::from_name(value)?,
+            "utc-offset" => {
+                let offset = value.parse().ok().filter(|n| (-12..=14).contains(n));
+                self.utc_offset = offset.ok_or("The UTC offset must be between -12 and 14 hours")?;
+            },
+            "text-color" => self.text_color = parse_color(value)?,
+            "snake-color" => self.snake_color = parse_color(value)?,
+            "food-color" => self.food_color = parse_color(value)?,
+            "back-color" => self.back_color = parse_color(value)?,
+            "enemy-color" => self.enemy_color = parse_color(value)?,
+            "poison-color" => self.poison_color = parse_color(value)?,
+            _ => return Err("Unknown option, see the README for the list of options")
+        };
+        Ok(())
+    }
 
+    /// Checks that all the options are within their valid range.
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.entity_size <= 4 {
+            return Err("The entity size must be greater than 4");
+        }
+        if self.window_size.x / self.entity_size < 10 || self.window_size.y / self.entity_size < 10 {
+            return Err("The window must be at least 10x10 cells (of the entity size)");
+        }
+        if self.fps < 1 || self.fps > 240 {
+            return Err("The speed must be between 1 and 240 steps per second");
+        }
+        if self.text_size == 0 {
+            return Err("The text size must be positive");
+        }
+        if self.board_columns == Some(0) || self.board_rows == Some(0) {
+            return Err("The number of board columns and rows must be positive");
+        }
+        if self.cell_size == Some(0) {
+            return Err("The cell size must be positive");
+        }
+        if self.ui_scale.is_nan() || self.ui_scale <= 0.0 {
+            return Err("The UI scale must be a positive number");
+        }
+        if self.rounds == 0 {
+            return Err("The number of rounds must be positive");
+        }
+        Ok(())
+    }
 
-/// Game resources.
-struct Resources {
-    font: Font,                 // text font
-    eat_buffer: SoundBuffer,    // eat sound buffer
-    over_buffer: SoundBuffer,   // game over sound buffer
-    pause_texture: Texture,     // pause image texture
 }
 
-impl<'a> Resources {
 
-    /// Loads and initializes the game resources.
-    fn new() -> Resources {
-        // loaf text font
-        let filename = "resources/joystix.ttf";
-        let font = Font::from_file(filename).expect("Unable to load the font.");
-        // load sound buffer
-        let filename = "resources/eat.ogg";
-        let eat_buffer = SoundBuffer::from_file(filename).expect("Unable to load the eat sound.");
-        let filename = "resources/error.ogg";
-        let over_buffer = SoundBuffer::from_file(filename).expect("Unable to load the game over sound.");
-        // load textures
-        let filename = "resources/pause.png";
-        let pause_texture = Texture::from_file(filename).expect("Unable to load the pause texture.");
-        Resources { font, eat_buffer, over_buffer, pause_texture }
+/// Parses a color given as `#RRGGBB` or `#RRGGBBAA` hexadecimal string, or
+/// by name (e.g. `green` or `grey`).
+fn parse_color(value: &str) -> Result<Color, &'static str> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("Invalid color, the hexadecimal colors must be #RRGGBB or #RRGGBBAA");
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(255);
+        let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+        return Ok(Color::rgba(channel(0), channel(2), channel(4), alpha));
     }
+    let color = match value.to_lowercase().as_str() {
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "red" => Color::RED,
+        "green" => Color::GREEN,
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "magenta" => Color::MAGENTA,
+        "cyan" => Color::CYAN,
+        "grey" | "gray" => Color::rgb(122, 122, 122),
+        "purple" => Color::rgb(128, 0, 128),
+        "orange" => Color::rgb(255, 165, 0),
+        _ => return Err("Unknown color name, use a #RRGGBB color or one of black, white, red, green, blue, yellow, magenta, cyan, grey, purple and orange")
+    };
+    Ok(color)
+}
 
+
+/// Builder of the game configuration, the options are validated once built.
+pub struct ConfigBuilder {
+    config: Config,     // configuration being built
 }
 
+impl ConfigBuilder {
 
-trait Game {
+    /// Sets the game mode.
+    pub fn mode(mut self, mode: Mode) -> ConfigBuilder {
+        self.config.mode = Some(mode);
+        self
+    }
 
-    /// Runs the game main loop.
-    fn run(&mut self);
+    /// Sets the window size (in pixels).
+    pub fn window_size(mut self, width: u32, height: u32) -> ConfigBuilder {
+        self.config.window_size = Vector2u::new(width, height);
+        self
+    }
 
-    /// Handles player inputs.
-    fn process_events(&mut self);
+    /// Sets the default cell size and HUD margin (in pixels).
+    pub fn entity_size(mut self, size: u32) -> ConfigBuilder {
+        self.config.entity_size = size;
+        self
+    }
 
-    /// Updates the game status.
-    /// * `time` - Elapsed time between two consecutive frames.
-    fn update(&mut self, time: Time);
+    /// Sets the number of snake steps per second.
+    pub fn speed(mut self, speed: u32) -> ConfigBuilder {
+        self.config.fps = speed;
+        self
+    }
 
-    /// Renders graphics.
-    fn render(&mut self);
+    /// Sets the speed of the rival snake of the versus mode, as a
+    /// percentage of the speed of the player snake.
+    pub fn rival_speed(mut self, speed: u32) -> ConfigBuilder {
+        self.config.rival_speed = speed;
+        self
+    }
 
-}
+    /// Sets the points overriding the preset of the mode, given as comma
+    /// separated `<name>=<points>` pairs.
+    pub fn scoring(mut self, overrides: &str) -> ConfigBuilder {
+        self.config.scoring = overrides.to_string();
+        self
+    }
 
-trait Graphic {
+    /// Sets the score character size.
+    pub fn text_size(mut self, size: u32) -> ConfigBuilder {
+        self.config.text_size = size;
+        self
+    }
 
-    /// Draws the graphic element.
-    fn draw(&self, window: &mut RenderWindow);
+    /// Sets the score text color.
+    pub fn text_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.text_color = color;
+        self
+    }
 
-}
+    /// Sets the snake color.
+    pub fn snake_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.snake_color = color;
+        self
+    }
 
-/// Enumerates all possible snake directions.
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Direction {
-    Left,
-    Up,
-    Right,
-    Down
-}
-
-impl Direction {
-
-    /// Returns true only if the self direction is opposite to
-    /// the give one.
-    fn is_opposite_to(&self, other: &Option<Direction>) -> bool {
-        match other {
-            Some(direction) => {
-                match *self {
-                    Direction::Left => *direction == Direction::Right,
-                    Direction::Up => *direction == Direction::Down,
-                    Direction::Right => *direction == Direction::Left,
-                    Direction::Down => *direction == Direction::Up,
-                }
-            },
-            None => false
-        }
+    /// Sets the food color.
+    pub fn food_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.food_color = color;
+        self
     }
 
-}
+    /// Sets the window background color.
+    pub fn back_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.back_color = color;
+        self
+    }
 
+    /// Sets the look of the colored cells.
+    pub fn cell_style(mut self, style: CellStyle) -> ConfigBuilder {
+        self.config.cell_style = style;
+        self
+    }
 
-/// A single game entity.
-struct Entity<'a> {
-    shape: RectangleShape<'a>,  // shape of the entity
-}
+    /// Sets the variant of the palette, and the offset of the local time
+    /// from UTC (in hours) used to choose it if automatic.
+    pub fn ambience(mut self, ambience: Ambience, utc_offset: i32) -> ConfigBuilder {
+        self.config.ambience = ambience;
+        self.config.utc_offset = utc_offset;
+        self
+    }
 
-impl<'a> Entity<'a> {
+    /// Sets the number of enemies and the number of ticks between two of
+    /// their moves.
+    pub fn enemies(mut self, count: u32, period: u32) -> ConfigBuilder {
+        self.config.enemy_count = count;
+        self.config.enemy_period = period;
+        self
+    }
 
-    /// Initializes a new entity with the given size and color.
-    fn new(size: u32, position: Vector2f, color: &Color) -> Entity<'a> {
-        let mut shape = RectangleShape::new();
-        shape.set_fill_color(&color);
-        shape.set_outline_color(&Color::BLACK);
-        shape.set_outline_thickness(1.0);
-        shape.set_size(Vector2f::new(size as f32, size as f32));
-        shape.set_position(position);
-        Entity { shape }
+    /// Sets the enemy color.
+    pub fn enemy_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.enemy_color = color;
+        self
     }
 
-    /// Gets the position of the entity.
-    fn position(&self) -> Vector2f {
-        self.shape.position()
+    /// Sets the number of portal pairs.
+    pub fn portals(mut self, count: u32) -> ConfigBuilder {
+        self.config.portal_count = count;
+        self
     }
 
-    /// Sets the position of the entity.
-    fn set_position(&mut self, position: Vector2f) {
-        self.shape.set_position(position);
+    /// Sets the number of foods on the board at once.
+    pub fn foods(mut self, count: u32) -> ConfigBuilder {
+        self.config.food_count = count;
+        self
     }
 
-    /// Gets the size of the entity.
-    fn size(&self) -> Vector2f {
-        self.shape.size()
+    /// Sets the number of segments gained eating food.
+    pub fn growth(mut self, growth: i32) -> ConfigBuilder {
+        self.config.growth_per_food = growth;
+        self
     }
 
-    /// Gets the area of the segment.
-    fn area(&self) -> FloatRect {
-        let position = self.position();
-        let size = self.size();
-        FloatRect::new(position.x, position.y, size.x, size.y)
+    /// Sets the number of segments gained eating poison (0 to disable it).
+    pub fn poison(mut self, growth: i32) -> ConfigBuilder {
+        self.config.poison_growth = growth;
+        self
     }
-    
-    /// Gets the fill color of the entity.
-    fn color(&self) -> Color {
-        self.shape.fill_color()
+
+    /// Sets the number of seconds the magnet power-up lasts (0 to disable
+    /// it).
+    pub fn magnet(mut self, seconds: u32) -> ConfigBuilder {
+        self.config.magnet_seconds = seconds;
+        self
     }
-}
 
-impl<'a> Graphic for Entity<'a> {
+    /// Sets the number of seconds the star power-up lasts (0 to disable
+    /// it).
+    pub fn star(mut self, seconds: u32) -> ConfigBuilder {
+        self.config.star_seconds = seconds;
+        self
+    }
 
-    /// Draws the entity.
-    fn draw(&self, window: &mut RenderWindow) {
-        window.draw(&self.shape);
+    /// Sets the poison food color.
+    pub fn poison_color(mut self, color: Color) -> ConfigBuilder {
+        self.config.poison_color = color;
+        self
     }
 
-}
+    /// Sets the number of board columns and rows (derived from the window
+    /// size if none).
+    pub fn board_size(mut self, columns: Option<u32>, rows: Option<u32>) -> ConfigBuilder {
+        self.config.board_columns = columns;
+        self.config.board_rows = rows;
+        self
+    }
 
+    /// Sets the shape of the board cells (e.g. `Topology::Hex`).
+    pub fn topology(mut self, topology: Topology) -> ConfigBuilder {
+        self.config.topology = topology;
+        self
+    }
 
-/// The snake.
-struct Snake<'a> {
-    segments: VecDeque<Entity<'a>>,     // snake segments
-    direction: Option<Direction>,       // snake current direction
-    next_direction: Option<Direction>,  // snake next direction
-}
+    /// Shows the whole board in a corner of the window.
+    pub fn minimap(mut self, minimap: bool) -> ConfigBuilder {
+        self.config.minimap = minimap;
+        self
+    }
 
-impl<'a> Snake<'a> {
+    /// Shows how often the snake visited each cell once the run is over.
+    pub fn heatmap(mut self, heatmap: bool) -> ConfigBuilder {
+        self.config.heatmap = heatmap;
+        self
+    }
 
-    /// Creates a new snake with a single segment.
-    fn new(position: Vector2f, size: u32, color: &Color) -> Snake<'a> {
-        let mut segments = VecDeque::new();
-        // create snake head
-        let head = Entity::new(size, position, color);
-        segments.push_back(head);
-        Snake { segments, direction: None, next_direction: None }
+    /// Saves a picture of the board and the score of every run over to the
+    /// screenshots directory.
+    pub fn summary_card(mut self, summary_card: bool) -> ConfigBuilder {
+        self.config.summary_card = summary_card;
+        self
     }
 
-    /// Gets the position of the snake head.
-    fn head_position(&self) -> Vector2f {
-        // the snake has always at least 1 segment
-        self.segments.front().unwrap().position()
+    /// Sets the keys moving the snake of the first player.
+    pub fn keys(mut self, layout: KeyLayout) -> ConfigBuilder {
+        self.config.key_layout = layout;
+        self
     }
 
-    /// Gets the size of each snake segment.
-    fn size(&self) -> Vector2f {
-        // the snake has always at least 1 segment
-        self.segments.front().unwrap().size()
+    /// Sets the layout of the keyboard, the movement keys being found by
+    /// their place on it rather than by their label.
+    pub fn keyboard(mut self, keyboard: Keyboard) -> ConfigBuilder {
+        self.config.keyboard = keyboard;
+        self
     }
 
-    /// Gets the area of the snake head.
-    fn area(&self) -> FloatRect {
-        self.segments.front().unwrap().area()
+    /// Sets the length of the mouse or touch swipes turning the snake (in
+    /// pixels, 0 to disable them).
+    pub fn swipe(mut self, length: u32) -> ConfigBuilder {
+        self.config.swipe = length;
+        self
     }
 
-    /// Gets the fill color of each snake segment.
-    fn color(&self) -> Color {
-        // the snake has always at least 1 segment
-        self.segments.front().unwrap().color()
+    /// Sets whether the board is blurred behind the overlays (if shaders
+    /// are available).
+    pub fn blur(mut self, blur: bool) -> ConfigBuilder {
+        self.config.blur = blur;
+        self
     }
 
-    /// Returns true if the snake head collided with any
-    /// of its segments.
-    fn self_collision(&self) -> bool {
-        // check collision between the head (first segment) and
-        // all the followings elements
-        self.collision(&self.area(), 1)
+    /// Sets whether the cells just left by the snake tail are tinted,
+    /// fading out.
+    pub fn trail_fade(mut self, trail_fade: bool) -> ConfigBuilder {
+        self.config.trail_fade = trail_fade;
+        self
     }
 
-    /// Returns true only if the given area collides with any of the
-    /// snake segments starting from the `n_skip`th one.
-    fn collision(&self, area: &FloatRect, n_skip: usize) -> bool {
-        // check the snake segments starting from the `n_skip`th
-        for segment in self.segments.iter().skip(n_skip) {
-            let seg_position = segment.position();
-            let seg_area = FloatRect::new(seg_position.x, seg_position.y, area.width, area.height);
-            match area.intersection(&seg_area) {
-                Some(_) => return true,
-                None => ()
-            };
-        }
-        false
-    }
-
-    /// Adds a new segment to the end of the snake.
-    fn grow(&mut self) {
-        // create a new segment and init with the same position of the last segment
-        let segment = Entity::new(self.size().x as u32, self.head_position(), &self.color());
-        self.segments.push_back(segment);
-    }
-
-    /// Removes all the segments but the head.
-    fn reset(&mut self) {
-        while self.segments.len() > 1 {
-            self.segments.pop_back();
-        }
-        self.direction = None;
-        self.next_direction = None;
-    }
-
-    /// Updates the snake position.
-    fn advance(&mut self, viewport: FloatRect) {
-        // update direction
-        self.direction = self.next_direction;
-        let front_position = self.head_position();
-        let size = self.size().x; // it's a square => x == y
-        // the snake has always at least 1 segment
-        let mut last = self.segments.pop_back().unwrap();
-        let back_position = last.position();
-        // move the last segment to the new position of the first segment
-        // the old tail becomes the new head, gives the "illusion" of movement
-        // the environment is implemented as a Toroid
-        // https://en.wikipedia.org/wiki/Toroid
-        last.set_position(match self.direction {
-            Some(Direction::Left) => {
-                let x = (front_position.x - size + viewport.width - viewport.left) % viewport.width;
-                let x = x + viewport.left;
-                Vector2f::new(x, front_position.y)
-            },
-            Some(Direction::Up) => {
-                let y = (front_position.y - size + viewport.height - viewport.top) % viewport.height;
-                let y = y + viewport.top;
-                Vector2f::new(front_position.x, y)
-            },
-            Some(Direction::Right) => {
-                let x = (front_position.x + size - viewport.left) % viewport.width;
-                let x = x + viewport.left;
-                Vector2f::new(x, front_position.y)
-            },
-            Some(Direction::Down) => {
-                let y = (front_position.y + size - viewport.top) % viewport.height;
-                let y = y + viewport.top;
-                Vector2f::new(front_position.x, y)
-            },
-            _ => back_position
-        });
-        // the last segment is now the first
-        self.segments.push_front(last);
+    /// Sets the on-screen cell size (derived from the window and board size
+    /// if none).
+    pub fn cell_size(mut self, size: Option<u32>) -> ConfigBuilder {
+        self.config.cell_size = size;
+        self
     }
-}
 
-impl<'a> Graphic for Snake<'a> {
+    /// Sets the scale factor of the HUD.
+    pub fn ui_scale(mut self, scale: f32) -> ConfigBuilder {
+        self.config.ui_scale = scale;
+        self
+    }
 
-    /// Draws all the snake segments.
-    fn draw(&self, window: &mut RenderWindow) {
-        for segment in &self.segments {
-            segment.draw(window);
-        }
+    /// Opens a borderless window as large as the desktop.
+    pub fn borderless(mut self, borderless: bool) -> ConfigBuilder {
+        self.config.borderless = borderless;
+        self
     }
 
-}
+    /// Sets the index of the monitor the window is opened on (0 for the
+    /// main one, where it was in the last session if none). The other
+    /// monitors are only known with `xrandr`, the game refusing to start
+    /// otherwise.
+    pub fn monitor(mut self, monitor: Option<u32>) -> ConfigBuilder {
+        self.config.monitor = monitor;
+        self
+    }
 
-#[derive(Debug)]
-enum State {
-    Pause,
-    Play,
-    GameOver,
-}
+    /// Synchronizes the frames with the monitor refresh rate.
+    pub fn vsync(mut self, vsync: bool) -> ConfigBuilder {
+        self.config.vsync = vsync;
+        self
+    }
 
+    /// Sets the maximum number of frames rendered per second (0 for no
+    /// limit), ignored with the vertical synchronization.
+    pub fn frame_limit(mut self, limit: u32) -> ConfigBuilder {
+        self.config.frame_limit = limit;
+        self
+    }
 
-struct SnakeGame<'a> {
-    window: RenderWindow,
-    player: Snake<'a>,
-    food: Entity<'a>,
-    time_per_frame: Time,
-    entity_size: u32,
-    viewport: FloatRect,
-    border: RectangleShape<'a>,
-    score: u32,
-    state: State,
-    score_text: Text<'a>,
-    over_text: Text<'a>,
-    eat_sound: Sound<'a>,
-    over_sound: Sound<'a>,
-    pause_sprite: Sprite<'a>,
-    back_color: Color,
-}
+    /// Pauses the game when the window loses focus.
+    pub fn auto_pause(mut self, auto_pause: bool) -> ConfigBuilder {
+        self.config.auto_pause = auto_pause;
+        self
+    }
 
-impl<'a> SnakeGame<'a> {
+    /// Allows rewinding the runs, which are not ranked.
+    pub fn practice(mut self, practice: bool) -> ConfigBuilder {
+        self.config.practice = practice;
+        self
+    }
 
-    /// Create a new Snake Game.
-    fn new(config: &Config, resources: &'a Resources) -> SnakeGame<'a> {
-        // window size multiple of entity_size
-        let window_size = Vector2u::new(
-            config.window_size.x - config.window_size.x % config.entity_size,
-            config.window_size.y - config.window_size.y % config.entity_size);
-        // define the viewport where the snake can run
-        let viewport = FloatRect::new(
-            config.entity_size as f32,
-            config.entity_size as f32 * 2.0,
-            window_size.x as f32 - 2. * config.entity_size as f32,
-            window_size.y as f32 - 3. * config.entity_size as f32);
-        println!("viewport = {:?}", viewport);
-        // create the window
-        let mut window = RenderWindow::new(
-            (window_size.x, window_size.y),
-            "Snake",
-            Style::CLOSE,
-            &Default::default());
-        // set frame limit
-        let time_per_frame = Time::seconds(1.0 / config.fps as f32);
-        window.set_framerate_limit(config.fps);
+    /// Lets the bot play the runs, which are not ranked.
+    pub fn demo(mut self, demo: bool) -> ConfigBuilder {
+        self.config.demo = demo;
+        self
+    }
 
-        // create the border to separate the viewport from the top window section
-        // with the score
-        let mut border = RectangleShape::new();
-        border.set_fill_color(&Color::TRANSPARENT);
-        border.set_outline_color(&Color::WHITE);
-        border.set_outline_thickness(1.0);
-        border.set_size(Vector2f::new(viewport.width + config.entity_size as f32, 5.0));
-        border.set_position(Vector2f::new(viewport.left - (config.entity_size / 2) as f32, viewport.top - 5.0));
+    /// Adjusts the speed and the number of enemies of each run to the
+    /// performance in the last ones.
+    pub fn adaptive(mut self, adaptive: bool) -> ConfigBuilder {
+        self.config.adaptive = adaptive;
+        self
+    }
 
-        // create text with the given string and default configuration properties
-        let create_text = |content: &str| {
-            let mut text = Text::default();
-            text.set_font(&resources.font);
-            text.set_character_size(config.text_size);
-            text.set_fill_color(&config.text_color);
-            text.set_string(&content);
-            text
-        };
-        // initialize the score text
-        let score = 0;
-        let mut score_text = create_text(&score.to_string());
-        score_text.set_position(((window_size.x - config.text_size) as f32, 10.0));
-        // initialize the game over text and sets its position in the middle of the window
-        let mut over_text = create_text("GAME OVER");
-        let bounds = over_text.local_bounds();
-        let x = window_size.x as f32 / 2.0 - bounds.width / 2.0;
-        let y = window_size.y as f32 / 2.0 - bounds.height / 2.0;
-        over_text.set_position((x, y));
+    /// Slows down the game for a moment after a near miss.
+    pub fn slow_motion(mut self, slow_motion: bool) -> ConfigBuilder {
+        self.config.slow_motion = slow_motion;
+        self
+    }
 
-        // init the audio
-        let eat_sound = Sound::with_buffer(&resources.eat_buffer);
-        let over_sound = Sound::with_buffer(&resources.over_buffer);
+    /// Sets the mutators of every run (e.g. `Mutators::FOG_OF_WAR`).
+    pub fn mutators(mut self, mutators: Mutators) -> ConfigBuilder {
+        self.config.mutators = mutators;
+        self
+    }
 
-        // initialize the snake
-        let player_position = SnakeGame::random_position(viewport, config.entity_size);
-        let player = Snake::new(player_position, config.entity_size, &config.snake_color);
-        // initialize the food
-        let food_position = SnakeGame::random_position(viewport, config.entity_size);
-        let food = Entity::new(config.entity_size, food_position, &config.food_color);
+    /// Sets the policy choosing where the food spawns.
+    pub fn food_spawn(mut self, food_spawn: FoodSpawn) -> ConfigBuilder {
+        self.config.food_spawn = food_spawn;
+        self
+    }
 
-        // initialize the pause sprite
-        let pause_sprite = Sprite::with_texture(&resources.pause_texture);
+    /// Sets the number of rounds of the versus matches, won by whoever wins
+    /// most of them (1 for single runs).
+    pub fn rounds(mut self, rounds: u32) -> ConfigBuilder {
+        self.config.rounds = rounds;
+        self
+    }
 
-        SnakeGame {
-            window,
-            player,
-            food,
-            time_per_frame,
-            entity_size: config.entity_size,
-            viewport,
-            border,
-            score,
-            state: State::Pause,
-            score_text,
-            over_text,
-            eat_sound,
-            over_sound,
-            pause_sprite,
-            back_color: config.back_color,
-        }
+    /// Sets the snake starting cell (random if none) and direction (none
+    /// to wait for the player).
+    pub fn start(mut self, column: Option<u32>, row: Option<u32>, direction: Option<Direction>) -> ConfigBuilder {
+        self.config.start_column = column;
+        self.config.start_row = row;
+        self.config.start_direction = direction;
+        self
     }
 
-    /// Returns a random position within the viewport that is a multiple
-    /// of the given entity_size.
-    fn random_position(viewport: FloatRect, entity_size: u32) -> Vector2f {
-        let mut rng = thread_rng();
-        let x = rng.gen_range(0.0, viewport.width) + viewport.left;
-        let x = x - x % entity_size as f32;
-        let y = rng.gen_range(0.0, viewport.height) + viewport.top;
-        let y = y - y % entity_size as f32;
-        Vector2f::new(x, y)
+    /// Sets the maximum level of the logged events (RUST_LOG if none).
+    pub fn log_level(mut self, level: Option<LevelFilter>) -> ConfigBuilder {
+        self.config.log_level = level;
+        self
     }
 
-    /// Handles the player input.
-    fn handle_input(&mut self, key: Key) {
-        let key_direction = || {
-            match key {
-                Key::A => Some(Direction::Left),
-                Key::W => Some(Direction::Up),
-                Key::D => Some(Direction::Right),
-                Key::S => Some(Direction::Down),
-                _ => None
-            }
-        };
-        match key_direction() {
-            Some(direction) => {
-                // reset game if necessary
-                if let State::GameOver = self.state {
-                    self.player.reset();
-                    self.set_score(0);
-                }
-                // check if going backwards is allowed
-                if self.player.segments.len() == 1 || !direction.is_opposite_to(&self.player.direction) {
-                    self.player.next_direction = Some(direction);
-                    self.state = State::Play;
-                }
-            },
-            None => if let Key::P = key {
-                if let State::Play = self.state {
-                    // set the game state to pause
-                    self.player.next_direction = None;
-                    self.state = State::Pause;
-                }
-            }
-        };
+    /// Sets the file the events are logged to (stderr if none).
+    pub fn log_file(mut self, path: Option<String>) -> ConfigBuilder {
+        self.config.log_file = path;
+        self
     }
 
-    /// Sets the game state to Game Over.
-    fn game_over(&mut self) {
-        self.state = State::GameOver;
-        self.over_sound.play();
+    /// Sets the URL of the online leaderboard (offline if none).
+    #[cfg(feature = "online")]
+    pub fn leaderboard(mut self, url: Option<String>) -> ConfigBuilder {
+        self.config.leaderboard = url;
+        self
     }
 
-    /// Increase player score.
-    fn set_score(&mut self, value: u32) {
-        // get the number of decimal digits
-        let digit_count = |mut n: u32| {
-            let mut count = 1;
-            while n / 10 != 0 {
-                count += 1;
-                n = n / 10;
-            }
-            count
-        };
-        self.score = value;
-        // update score position and text
-        let offset = digit_count(self.score) * self.score_text.character_size();
-        self.score_text.set_position(((self.window.size().x - offset) as f32, 10.0));
-        self.score_text.set_string(&self.score.to_string());
+    /// Sets the player name shown on the online leaderboard and to the
+    /// other players of the networked play.
+    pub fn player(mut self, name: &str) -> ConfigBuilder {
+        self.config.player = name.to_string();
+        self
     }
 
-}
+    /// Sets the address (`host:port`) of the server to play online with
+    /// (offline if none).
+    pub fn connect(mut self, address: Option<String>) -> ConfigBuilder {
+        self.config.connect = address;
+        self
+    }
 
-impl<'a> Game for SnakeGame<'a> {
+    /// Sets the port of the server hosted by the game to play online with,
+    /// the game joining it (none to join the one given by `connect`).
+    pub fn host(mut self, port: Option<u16>) -> ConfigBuilder {
+        self.config.host = port;
+        self
+    }
 
-    /// Runs the game.
+    /// Sets the ID of the Discord application the current activity is
+    /// published to (none to disable the Rich Presence).
+    #[cfg(feature = "discord")]
+    pub fn discord(mut self, application: Option<String>) -> ConfigBuilder {
+        self.config.discord = application;
+        self
+    }
+
+    /// Sets the directory of the resources (the asset pack), which must
+    /// contain a `manifest.toml` listing them.
+    pub fn resources(mut self, directory: &str) -> ConfigBuilder {
+        self.config.resources = directory.to_string();
+        self
+    }
+
+    /// Sets the font file of the texts, used instead of the asset pack one
+    /// (which is used if it cannot be loaded).
+    pub fn font(mut self, path: &str) -> ConfigBuilder {
+        self.config.font = Some(path.to_string());
+        self
+    }
+
+    /// Sets the font file of the score, used instead of the texts one
+    /// (which is used if it cannot be loaded).
+    pub fn score_font(mut self, path: &str) -> ConfigBuilder {
+        self.config.score_font = Some(path.to_string());
+        self
+    }
+
+    /// Sets an option given its command line name and value (e.g. `speed`
+    /// and `20`), see the README for the list of options.
+    pub fn option(mut self, name: &str, value: &str) -> Result<ConfigBuilder, &'static str> {
+        self.config.set(name, value)?;
+        Ok(self)
+    }
+
+    /// Sets the options of the given configuration file.
+    pub fn file(mut self, path: &str) -> Result<ConfigBuilder, &'static str> {
+        self.config.load(path)?;
+        Ok(self)
+    }
+
+    /// Validates the options and builds the configuration.
+    pub fn build(self) -> Result<Config, &'static str> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+
+}
+
+
+/// Raw data of a resource, read or synthesized by the loading thread.
+enum ResourceData {
+    File(String, Result<Vec<u8>, String>),  // content of one of the resource files, by name (once verified)
+    Samples(Vec<i16>),                      // samples of the synthesized sounds
+}
+
+
+/// Game resources.
+struct Resources {
+    font: Font,                       // text font
+    score_font: Option<Font>,         // score font (the text font if none)
+    eat_buffer: SoundBuffer,          // eat sound buffer
+    over_buffer: SoundBuffer,         // game over sound buffer
+    victory_buffer: SoundBuffer,      // victory sound buffer
+    pause_texture: Option<Texture>,   // pause image texture (the overlay drawn without it if none)
+    music_buffers: Vec<SoundBuffer>,  // music stems, in the order they are layered
+    credits: String,                  // credits of the resources, a line for each one
+    atlas: Option<Atlas>,             // images of the entities (drawn as colored squares if none)
+    _font_data: Vec<u8>,              // font file content, read by the font until dropped (after it)
+}
+
+impl Resources {
+
+    /// Loads and initializes the game resources. The files are read and
+    /// verified, and the sounds synthesized, by a background thread while
+    /// the given window shows the loading progress. All the files that
+    /// cannot be read or are corrupted are reported at once.
+    /// * `assets` - Resources of the game (required and optional ones), as
+    ///   listed by the manifest.
+    /// * `config` - Configuration, with the font files that replace the
+    ///   asset pack font.
+    fn load(window: &mut RenderWindow, assets: Vec<Asset>, config: &Config) -> Result<Resources, String> {
+        let total = assets.len() + 1;
+        let credits = assets.iter().map(Asset::credit_line).collect::<Vec<_>>().join("\n");
+        let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut errors = Vec::new();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for asset in &assets {
+                let content = fs::read(&asset.path)
+                    .map_err(|err| format!("{}: {}", asset.path.display(), err))
+                    .and_then(|content| asset.verify(&content).map(|_| content));
+                if sender.send(ResourceData::File(asset.name.clone(), content)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(ResourceData::Samples(Resources::jingle()));
+        });
+        let mut samples = None;
+        let mut loaded = 0;
+        while loaded < total {
+            // the events are discarded, but the window must keep responding
+            while window.poll_event().is_some() {}
+            Resources::draw_progress(window, loaded as f32 / total as f32);
+            match receiver.recv_timeout(Duration::from_millis(16)) {
+                Ok(ResourceData::File(name, Ok(content))) => { files.insert(name, content); },
+                Ok(ResourceData::File(_, Err(err))) => errors.push(err),
+                Ok(ResourceData::Samples(data)) => samples = Some(data),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => panic!("The resources loading thread stopped"),
+            };
+            loaded += 1;
+        }
+        Resources::draw_progress(window, 1.0);
+        if !errors.is_empty() {
+            return Err(format!("Invalid resources: {}", errors.join(", ")));
+        }
+        // all the files and samples were received
+        let mut file = |name: &str| files.remove(name).unwrap_or_default();
+        // each font falls back to the asset pack one, then to the embedded one
+        let font_data = file("font");
+        let pack_font = if font_data.is_empty() { None } else { Font::from_memory(&font_data) };
+        if !font_data.is_empty() && pack_font.is_none() {
+            warn!("unable to load the asset pack font, falling back to the embedded one");
+        }
+        let font = config.font.as_ref().and_then(|path| Resources::load_font(path))
+            .or(pack_font)
+            .or_else(|| Font::from_memory(DEFAULT_FONT))
+            .expect("Unable to load the embedded font.");
+        let score_font = config.score_font.as_ref().and_then(|path| Resources::load_font(path));
+        let eat_buffer = SoundBuffer::from_memory(&file("eat-sound")).expect("Unable to load the eat sound.");
+        let over_buffer = SoundBuffer::from_memory(&file("over-sound")).expect("Unable to load the game over sound.");
+        let victory_buffer = SoundBuffer::from_samples(&samples.unwrap_or_default(), 1, 44_100)
+            .expect("Unable to create the victory sound.");
+        let pause_texture = match files.remove("pause-image") {
+            Some(image) => Some(Texture::from_memory(&image, &IntRect::default()).ok_or("Invalid resources: unable to load the pause image")?),
+            None => None,
+        };
+        let atlas = match (files.remove("atlas"), files.remove("atlas-regions")) {
+            (Some(image), Some(regions)) => {
+                let texture = Texture::from_memory(&image, &IntRect::default())
+                    .ok_or("Invalid resources: unable to load the atlas image")?;
+                Some(Atlas::new(texture, &String::from_utf8_lossy(&regions))?)
+            },
+            (None, None) => None,
+            _ => return Err("Invalid resources: the atlas requires both atlas and atlas-regions".to_string()),
+        };
+        let music_buffers = MUSIC_STEMS.iter()
+            .filter_map(|stem| files.remove(*stem))
+            .map(|data| SoundBuffer::from_memory(&data).ok_or("Invalid resources: unable to load a music stem"))
+            .collect::<Result<Vec<SoundBuffer>, &str>>()?;
+        Ok(Resources { font, score_font, eat_buffer, over_buffer, victory_buffer, pause_texture, music_buffers, credits, atlas, _font_data: font_data })
+    }
+
+    /// Loads the font of the given file, if possible.
+    fn load_font(path: &str) -> Option<Font> {
+        let font = Font::from_file(path);
+        if font.is_none() {
+            warn!("unable to load the font {}, falling back to the default one", path);
+        }
+        font
+    }
+
+    /// Draws the loading screen: a bar filled as the resources are loaded
+    /// (there is no font to show any text yet).
+    /// * `progress` - Fraction of the resources loaded.
+    fn draw_progress(window: &mut RenderWindow, progress: f32) {
+        let size = window.size();
+        let view = View::from_rect(&FloatRect::new(0.0, 0.0, size.x as f32, size.y as f32));
+        window.set_view(&view);
+        window.clear(&Color::BLACK);
+        let bar_size = Vector2f::new(size.x as f32 / 2.0, (size.y as f32 / 40.0).max(2.0));
+        let position = Vector2f::new(size.x as f32 / 4.0, size.y as f32 / 2.0 - bar_size.y / 2.0);
+        let mut frame = RectangleShape::with_size(bar_size);
+        frame.set_position(position);
+        frame.set_fill_color(&Color::TRANSPARENT);
+        frame.set_outline_color(&Color::WHITE);
+        frame.set_outline_thickness(1.0);
+        let mut bar = RectangleShape::with_size(Vector2f::new(bar_size.x * progress, bar_size.y));
+        bar.set_position(position);
+        bar.set_fill_color(&Color::GREEN);
+        window.draw(&bar);
+        window.draw(&frame);
+        window.display();
+    }
+
+    /// Synthesizes the samples of a short ascending jingle (mono, 44.1 kHz).
+    fn jingle() -> Vec<i16> {
+        let sample_rate = 44_100.0;
+        let note_duration = 0.12;
+        // C5, E5, G5, C6
+        let frequencies = [523.25, 659.25, 783.99, 1046.5];
+        let note_samples = (sample_rate * note_duration) as usize;
+        let mut samples = Vec::with_capacity(note_samples * frequencies.len());
+        for frequency in &frequencies {
+            for i in 0..note_samples {
+                let t = i as f32 / sample_rate;
+                // fade out each note to avoid clicks
+                let envelope = 1.0 - i as f32 / note_samples as f32;
+                let value = (2.0 * std::f32::consts::PI * frequency * t).sin() * envelope;
+                samples.push((value * 0.3 * f32::from(i16::MAX)) as i16);
+            }
+        }
+        samples
+    }
+
+}
+
+
+trait Game {
+
+    /// Runs the game main loop.
+    fn run(&mut self);
+
+    /// Handles player inputs.
+    fn process_events(&mut self);
+
+    /// Updates the game status by a single fixed time step.
+    fn update(&mut self);
+
+    /// Renders graphics.
+    fn render(&mut self);
+
+}
+
+trait Graphic {
+
+    /// Adds the graphic element to the batch of quads to draw.
+    fn draw(&self, batch: &mut Batch, color: &Color);
+
+}
+
+
+/// Converts grid cells into window coordinates.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    origin: Vector2f,       // window position of the top left cell
+    cell_size: f32,         // size of each cell (the width of the hexagons)
+    topology: Topology,     // shape of the cells
+}
+
+impl Layout {
+
+    /// Gets the size of a board of the given shape, in cell widths.
+    fn extent(topology: Topology, columns: i32, rows: i32) -> Vector2f {
+        match topology {
+            Topology::Square | Topology::Diagonal => Vector2f::new(columns as f32, rows as f32),
+            // the odd rows stick out by half a cell, and the first and last
+            // rows by a quarter of a hexagon
+            Topology::Hex => Vector2f::new(columns as f32 + 0.5, (rows - 1) as f32 * HEX_ROW_HEIGHT + 1.0 / HEX_ROW_HEIGHT),
+        }
+    }
+
+    /// Gets the window size of the given board.
+    fn board_size(&self, grid: Grid) -> Vector2f {
+        Layout::extent(self.topology, grid.columns, grid.rows) * self.cell_size
+    }
+
+    /// Gets the window position of the given cell: the top left corner of
+    /// the square as wide as the cell and centered on it.
+    fn position(&self, cell: GridPos) -> Vector2f {
+        match self.topology {
+            Topology::Square | Topology::Diagonal => self.origin + Vector2f::new(cell.x as f32, cell.y as f32) * self.cell_size,
+            Topology::Hex => {
+                let x = cell.x as f32 + (cell.y & 1) as f32 / 2.0;
+                let y = cell.y as f32 * HEX_ROW_HEIGHT + (1.0 / HEX_ROW_HEIGHT - 1.0) / 2.0;
+                self.origin + Vector2f::new(x, y) * self.cell_size
+            },
+        }
+    }
+
+    /// Gets the angle (in degrees, clockwise from up) of a step in the given
+    /// direction on the window.
+    fn heading(&self, direction: Direction) -> f32 {
+        let (dx, dy) = direction.delta();
+        let (x, y) = match self.topology {
+            // the diagonal neighbours of a hexagon are half a cell aside
+            Topology::Hex if dy != 0 => (dx as f32 / 2.0, dy as f32 * HEX_ROW_HEIGHT),
+            _ => (dx as f32, dy as f32),
+        };
+        x.atan2(-y).to_degrees()
+    }
+
+    /// Gets the window position of the center of the given cell.
+    fn center(&self, cell: GridPos) -> Vector2f {
+        let half_cell = self.cell_size / 2.0;
+        self.position(cell) + Vector2f::new(half_cell, half_cell)
+    }
+
+}
+
+
+/// Batch of quads drawn with a draw call for the atlas images and another
+/// one for the colored quads.
+struct Batch<'a> {
+    vertices: VertexArray,                  // vertices of all the colored quads
+    sprites: VertexArray,                   // vertices of all the quads textured from the atlas
+    atlas: Option<&'a Atlas>,               // images of the entities (drawn as colored squares if none)
+    animations: HashMap<String, Animation>, // current frame of each atlas image, by name
+    fog: Option<(Grid, GridPos)>,           // grid and cell around which the cells are shown (all if none)
+    layout: Layout,                         // conversion from grid cells to window coordinates
+    tongue: f32,                            // seconds left with the tongue out (of the head drawn without atlas)
+    style: CellStyle,                       // look of the colored cells
+}
+
+impl<'a> Batch<'a> {
+
+    /// Creates a new empty batch.
+    fn new(layout: Layout, atlas: Option<&'a Atlas>, style: CellStyle) -> Batch<'a> {
+        Batch {
+            vertices: VertexArray::new(PrimitiveType::Quads, 0),
+            sprites: VertexArray::new(PrimitiveType::Quads, 0),
+            atlas,
+            animations: atlas.map(Atlas::animations).unwrap_or_default(),
+            fog: None,
+            layout,
+            tongue: 0.0,
+            style,
+        }
+    }
+
+    /// Removes all the quads.
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.sprites.clear();
+    }
+
+    /// Advances all the animations by the given frame time.
+    fn animate(&mut self, frame_time: Time) {
+        for animation in self.animations.values_mut() {
+            animation.update(frame_time.as_seconds());
+        }
+        self.tongue = (self.tongue - frame_time.as_seconds()).max(0.0);
+    }
+
+    /// Starts over the animation of the atlas image with the given name.
+    fn restart_animation(&mut self, name: &str) {
+        if let Some(animation) = self.animations.get_mut(name) {
+            animation.restart();
+        }
+    }
+
+    /// Flicks the tongue of the snake head: its atlas animation starts over,
+    /// or the tongue of the head drawn without atlas sticks out for a while.
+    fn flick_tongue(&mut self) {
+        self.restart_animation("snake-head");
+        self.tongue = TONGUE_SECONDS;
+    }
+
+    /// Draws all the quads, the colored ones on top of the atlas images.
+    fn draw<T: RenderTarget>(&self, target: &mut T) {
+        if let Some(atlas) = self.atlas {
+            let states = RenderStates { texture: Some(&atlas.texture), ..Default::default() };
+            target.draw_with_renderstates(&self.sprites, states);
+        }
+        target.draw(&self.vertices);
+    }
+
+    /// Returns true if the given cell is hidden by the fog of war.
+    fn hidden(&self, cell: GridPos) -> bool {
+        match self.fog {
+            Some((grid, center)) => grid.ring(center, cell) > FOG_RADIUS,
+            None => false
+        }
+    }
+
+    /// Adds a quad with the given window position and size.
+    fn add_quad(&mut self, position: Vector2f, size: Vector2f, color: Color) {
+        let corners = [
+            position,
+            position + Vector2f::new(size.x, 0.0),
+            position + size,
+            position + Vector2f::new(0.0, size.y),
+        ];
+        for corner in &corners {
+            self.vertices.append(&Vertex::with_pos_color(*corner, color));
+        }
+    }
+
+    /// Adds a line between the given window positions, as a quad of the
+    /// given thickness.
+    fn add_line(&mut self, from: Vector2f, to: Vector2f, thickness: f32, color: Color) {
+        let along = to - from;
+        let length = (along.x * along.x + along.y * along.y).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        // half the thickness on each side of the line
+        let across = Vector2f::new(-along.y, along.x) * (thickness / 2.0 / length);
+        for corner in &[from + across, to + across, to - across, from - across] {
+            self.vertices.append(&Vertex::with_pos_color(*corner, color));
+        }
+    }
+
+    /// Adds a hexagon (pointy side up) with the given window center and
+    /// radius, from the center to a corner.
+    fn add_hex(&mut self, center: Vector2f, radius: f32, color: Color) {
+        // the corners clockwise from the top one, drawn as two quads
+        let corner = |index: usize| {
+            let angle = (60.0 * index as f32 - 90.0).to_radians();
+            center + Vector2f::new(angle.cos(), angle.sin()) * radius
+        };
+        for index in &[0, 1, 2, 3, 3, 4, 5, 0] {
+            self.vertices.append(&Vertex::with_pos_color(corner(*index), color));
+        }
+    }
+
+    /// Adds a square (or a hexagon) filling the given cell, drawn with the
+    /// current `CellStyle` (outline, gap and rounded corners).
+    fn add_cell(&mut self, cell: GridPos, color: &Color) {
+        self.add_scaled_cell(cell, 1.0, color);
+    }
+
+    /// Adds a square (or a hexagon) centered on the given cell and scaled by
+    /// the given factor, with the outline of the cell style.
+    fn add_scaled_cell(&mut self, cell: GridPos, scale: f32, color: &Color) {
+        if self.hidden(cell) {
+            return;
+        }
+        if self.style.outline > 0.0 {
+            self.add_shape(cell, scale, self.style.outline, self.style.outline_color);
+        }
+        self.add_shape(cell, scale, 0.0, *color);
+    }
+
+    /// Adds a square (or a hexagon) centered on the given cell and scaled by
+    /// the given factor, without outline.
+    fn add_plain_cell(&mut self, cell: GridPos, scale: f32, color: Color) {
+        if !self.hidden(cell) {
+            self.add_shape(cell, scale, 0.0, color);
+        }
+    }
+
+    /// Adds the cells of a snake moving from its cells before the last
+    /// update (if known), each one drawn the given fraction (from 0 to 1) of
+    /// the way to its current cell.
+    fn add_moving_snake(&mut self, snake: &Snake, previous: Option<&Snake>, progress: f32, color: &Color) {
+        let mut from = previous.into_iter().flat_map(Snake::segments);
+        for segment in snake.segments() {
+            let start = from.next().unwrap_or(segment);
+            self.add_moving_cell(*start, *segment, progress, color);
+        }
+    }
+
+    /// Adds a square (or a hexagon) the given fraction (from 0 to 1) of the
+    /// way from a cell to the next one, with the outline of the cell style.
+    /// The cells apart (across the edges or through the portals) are not
+    /// moved between.
+    fn add_moving_cell(&mut self, from: GridPos, to: GridPos, progress: f32, color: &Color) {
+        if self.hidden(to) {
+            return;
+        }
+        let (start, end) = (self.layout.position(from), self.layout.position(to));
+        let step = end - start;
+        let position = if (step.x * step.x + step.y * step.y).sqrt() <= self.layout.cell_size * 1.5 {
+            start + step * progress
+        } else {
+            end
+        };
+        if self.style.outline > 0.0 {
+            self.add_shape_at(position, 1.0, self.style.outline, self.style.outline_color);
+        }
+        self.add_shape_at(position, 1.0, 0.0, *color);
+    }
+
+    /// Adds the shape of the given cell scaled by the given factor, grown
+    /// by the given number of pixels on each side. The shape leaves the gap
+    /// of the cell style, and rounds its corners on square cells.
+    fn add_shape(&mut self, cell: GridPos, scale: f32, grow: f32, color: Color) {
+        self.add_shape_at(self.layout.position(cell), scale, grow, color);
+    }
+
+    /// Adds the shape of a cell at the given window position, as
+    /// `add_shape` does.
+    fn add_shape_at(&mut self, position: Vector2f, scale: f32, grow: f32, color: Color) {
+        let cell_size = self.layout.cell_size;
+        // the gap never takes more than half the cell
+        let gap = self.style.gap.min(cell_size / 2.0);
+        if self.layout.topology == Topology::Hex {
+            // the hexagons are as wide as the cells
+            let radius = (cell_size - gap) * scale / 2.0 / HEX_ROW_HEIGHT;
+            self.add_hex(position + Vector2f::new(cell_size, cell_size) / 2.0, radius + grow, color);
+            return;
+        }
+        let size = (cell_size - gap) * scale;
+        let position = position + Vector2f::new(cell_size - size, cell_size - size) / 2.0;
+        let radius = self.style.corner_radius * size;
+        let grow_offset = Vector2f::new(grow, grow);
+        self.add_rounded_quad(position - grow_offset, Vector2f::new(size, size) + grow_offset * 2.0, radius + grow, color);
+    }
+
+    /// Adds a quad with the given window position and size, its corners
+    /// rounded with the given radius.
+    fn add_rounded_quad(&mut self, position: Vector2f, size: Vector2f, radius: f32, color: Color) {
+        let radius = radius.min(size.x / 2.0).min(size.y / 2.0);
+        if radius < 1.0 {
+            self.add_quad(position, size, color);
+            return;
+        }
+        // a band across the whole width, and the sides between the corners
+        self.add_quad(position + Vector2f::new(0.0, radius), Vector2f::new(size.x, size.y - 2.0 * radius), color);
+        self.add_quad(position + Vector2f::new(radius, 0.0), Vector2f::new(size.x - 2.0 * radius, radius), color);
+        self.add_quad(position + Vector2f::new(radius, size.y - radius), Vector2f::new(size.x - 2.0 * radius, radius), color);
+        // each corner is a quarter of a circle, drawn as two quads fanning
+        // out of its center
+        let corners = [
+            (position + Vector2f::new(radius, radius), 180.0),
+            (position + Vector2f::new(size.x - radius, radius), 270.0),
+            (position + Vector2f::new(size.x - radius, size.y - radius), 0.0),
+            (position + Vector2f::new(radius, size.y - radius), 90.0),
+        ];
+        for (center, start) in &corners {
+            let arc = |step: usize| {
+                let angle = (start + 22.5 * step as f32).to_radians();
+                *center + Vector2f::new(angle.cos(), angle.sin()) * radius
+            };
+            for quad in &[[*center, arc(0), arc(1), arc(2)], [*center, arc(2), arc(3), arc(4)]] {
+                for corner in quad {
+                    self.vertices.append(&Vertex::with_pos_color(*corner, color));
+                }
+            }
+        }
+    }
+
+    /// Adds the current frame of the given atlas region filling the given
+    /// cell, returning whether the atlas has such a region.
+    /// * `tint` - Color multiplied with the image (white to keep it as is).
+    fn add_sprite(&mut self, cell: GridPos, region: &str, tint: Color) -> bool {
+        self.add_turned_sprite(cell, region, tint, 0.0)
+    }
+
+    /// Adds the current frame of the given atlas region filling the given
+    /// cell, turned clockwise around its center by the given angle (in
+    /// degrees), returning whether the atlas has such a region.
+    fn add_turned_sprite(&mut self, cell: GridPos, region: &str, tint: Color, angle: f32) -> bool {
+        let region = match self.animations.get(region) {
+            Some(_) if self.hidden(cell) => return true,
+            Some(animation) => animation.frame(),
+            None => return false
+        };
+        let center = self.layout.center(cell);
+        let half_size = self.layout.cell_size / 2.0;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let turn = |x: f32, y: f32| center + Vector2f::new(x * cos - y * sin, x * sin + y * cos) * half_size;
+        let (left, top) = (region.left as f32, region.top as f32);
+        let (right, bottom) = (left + region.width as f32, top + region.height as f32);
+        let corners = [
+            (turn(-1.0, -1.0), Vector2f::new(left, top)),
+            (turn(1.0, -1.0), Vector2f::new(right, top)),
+            (turn(1.0, 1.0), Vector2f::new(right, bottom)),
+            (turn(-1.0, 1.0), Vector2f::new(left, bottom)),
+        ];
+        for (corner, tex_coords) in &corners {
+            self.sprites.append(&Vertex::new(*corner, tint, *tex_coords));
+        }
+        true
+    }
+
+    /// Adds the image of the head of the given snake, facing its direction
+    /// (the image facing up), or a colored cell if the atlas has no such
+    /// image.
+    fn add_head(&mut self, snake: &Snake, color: &Color) {
+        let head = snake.head_position();
+        let angle = snake.direction().map_or(0.0, |direction| self.layout.heading(direction));
+        if self.add_turned_sprite(head, "snake-head", Color::WHITE, angle) {
+            return;
+        }
+        self.add_cell(head, color);
+        if self.hidden(head) {
+            return;
+        }
+        // the eyes look toward the direction of the snake, side by side
+        let cell_size = self.layout.cell_size;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (forward, side) = (Vector2f::new(sin, -cos), Vector2f::new(cos, sin));
+        let center = self.layout.center(head);
+        let (eye, pupil) = (cell_size * 0.24, cell_size * 0.12);
+        for side in &[side, -side] {
+            let eye_center = center + forward * (cell_size * 0.12) + *side * (cell_size * 0.2);
+            self.add_quad(eye_center - Vector2f::new(eye, eye) / 2.0, Vector2f::new(eye, eye), Color::WHITE);
+            let pupil_center = eye_center + forward * (cell_size * 0.05);
+            self.add_quad(pupil_center - Vector2f::new(pupil, pupil) / 2.0, Vector2f::new(pupil, pupil), Color::BLACK);
+        }
+        if self.tongue > 0.0 {
+            let (from, to) = (center + forward * (cell_size * 0.5), center + forward * (cell_size * 0.8));
+            self.add_line(from, to, (cell_size / 10.0).max(1.0), Color::RED);
+        }
+    }
+
+    /// Adds the image of the given atlas region filling the given cell, or
+    /// a colored square if the atlas has no such region.
+    fn add_image(&mut self, cell: GridPos, region: &str, color: &Color) {
+        if !self.add_sprite(cell, region, Color::WHITE) {
+            self.add_cell(cell, color);
+        }
+    }
+
+    /// Adds the outline of the given cell.
+    #[cfg(feature = "debug-tools")]
+    fn add_outline(&mut self, cell: GridPos, color: Color) {
+        let position = self.layout.position(cell);
+        let size = self.layout.cell_size;
+        let thickness = 2.0;
+        self.add_quad(position, Vector2f::new(size, thickness), color);
+        self.add_quad(position + Vector2f::new(0.0, size - thickness), Vector2f::new(size, thickness), color);
+        self.add_quad(position, Vector2f::new(thickness, size), color);
+        self.add_quad(position + Vector2f::new(size - thickness, 0.0), Vector2f::new(thickness, size), color);
+    }
+
+    /// Adds a square (or hexagonal) ring filling the given cell.
+    fn add_ring(&mut self, cell: GridPos, color: &Color) {
+        if self.hidden(cell) {
+            return;
+        }
+        let position = self.layout.position(cell);
+        let size = self.layout.cell_size;
+        if self.layout.topology == Topology::Hex {
+            let radius = size / 2.0 / HEX_ROW_HEIGHT;
+            self.add_hex(self.layout.center(cell), radius, *color);
+            self.add_hex(self.layout.center(cell), radius * 0.75, Color::BLACK);
+            return;
+        }
+        let thickness = size / 8.0;
+        let inset = Vector2f::new(thickness, thickness);
+        self.add_quad(position, Vector2f::new(size, size), *color);
+        self.add_quad(position + inset, Vector2f::new(size, size) - inset * 2.0, Color::BLACK);
+    }
+
+}
+
+
+impl Graphic for Snake {
+
+    /// Draws all the snake segments.
+    fn draw(&self, batch: &mut Batch, color: &Color) {
+        // the first segment is the head
+        batch.add_head(self, color);
+        for segment in self.segments().skip(1) {
+            batch.add_image(*segment, "snake-body", color);
+        }
+    }
+
+}
+
+impl Graphic for Portal {
+
+    /// Draws both ends of the portal.
+    fn draw(&self, batch: &mut Batch, color: &Color) {
+        // portals are rings to distinguish them from the other entities
+        for position in &self.positions {
+            // the image is tinted with the color of the pair
+            if !batch.add_sprite(*position, "portal", *color) {
+                batch.add_ring(*position, color);
+            }
+        }
+    }
+
+}
+
+impl Graphic for Enemy {
+
+    /// Draws the enemy.
+    fn draw(&self, batch: &mut Batch, color: &Color) {
+        batch.add_image(self.position(), "enemy", color);
+    }
+
+}
+
+
+/// Statistics of the games played in the current session.
+#[derive(Debug, Default)]
+struct Stats {
+    games: u32,         // number of games played
+    victories: u32,     // number of games won filling the whole board
+    best_score: u32,    // highest score
+}
+
+impl Stats {
+
+    /// Records the outcome of a game.
+    fn record(&mut self, score: u32, victory: bool) {
+        self.games += 1;
+        if victory {
+            self.victories += 1;
+        }
+        self.best_score = self.best_score.max(score);
+    }
+
+}
+
+
+/// Outcome of a match, from the player point of view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MatchResult {
+    Won,
+    Lost,
+    Draw,
+}
+
+
+/// Best-of-N match between the player and the rival snake of the versus
+/// mode, each round played as a run.
+#[derive(Debug)]
+struct Match {
+    rounds: u32,                // maximum number of rounds
+    scores: Vec<(u32, u32)>,    // player and rival score of each round played
+}
+
+impl Match {
+
+    /// Creates a match of the given number of rounds.
+    fn new(rounds: u32) -> Match {
+        Match { rounds, scores: Vec::new() }
+    }
+
+    /// Records the scores of the round just played.
+    fn record(&mut self, score: u32, rival_score: u32) {
+        self.scores.push((score, rival_score));
+    }
+
+    /// Gets the number of rounds won by the player and by the rival (ties
+    /// are won by nobody).
+    fn wins(&self) -> (u32, u32) {
+        let won = self.scores.iter().filter(|(score, rival)| score > rival).count() as u32;
+        let lost = self.scores.iter().filter(|(score, rival)| score < rival).count() as u32;
+        (won, lost)
+    }
+
+    /// Gets the outcome of the match, once won by either side or once all
+    /// its rounds are played.
+    fn result(&self) -> Option<MatchResult> {
+        let (won, lost) = self.wins();
+        let majority = self.rounds / 2 + 1;
+        if won < majority && lost < majority && (self.scores.len() as u32) < self.rounds {
+            return None;
+        }
+        Some(if won > lost {
+            MatchResult::Won
+        } else if won < lost {
+            MatchResult::Lost
+        } else {
+            MatchResult::Draw
+        })
+    }
+
+    /// Gets the scoreboard of the rounds played, one line per round
+    /// followed by the rounds won by each side.
+    fn scoreboard(&self) -> String {
+        let mut scoreboard = String::new();
+        for (i, (score, rival)) in self.scores.iter().enumerate() {
+            let _ = writeln!(scoreboard, "ROUND {}   {} - {}", i + 1, score, rival);
+        }
+        let (won, lost) = self.wins();
+        let _ = write!(scoreboard, "\nYOU {} - {} AI", won, lost);
+        scoreboard
+    }
+
+}
+
+
+/// Multiplier of the game time, applied to the fixed time step accumulator
+/// of the game loop to slow down or speed up the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeScale(f32);
+
+impl TimeScale {
+
+    /// Slowest supported time scale.
+    pub const MIN: f32 = 0.25;
+    /// Fastest supported time scale.
+    pub const MAX: f32 = 4.0;
+
+    /// Creates a new time scale with the given factor, which must be within
+    /// the supported range.
+    pub fn new(factor: f32) -> Result<TimeScale, &'static str> {
+        if (TimeScale::MIN..=TimeScale::MAX).contains(&factor) {
+            Ok(TimeScale(factor))
+        } else {
+            Err("The time scale must be between 0.25 and 4")
+        }
+    }
+
+    /// Gets the factor the game time is multiplied by.
+    pub fn factor(self) -> f32 {
+        self.0
+    }
+
+    /// Scales the given amount of real time.
+    pub fn scale(self, time: Time) -> Time {
+        time * self.0
+    }
+
+}
+
+impl Default for TimeScale {
+
+    /// Creates a time scale that leaves the game time unchanged.
+    fn default() -> TimeScale {
+        TimeScale(1.0)
+    }
+
+}
+
+
+/// Timing diagnostics shown by the debug overlay.
+struct Diagnostics {
+    frames: u32,        // frames rendered since the last sample
+    ticks: u32,         // board ticks at the last sample
+    elapsed: Time,      // time elapsed since the last sample
+    render_fps: f32,    // frames rendered per second
+    tick_rate: f32,     // board ticks per second
+    update_time: Time,  // time spent by the last update
+}
+
+impl Diagnostics {
+
+    /// Creates new diagnostics with all the rates set to zero.
+    fn new() -> Diagnostics {
+        Diagnostics {
+            frames: 0,
+            ticks: 0,
+            elapsed: Time::ZERO,
+            render_fps: 0.0,
+            tick_rate: 0.0,
+            update_time: Time::ZERO,
+        }
+    }
+
+    /// Records a rendered frame, the rates are sampled every second.
+    /// * `frame_time` - Time elapsed since the previous frame.
+    /// * `ticks` - Number of ticks of the current run.
+    fn record_frame(&mut self, frame_time: Time, ticks: u32) {
+        self.frames += 1;
+        self.elapsed += frame_time;
+        if self.elapsed >= Time::seconds(1.0) {
+            let seconds = self.elapsed.as_seconds();
+            self.render_fps = self.frames as f32 / seconds;
+            // the ticks start again from zero when a new run starts
+            self.tick_rate = ticks.saturating_sub(self.ticks) as f32 / seconds;
+            self.frames = 0;
+            self.ticks = ticks;
+            self.elapsed = Time::ZERO;
+        }
+    }
+
+}
+
+
+/// Camera showing the region of the board around the snake head, when
+/// the board is larger than the window.
+struct Camera {
+    view: View,             // region of the board shown in the viewport
+    minimap: Option<View>,  // whole board shown in a corner of the viewport
+    board_size: Vector2f,   // size of the board (in pixels)
+}
+
+impl Camera {
+
+    /// Creates a new camera showing the board, its viewport must be set
+    /// by resizing the camera.
+    fn new(board_size: Vector2f, minimap: bool) -> Camera {
+        let view = View::from_rect(&FloatRect::new(0.0, 0.0, board_size.x, board_size.y));
+        let minimap = if minimap { Some(view.to_owned()) } else { None };
+        Camera { view, minimap, board_size }
+    }
+
+    /// Shows the board within the given viewport of the window.
+    /// * `zoom` - Scale factor of the board.
+    fn resize(&mut self, viewport: FloatRect, window_size: Vector2f, zoom: f32) {
+        // viewports are expressed as a factor of the window size
+        let normalize = |area: FloatRect| FloatRect::new(
+            area.left / window_size.x,
+            area.top / window_size.y,
+            area.width / window_size.x,
+            area.height / window_size.y);
+        self.view.set_size(Vector2f::new(viewport.width, viewport.height) / zoom);
+        self.view.set_viewport(&normalize(viewport));
+        // the minimap is in the bottom right corner of the viewport
+        if let Some(minimap) = self.minimap.as_mut() {
+            let width = viewport.width / 4.0;
+            let height = width * self.board_size.y / self.board_size.x;
+            let margin = 8.0;
+            let area = FloatRect::new(
+                viewport.left + viewport.width - width - margin,
+                viewport.top + viewport.height - height - margin,
+                width,
+                height);
+            minimap.set_viewport(&normalize(area));
+        }
+    }
+
+    /// Moves the camera towards the given board position, without showing
+    /// what is beyond the board edges.
+    fn follow(&mut self, target: Vector2f) {
+        let center = |target: f32, visible: f32, board: f32| {
+            if board <= visible {
+                // the whole board is visible
+                board / 2.0
+            } else {
+                target.max(visible / 2.0).min(board - visible / 2.0)
+            }
+        };
+        let visible = self.view.size();
+        let x = center(target.x, visible.x, self.board_size.x);
+        let y = center(target.y, visible.y, self.board_size.y);
+        self.view.set_center(Vector2f::new(x, y));
+    }
+
+}
+
+
+/// Metadata of the game window: its icon and a title showing the game mode
+/// and the score, changed only when different.
+struct WindowMeta {
+    title: String,  // title currently shown
+}
+
+impl WindowMeta {
+
+    /// Sets the icon of the given window and gets its metadata.
+    fn new(window: &mut RenderWindow) -> WindowMeta {
+        match Image::from_memory(ICON) {
+            Some(icon) => window.set_icon(icon.size().x, icon.size().y, icon.pixel_data()),
+            None => warn!("unable to load the window icon"),
+        };
+        WindowMeta { title: String::from("Snake") }
+    }
+
+    /// Shows the given title, unless already shown.
+    fn set_title(&mut self, window: &mut RenderWindow, title: String) {
+        if title != self.title {
+            window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+}
+
+
+#[derive(Debug)]
+enum State {
+    Pause,
+    Play,
+    GameOver,
+    Victory,
+}
+
+
+/// Screens shown over the game, each one taking all the input while on
+/// top of the others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Screen {
+    Splash,     // title shown at startup
+    Credits,    // credits of the game and its resources
+    Scoreboard, // scores of the match rounds, between two rounds
+    MatchOver,  // winner of the match
+    Lobby,      // rooms of the networked play, until the run of a match starts
+}
+
+impl Screen {
+
+    /// Gets the number of seconds the screen is shown before closing by
+    /// itself, if it does.
+    fn duration(self) -> Option<f32> {
+        match self {
+            Screen::Splash => Some(SPLASH_SECONDS),
+            Screen::Credits | Screen::Scoreboard | Screen::MatchOver | Screen::Lobby => None,
+        }
+    }
+
+    /// Gets the opacity of the screen (from 0 to 1) after being shown for
+    /// the given number of seconds: it fades in, and out if it closes by
+    /// itself.
+    fn opacity(self, elapsed: f32) -> f32 {
+        let fade_in = (elapsed / FADE_SECONDS).min(1.0);
+        let fade_out = self.duration().map_or(1.0, |duration| ((duration - elapsed) / FADE_SECONDS).clamp(0.0, 1.0));
+        fade_in.min(fade_out)
+    }
+
+}
+
+
+/// Steps of the tutorial shown on the first run, each one completed by
+/// the action it explains.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TutorialStep {
+    Turn,   // turn the snake
+    Eat,    // eat the food
+    Pause,  // pause the game
+}
+
+impl TutorialStep {
+
+    /// Gets the message explaining the step, with the keys of the given
+    /// layout.
+    fn message(self, layout: KeyLayout) -> &'static str {
+        match self {
+            TutorialStep::Turn => match layout {
+                KeyLayout::Wasd => "USE W A S D TO TURN THE SNAKE",
+                KeyLayout::Hjkl => "USE H J K L TO TURN THE SNAKE",
+                KeyLayout::Numpad => "USE THE NUMPAD 8 4 6 2 TO TURN THE SNAKE",
+                KeyLayout::Esdf => "USE E S D F TO TURN THE SNAKE",
+            },
+            TutorialStep::Eat => "EAT THE FOOD TO GROW\nTHE SNAKE WRAPS AROUND THE EDGES",
+            TutorialStep::Pause => "PRESS P TO PAUSE",
+        }
+    }
+
+    /// Gets the step that follows this one in the given mode, if any.
+    fn next(self, mode: Mode) -> Option<TutorialStep> {
+        match self {
+            // there is no food in Tron mode
+            TutorialStep::Turn if !mode.variant().has_food() => Some(TutorialStep::Pause),
+            TutorialStep::Turn => Some(TutorialStep::Eat),
+            TutorialStep::Eat => Some(TutorialStep::Pause),
+            TutorialStep::Pause => None,
+        }
+    }
+
+}
+
+
+struct SnakeGame<'a> {
+    window: RenderWindow,
+    window_meta: WindowMeta,
+    settings: Settings,
+    borderless: bool,
+    monitor: Option<Monitor>,
+    vsync: bool,
+    frame_limit: u32,
+    board: Board,
+    inputs: Vec<(u32, Input)>,
+    snake_color: Color,
+    food_color: Color,
+    poison_color: Color,
+    enemy_color: Color,
+    time_per_frame: Time,
+    batch: Batch<'a>,
+    camera: Camera,
+    hud_view: View,
+    base_size: Vector2u,
+    entity_size: u32,
+    text_size: u32,
+    ui_scale: f32,
+    hud_scale: f32,
+    auto_pause: bool,
+    practice: bool,
+    demo: bool,
+    demo_wait: u32,
+    adaptive: Option<Rules>,
+    history: VecDeque<Board>,
+    rewinding: bool,
+    rewind_text: Text<'a>,
+    slow_motion: bool,
+    slow_motion_left: Time,
+    near_miss: bool,
+    slow_motion_scale: f32,
+    time_scale: TimeScale,
+    border: RectangleShape<'a>,
+    state: State,
+    score_text: Text<'a>,
+    score_string: String,
+    rival_text: Text<'a>,
+    timer_text: Text<'a>,
+    latency_text: Text<'a>,
+    summary_text: Text<'a>,
+    over_text: Text<'a>,
+    victory_text: Text<'a>,
+    quit_text: Text<'a>,
+    dim: RectangleShape<'a>,
+    snapshot: Option<RenderTexture>,
+    blur: Option<Shader<'static>>,
+    quit_dialog: bool,
+    hovered: Option<Key>,
+    key_layout: KeyLayout,
+    keyboard: Keyboard,
+    swipe: Option<Swipe>,
+    eat_sound: Sound<'a>,
+    over_sound: Sound<'a>,
+    victory_sound: Sound<'a>,
+    music: Vec<Sound<'a>>,
+    intensity: usize,
+    stats: Stats,
+    pause_sprite: Option<Sprite<'a>>,
+    pause_text: Text<'a>,
+    run_text: Text<'a>,
+    back_color: Color,
+    text_color: Color,
+    diagnostics: Diagnostics,
+    debug_text: Text<'a>,
+    debug_overlay: bool,
+    redraw: bool,
+    console: Option<String>,
+    console_message: String,
+    console_text: Text<'a>,
+    chat: Option<String>,
+    chat_lines: VecDeque<(String, f32)>,
+    trail_fade: bool,
+    trail: VecDeque<(GridPos, f32)>,
+    chat_text: Text<'a>,
+    tutorial: Option<TutorialStep>,
+    tutorial_text: Text<'a>,
+    screens: Vec<Screen>,
+    screen_time: f32,
+    splash_text: Text<'a>,
+    credits_text: Text<'a>,
+    unranked: bool,
+    cheat_codes: CheatCodes,
+    versus_match: Option<Match>,
+    match_text: Text<'a>,
+    session: Option<Session>,
+    lobby_text: Text<'a>,
+    heatmap: bool,
+    summary_card: bool,
+    card_pending: bool,
+    utc_offset: i32,
+    hooks: Vec<Box<dyn GameHooks>>,
+    #[cfg(feature = "debug-tools")]
+    hitboxes: bool,
+    #[cfg(feature = "debug-tools")]
+    heatmap_shown: bool,
+    #[cfg(feature = "online")]
+    leaderboard: Option<Leaderboard>,
+    #[cfg(feature = "online")]
+    leaderboard_text: Text<'a>,
+    #[cfg(feature = "online")]
+    leaderboard_open: bool,
+    #[cfg(feature = "discord")]
+    presence: Option<Presence>,
+}
+
+impl<'a> SnakeGame<'a> {
+
+    /// Create a new Snake Game.
+    /// * `window` - Window opened with `open_window`.
+    fn new(config: &Config, resources: &'a Resources, settings: Settings, mut window: RenderWindow) -> SnakeGame<'a> {
+        let window_size = SnakeGame::window_size(config);
+        // define the viewport where the snake can run
+        let margin = config.entity_size as f32 * config.ui_scale;
+        let viewport = SnakeGame::viewport(Vector2f::new(window_size.x as f32, window_size.y as f32), margin);
+        debug!("viewport = {:?}", viewport);
+        // the grid where the snake can run, it fills the viewport unless
+        // its size is given explicitly
+        let topology = config.topology;
+        let columns = config.board_columns.map(|columns| columns.max(1));
+        let rows = config.board_rows.map(|rows| rows.max(1));
+        // size of the board in cell widths (the hex boards being a bit wider)
+        let extent = |columns: u32, rows: u32| Layout::extent(topology, columns as i32, rows as i32);
+        let cell_size = match (config.cell_size, columns, rows) {
+            (Some(cell_size), _, _) => cell_size,
+            // fit the whole board within the viewport
+            (None, Some(columns), Some(rows)) => {
+                let extent = extent(columns, rows);
+                ((viewport.width / extent.x).min(viewport.height / extent.y) as u32).max(1)
+            },
+            (None, Some(columns), None) => ((viewport.width / extent(columns, 1).x) as u32).max(1),
+            (None, None, Some(rows)) => ((viewport.height / extent(1, rows).y) as u32).max(1),
+            (None, None, None) => config.entity_size,
+        };
+        // fill the viewport with as many cells as fit
+        let (fit_columns, fit_rows) = match topology {
+            Topology::Square | Topology::Diagonal => (viewport.width as u32 / cell_size, viewport.height as u32 / cell_size),
+            Topology::Hex => {
+                let size = Vector2f::new(viewport.width, viewport.height) / cell_size as f32;
+                let rows = ((size.y - 1.0 / HEX_ROW_HEIGHT) / HEX_ROW_HEIGHT) as u32 + 1;
+                ((size.x - 0.5) as u32, rows - rows % 2)
+            },
+        };
+        let grid = Grid::with_topology(
+            columns.unwrap_or(fit_columns.max(1)) as i32,
+            rows.unwrap_or(fit_rows.max(1)) as i32,
+            topology);
+        let layout = Layout {
+            origin: Vector2f::new(0.0, 0.0),
+            cell_size: cell_size as f32,
+            topology,
+        };
+        // the camera shows the board within the viewport
+        let board_size = layout.board_size(grid);
+        let camera = Camera::new(board_size, config.minimap);
+        let hud_view = window.default_view().to_owned();
+        let window_meta = WindowMeta::new(&mut window);
+
+        // create the border to separate the viewport from the top window section
+        // with the score (placed with the rest of the HUD)
+        let mut border = RectangleShape::new();
+        border.set_fill_color(&Color::TRANSPARENT);
+        border.set_outline_color(&Color::WHITE);
+        border.set_outline_thickness(1.0);
+
+        // create text with the given string and default configuration properties
+        let create_text = |content: &str| {
+            let mut text = Text::default();
+            text.set_font(&resources.font);
+            text.set_character_size(config.text_size);
+            text.set_fill_color(&config.text_color);
+            text.set_string(content);
+            text
+        };
+        // initialize the score, game over and victory texts
+        let mut score_text = create_text("0");
+        score_text.set_font(resources.score_font.as_ref().unwrap_or(&resources.font));
+        let mut rival_text = create_text("");
+        rival_text.set_fill_color(&RIVAL_COLOR);
+        let timer_text = create_text(&format_time(0));
+        let latency_text = create_text("");
+        let summary_text = create_text("");
+        let over_text = create_text("GAME OVER");
+        let rewind_text = create_text("<< REWIND");
+        let pause_text = create_text("PAUSED - P TO RESUME");
+        let run_text = create_text("");
+        let victory_text = create_text("YOU WIN");
+        let quit_text = create_text("QUIT? Y/N");
+        let debug_text = create_text("");
+        let console_text = create_text("");
+        let chat_text = create_text("");
+        let tutorial_text = create_text("");
+        let splash_text = create_text("SNAKE");
+        let credits = format!("CREDITS\n\nSNAKE {} BY GLIDERKITE\nBUILT WITH SFML (ZLIB LICENSE)\n\n{}",
+            env!("CARGO_PKG_VERSION"), resources.credits.to_uppercase());
+        let credits_text = create_text(&credits);
+        let match_text = create_text("");
+        let lobby_text = create_text("");
+        #[cfg(feature = "online")]
+        let leaderboard_text = create_text("");
+        // the board is dimmed behind the dialogs
+        let mut dim = RectangleShape::new();
+        dim.set_fill_color(&Color::rgba(0, 0, 0, 160));
+
+        // init the audio
+        let eat_sound = Sound::with_buffer(&resources.eat_buffer);
+        let over_sound = Sound::with_buffer(&resources.over_buffer);
+        let victory_sound = Sound::with_buffer(&resources.victory_buffer);
+        // the music stems loop, all of them playing together to stay in sync
+        let music = resources.music_buffers.iter()
+            .map(|buffer| {
+                let mut stem = Sound::with_buffer(buffer);
+                stem.set_looping(true);
+                stem
+            })
+            .collect();
+        let volume = settings.volume;
+
+        // the rules of every run, the board is created when the game is restarted
+        let mode = config.mode.or(settings.mode).unwrap_or(Mode::Classic);
+        let rules = Rules {
+            mode,
+            grid,
+            growth_per_food: config.growth_per_food,
+            poison_growth: config.poison_growth,
+            magnet_seconds: config.magnet_seconds,
+            star_seconds: config.star_seconds,
+            portal_count: config.portal_count,
+            food_count: config.food_count,
+            enemy_count: config.enemy_count,
+            enemy_period: config.enemy_period.max(1),
+            ticks_per_second: config.fps,
+            rival_speed: config.rival_speed,
+            start_column: config.start_column,
+            start_row: config.start_row,
+            start_direction: config.start_direction,
+            modifiers: Modifiers::default(),
+            mutators: config.mutators,
+            food_spawn: config.food_spawn.clone(),
+            scoring: Scoring::new(mode).with(&config.scoring).unwrap_or_else(|_| Scoring::new(mode)),
+        };
+        // the board ticks at a fixed rate, whatever the frame rate
+        let time_per_frame = Time::seconds(1.0 / rules.tick_rate() as f32);
+
+        // initialize the pause sprite, from the atlas if it has the pause
+        // image (the overlay is drawn without it if there is no image)
+        let pause_region = resources.atlas.as_ref().and_then(|atlas| atlas.region("pause").map(|region| (atlas, region)));
+        let pause_sprite = match pause_region {
+            Some((atlas, region)) => {
+                let mut sprite = Sprite::with_texture(&atlas.texture);
+                sprite.set_texture_rect(&region);
+                Some(sprite)
+            },
+            None => resources.pause_texture.as_ref().map(|texture| Sprite::with_texture(texture)),
+        };
+
+        // the versus runs are played as rounds of a match
+        let versus_match = if rules.mode.variant().has_rival() && config.rounds > 1 { Some(Match::new(config.rounds)) } else { None };
+
+        // the adaptive difficulty adjusts the options of every run
+        let adaptive = if config.adaptive { Some(rules.clone()) } else { None };
+
+        // the tutorial is shown until completed once
+        let tutorial = if settings.tutorial_done { None } else { Some(TutorialStep::Turn) };
+
+        // the palette is tinted by the ambience, chosen once at startup
+        let ambience = config.ambience.resolve(config.utc_offset);
+        info!("ambience: {}", ambience.name());
+        let palette = |color: Color, share: f32| match ambience.tint() {
+            Some(([r, g, b], weight)) => {
+                let blend = |from: u8, to: u8| (f32::from(from) + (f32::from(to) - f32::from(from)) * weight * share) as u8;
+                Color::rgba(blend(color.r, r), blend(color.g, g), blend(color.b, b), color.a)
+            },
+            None => color
+        };
+        let mut game = SnakeGame {
+            window,
+            window_meta,
+            settings,
+            borderless: config.borderless,
+            monitor: None,
+            vsync: config.vsync,
+            frame_limit: config.frame_limit,
+            board: Board::new(rules, 0),
+            inputs: Vec::new(),
+            snake_color: palette(config.snake_color, 1.0 / 3.0),
+            food_color: palette(config.food_color, 1.0 / 3.0),
+            poison_color: palette(config.poison_color, 1.0 / 3.0),
+            enemy_color: palette(config.enemy_color, 1.0 / 3.0),
+            time_per_frame,
+            batch: Batch::new(layout, resources.atlas.as_ref(), config.cell_style),
+            camera,
+            hud_view,
+            base_size: window_size,
+            entity_size: config.entity_size,
+            text_size: config.text_size,
+            ui_scale: config.ui_scale,
+            hud_scale: config.ui_scale,
+            auto_pause: config.auto_pause,
+            practice: config.practice,
+            demo: config.demo,
+            demo_wait: 0,
+            adaptive,
+            history: VecDeque::new(),
+            rewinding: false,
+            rewind_text,
+            slow_motion: config.slow_motion,
+            slow_motion_left: Time::ZERO,
+            near_miss: false,
+            slow_motion_scale: 1.0,
+            time_scale: TimeScale::default(),
+            border,
+            state: State::Pause,
+            score_text,
+            score_string: String::with_capacity(16),
+            rival_text,
+            timer_text,
+            latency_text,
+            summary_text,
+            over_text,
+            victory_text,
+            quit_text,
+            dim,
+            quit_dialog: false,
+            hovered: None,
+            key_layout: config.key_layout,
+            keyboard: config.keyboard,
+            swipe: if config.swipe > 0 { Some(Swipe::new(config.swipe as f32)) } else { None },
+            // the snapshot is created with the size of the window, if blurred
+            snapshot: None,
+            blur: if config.blur && Shader::is_available() { Shader::from_memory(None, None, Some(BLUR_SHADER)) } else { None },
+            eat_sound,
+            over_sound,
+            victory_sound,
+            music,
+            intensity: 0,
+            stats: Stats::default(),
+            pause_sprite,
+            pause_text,
+            run_text,
+            back_color: palette(config.back_color, 1.0),
+            text_color: config.text_color,
+            diagnostics: Diagnostics::new(),
+            debug_text,
+            debug_overlay: false,
+            redraw: true,
+            console: None,
+            console_message: String::new(),
+            console_text,
+            chat: None,
+            chat_lines: VecDeque::with_capacity(CHAT_LINES),
+            trail_fade: config.trail_fade,
+            trail: VecDeque::new(),
+            chat_text,
+            tutorial,
+            tutorial_text,
+            // the splash screen is shown at startup
+            screens: vec![Screen::Splash],
+            screen_time: 0.0,
+            splash_text,
+            credits_text,
+            unranked: false,
+            cheat_codes: CheatCodes::default(),
+            versus_match,
+            match_text,
+            session: None,
+            lobby_text,
+            heatmap: config.heatmap,
+            summary_card: config.summary_card,
+            card_pending: false,
+            utc_offset: config.utc_offset,
+            hooks: Vec::new(),
+            #[cfg(feature = "debug-tools")]
+            hitboxes: false,
+            #[cfg(feature = "debug-tools")]
+            heatmap_shown: false,
+            #[cfg(feature = "online")]
+            leaderboard: config.leaderboard.as_ref().map(|url| Leaderboard::new(url, &config.player)),
+            #[cfg(feature = "online")]
+            leaderboard_text,
+            #[cfg(feature = "online")]
+            leaderboard_open: false,
+            #[cfg(feature = "discord")]
+            presence: config.discord.as_ref().map(|application| Presence::new(application)),
+        };
+        game.set_volume(volume);
+        game.limit_frame_rate();
+        // place all the entities from a random seed
+        game.restart(thread_rng().gen());
+        // place the HUD according to the window size
+        game.layout();
+        game
+    }
+
+    /// Gets the size of the window given by the configuration, a multiple
+    /// of the entity size.
+    fn window_size(config: &Config) -> Vector2u {
+        Vector2u::new(
+            config.window_size.x - config.window_size.x % config.entity_size,
+            config.window_size.y - config.window_size.y % config.entity_size)
+    }
+
+    /// Gets the main monitor, the only one SFML reports the size of.
+    fn main_monitor() -> Monitor {
+        let desktop = VideoMode::desktop_mode();
+        Monitor { x: 0, y: 0, width: desktop.width, height: desktop.height }
+    }
+
+    /// Creates the game window: fullscreen, borderless (as large as the
+    /// monitor) or windowed, with the size and position of the last session.
+    /// * `size` - Window size if not given by the last session.
+    /// * `monitor` - Monitor where the window is centered.
+    fn open_window(size: Vector2u, settings: &Settings, borderless: bool, monitor: Option<Monitor>) -> RenderWindow {
+        let desktop = VideoMode::desktop_mode();
+        if settings.fullscreen {
+            return RenderWindow::new(desktop, "Snake", Style::FULLSCREEN, &Default::default());
+        }
+        let (size, style) = if borderless {
+            let screen = monitor.unwrap_or_else(SnakeGame::main_monitor);
+            (Vector2u::new(screen.width, screen.height), Style::NONE)
+        } else {
+            (settings.window_size.map_or(size, Vector2u::from), Style::CLOSE | Style::RESIZE)
+        };
+        let mut window = RenderWindow::new((size.x, size.y), "Snake", style, &Default::default());
+        let position = match monitor {
+            Some(monitor) => {
+                let x = monitor.x + (monitor.width as i32 - size.x as i32) / 2;
+                let y = monitor.y + (monitor.height as i32 - size.y as i32) / 2;
+                Some((x, y))
+            },
+            None if borderless => Some((0, 0)),
+            None => settings.window_position,
+        };
+        if let Some((x, y)) = position {
+            window.set_position(&Vector2i::new(x, y));
+        }
+        window
+    }
+
+    /// Switches between fullscreen and windowed mode, creating the window
+    /// again.
+    fn toggle_fullscreen(&mut self) {
+        self.remember_window();
+        self.settings.fullscreen = !self.settings.fullscreen;
+        self.window = SnakeGame::open_window(self.base_size, &self.settings, self.borderless, self.monitor);
+        self.limit_frame_rate();
+        self.window_meta = WindowMeta::new(&mut self.window);
+        self.layout();
+    }
+
+    /// Limits the frame rate with the vertical synchronization, if enabled,
+    /// or with the frame cap (SFML advises against using both).
+    fn limit_frame_rate(&mut self) {
+        self.window.set_vertical_sync_enabled(self.vsync);
+        self.window.set_framerate_limit(if self.vsync { 0 } else { self.frame_limit });
+    }
+
+    /// Remembers the position and size of the window, unless fullscreen
+    /// or borderless.
+    fn remember_window(&mut self) {
+        if !self.settings.fullscreen && !self.borderless {
+            let position = self.window.position();
+            let size = self.window.size();
+            self.settings.window_position = Some((position.x, position.y));
+            self.settings.window_size = Some((size.x, size.y));
+        }
+    }
+
+    /// Closes the window, saving the settings for the next session.
+    fn close(&mut self) {
+        self.remember_window();
+        self.settings.mode = Some(self.board.rules.mode);
+        if let Err(err) = self.settings.save() {
+            warn!("unable to save the settings: {}", err);
+        }
+        self.window.close();
+    }
+
+    /// Sets the volume of all the sounds (from 0 to 100).
+    fn set_volume(&mut self, volume: f32) {
+        self.settings.volume = volume.clamp(0.0, 100.0);
+        for sound in &mut [&mut self.eat_sound, &mut self.over_sound, &mut self.victory_sound] {
+            sound.set_volume(self.settings.volume);
+        }
+        self.mix_music();
+    }
+
+    /// Sets the volume of the music stems: the ones up to the intensity of
+    /// the run are heard, the others are muted.
+    fn mix_music(&mut self) {
+        for (i, stem) in self.music.iter_mut().enumerate() {
+            stem.set_volume(if i <= self.intensity { self.settings.volume } else { 0.0 });
+        }
+    }
+
+    /// Plays the music while the run is played, pausing it with the run and
+    /// stopping it once over.
+    fn play_music(&mut self) {
+        let status = match self.state {
+            State::Play => SoundStatus::Playing,
+            State::Pause => SoundStatus::Paused,
+            State::GameOver | State::Victory => SoundStatus::Stopped,
+        };
+        for stem in &mut self.music {
+            // a stopped stem stays stopped while paused
+            if stem.status() == status || (status == SoundStatus::Paused && stem.status() == SoundStatus::Stopped) {
+                continue;
+            }
+            match status {
+                SoundStatus::Playing => stem.play(),
+                SoundStatus::Paused => stem.pause(),
+                SoundStatus::Stopped => stem.stop(),
+            };
+        }
+    }
+
+    /// Updates the intensity of the run from its score, layering the music
+    /// stems and tinting the background accordingly.
+    fn update_intensity(&mut self) {
+        let intensity = INTENSITY_SCORES.iter().filter(|score| self.board.score >= **score).count();
+        if intensity != self.intensity {
+            debug!("intensity changed: {} -> {}", self.intensity, intensity);
+            self.intensity = intensity;
+            self.mix_music();
+        }
+    }
+
+    /// Gets the background color of the board, tinted by the intensity of
+    /// the run.
+    fn background(&self) -> Color {
+        let weight = self.intensity as f32 / INTENSITY_SCORES.len() as f32 / 2.0;
+        let blend = |from: u8, to: u8| (f32::from(from) + (f32::from(to) - f32::from(from)) * weight) as u8;
+        let color = self.back_color;
+        Color::rgba(blend(color.r, INTENSITY_COLOR.r), blend(color.g, INTENSITY_COLOR.g), blend(color.b, INTENSITY_COLOR.b), color.a)
+    }
+
+    /// Gets the area of the window where the board is shown, given the
+    /// margin reserved to the HUD.
+    fn viewport(window_size: Vector2f, margin: f32) -> FloatRect {
+        // the margin never takes the whole window (e.g. tiny or minimized windows)
+        let margin = margin.min(window_size.x / 4.0).min(window_size.y / 6.0).max(0.0);
+        FloatRect::new(
+            margin,
+            margin * 2.0,
+            (window_size.x - 2.0 * margin).max(1.0),
+            (window_size.y - 3.0 * margin).max(1.0))
+    }
+
+    /// Places and scales the board and the HUD elements according to the
+    /// current window size.
+    fn layout(&mut self) {
+        let size = self.window.size();
+        let window_size = Vector2f::new(size.x as f32, size.y as f32);
+        // both the board and the HUD are scaled with the window, the HUD
+        // also by the configured scale factor
+        let zoom = (window_size.x / self.base_size.x as f32).min(window_size.y / self.base_size.y as f32);
+        self.hud_scale = self.ui_scale * zoom;
+        let margin = self.entity_size as f32 * self.hud_scale;
+        let viewport = SnakeGame::viewport(window_size, margin);
+        self.camera.resize(viewport, window_size, zoom);
+        // the HUD is drawn in window coordinates
+        self.hud_view = View::from_rect(&FloatRect::new(0.0, 0.0, window_size.x, window_size.y));
+        let border_height = 5.0 * self.hud_scale;
+        self.border.set_size(Vector2f::new(viewport.width + margin, border_height));
+        self.border.set_position(Vector2f::new(viewport.left - margin / 2.0, viewport.top - border_height));
+        self.dim.set_size(window_size);
+        // the snapshot of the board is only needed to blur it
+        if let Some(blur) = self.blur.as_mut() {
+            self.snapshot = RenderTexture::new(window_size.x as u32, window_size.y as u32, false);
+            blur.set_uniform_current_texture("texture");
+            blur.set_uniform_vec2("pixel", Vector2f::new(2.0 / window_size.x, 2.0 / window_size.y));
+        }
+        // scale the texts and place the overlay texts in the middle of the window
+        let character_size = (self.text_size as f32 * self.hud_scale).round().max(1.0) as u32;
+        self.splash_text.set_character_size(character_size * 2);
+        self.credits_text.set_character_size((character_size / 2).max(1));
+        for text in &mut [&mut self.over_text, &mut self.victory_text, &mut self.quit_text, &mut self.rewind_text,
+                          &mut self.splash_text, &mut self.credits_text, &mut self.match_text] {
+            text.set_character_size(character_size);
+            let bounds = text.local_bounds();
+            let x = window_size.x / 2.0 - bounds.width / 2.0;
+            let y = window_size.y / 2.0 - bounds.height / 2.0;
+            text.set_position((x, y));
+        }
+        self.score_text.set_character_size(character_size);
+        self.rival_text.set_character_size((character_size / 2).max(1));
+        self.update_score();
+        self.timer_text.set_character_size(character_size);
+        self.update_timer();
+        self.latency_text.set_character_size((character_size / 3).max(1));
+        if let Some(round_trip) = self.session.as_ref().and_then(Session::round_trip) {
+            self.update_latency(round_trip);
+        }
+        self.summary_text.set_character_size((character_size / 2).max(1));
+        self.update_summary();
+        if let Some(sprite) = self.pause_sprite.as_mut() {
+            sprite.set_scale((self.hud_scale, self.hud_scale));
+        }
+        // the pause text is centered below the pause bars
+        self.pause_text.set_character_size((character_size / 2).max(1));
+        self.pause_text.set_position((0.0, 0.0));
+        let bounds = self.pause_text.global_bounds();
+        let x = window_size.x / 2.0 - (bounds.left + bounds.width / 2.0);
+        let y = window_size.y / 2.0 + character_size as f32 * 1.5 - bounds.top;
+        self.pause_text.set_position((x, y));
+        self.run_text.set_character_size((character_size / 3).max(1));
+        self.update_run_text();
+        self.debug_text.set_character_size((character_size / 3).max(1));
+        self.console_text.set_character_size((character_size / 3).max(1));
+        self.chat_text.set_character_size((character_size / 3).max(1));
+        self.update_console_text();
+        self.tutorial_text.set_character_size((character_size / 2).max(1));
+        self.update_tutorial_text();
+        self.debug_text.set_position((margin, margin * 2.0));
+        self.lobby_text.set_character_size((character_size / 2).max(1));
+        self.lobby_text.set_position((margin, margin * 2.0));
+        #[cfg(feature = "online")]
+        {
+            self.leaderboard_text.set_character_size((character_size / 2).max(1));
+            self.leaderboard_text.set_position((margin, margin * 2.0));
+        }
+    }
+
+    /// Restarts the game from scratch, placing all the entities with
+    /// a random number generator initialized with the given seed.
+    fn restart(&mut self, seed: u64) {
+        // the networked runs are started by the server
+        if self.networked() {
+            return;
+        }
+        info!("run started: seed = {}, mode = {}", seed, self.board.rules.mode.name());
+        let mut rules = self.board.rules.clone();
+        // the adaptive difficulty adjusts the speed and enemies of the options
+        if let Some(options) = &self.adaptive {
+            rules.ticks_per_second = options.ticks_per_second;
+            rules.enemy_count = options.enemy_count;
+            self.settings.difficulty.apply(&mut rules);
+            self.time_per_frame = Time::seconds(1.0 / rules.tick_rate() as f32);
+            debug!("difficulty applied: level = {}, speed = {}, enemies = {}", self.settings.difficulty.level, rules.ticks_per_second, rules.enemy_count);
+        }
+        crash::start(&rules, seed);
+        self.board = Board::new(rules, seed);
+        // the music of the new run starts over
+        for stem in &mut self.music {
+            stem.stop();
+        }
+        self.inputs.clear();
+        self.history.clear();
+        self.trail.clear();
+        self.slow_motion_left = Time::ZERO;
+        self.near_miss = false;
+        // runs with a different time scale, modifiers or scoring are not
+        // comparable
+        let scoring = self.board.rules.scoring != Scoring::new(self.board.rules.mode);
+        self.unranked = self.practice || self.demo || self.time_scale != TimeScale::default() || self.board.rules.modifiers.any() || scoring;
+        self.update_timer();
+        self.update_score();
+        self.state = State::Pause;
+    }
+
+    /// Lets the bot steer the snake, starting the run on its own and the
+    /// next one a few seconds after it ends.
+    fn play_demo(&mut self) {
+        if !self.screens.is_empty() || self.quit_dialog {
+            return;
+        }
+        match self.state {
+            State::GameOver | State::Victory => {
+                self.demo_wait += 1;
+                if self.demo_wait < DEMO_RESTART_SECONDS * self.board.rules.tick_rate() {
+                    return;
+                }
+                self.demo_wait = 0;
+                self.restart(thread_rng().gen());
+            },
+            // a run paused by the player stays paused
+            State::Pause if self.board.ticks > 0 => return,
+            _ => ()
+        };
+        if let Some(direction) = ai::seek(&self.board, &self.board.snake) {
+            // the key to press is swapped back like the turns
+            if self.board.snake.direction() != Some(direction) {
+                self.apply(Input::Turn(self.board.rules.mutators.swap(direction)));
+            }
+        }
+        if !matches!(self.state, State::Play) {
+            debug!("state changed: {:?} -> Play", self.state);
+            self.state = State::Play;
+        }
+    }
+
+    /// Applies the given player input to the board, recording it so that
+    /// the run can be replayed. Returns false if the input was discarded.
+    fn apply(&mut self, input: Input) -> bool {
+        // the inputs of the networked runs are applied by the server
+        if let Some(session) = self.session.as_mut().filter(|session| session.client.is_some()) {
+            session.input(input);
+            return true;
+        }
+        let applied = self.board.apply(input);
+        if applied {
+            self.inputs.push((self.board.ticks, input));
+            crash::record_input(self.board.ticks, input);
+        }
+        applied
+    }
+
+    /// Handles the player input.
+    /// * `shift` - Whether the shift key is pressed.
+    fn handle_input(&mut self, key: Key, shift: bool) {
+        // the screen on top is closed by any key, but the lobby
+        if !self.screens.is_empty() && !self.in_lobby() {
+            self.close_screen();
+            return;
+        }
+        // the quit dialog takes all the input while open
+        if self.quit_dialog {
+            match key {
+                Key::Y => self.close(),
+                Key::N | Key::Escape => self.quit_dialog = false,
+                _ => ()
+            };
+            return;
+        }
+        // the console takes all the input while open
+        if let Some(line) = self.console.as_mut() {
+            match key {
+                Key::Return => self.run_console_command(),
+                Key::BackSpace => {
+                    line.pop();
+                },
+                Key::Escape | Key::Tilde => self.console = None,
+                _ => ()
+            };
+            self.update_console_text();
+            return;
+        }
+        // the chat takes all the input while open
+        if let Some(line) = self.chat.as_mut() {
+            match key {
+                Key::Return => self.post_chat(),
+                Key::BackSpace => {
+                    line.pop();
+                },
+                Key::Escape => self.chat = None,
+                _ => ()
+            };
+            return;
+        }
+        if self.in_lobby() {
+            self.lobby_input(key);
+            return;
+        }
+        // once the networked run is over any key goes back to the lobby
+        if self.networked() && matches!(self.state, State::GameOver | State::Victory) {
+            self.return_to_lobby();
+            return;
+        }
+        // the key completing a cheat code only toggles the cheat
+        if let Some(cheat) = self.cheat_codes.press(key) {
+            self.toggle_cheat(cheat);
+            return;
+        }
+        // the leaderboard screen is closed by any key
+        #[cfg(feature = "online")]
+        {
+            if self.leaderboard_open {
+                self.leaderboard_open = false;
+                return;
+            }
+            // unless moving the snake with the vim keys
+            if key == Key::L && self.key_layout.direction(self.keyboard.position(key), true).is_none() {
+                self.open_leaderboard();
+                return;
+            }
+        }
+        // the first player turns with the keys of the layout, the second
+        // one in co-op mode with the arrow keys or the numeric keypad; the
+        // keys of the directions the board does not have are ignored, and
+        // the movement keys are found by their place on the keyboard
+        let position = self.keyboard.position(key);
+        let coop = self.board.rules.mode.variant().has_partner();
+        let directions = self.board.rules.grid.directions();
+        let diagonals = directions.contains(&Direction::UpLeft);
+        let layout = self.key_layout;
+        let key_input = || {
+            let input = match layout.direction(position, diagonals) {
+                Some(direction) => Input::Turn(direction),
+                None if coop => Input::PartnerTurn(layout.partner_direction(position, diagonals)?),
+                None => return None
+            };
+            match input {
+                Input::Turn(direction) | Input::PartnerTurn(direction) if !directions.contains(&direction) => None,
+                input => Some(input),
+            }
+        };
+        match key_input() {
+            Some(input) => self.steer(input),
+            None => match key {
+                Key::P if matches!(self.state, State::Pause) => self.resume(),
+                Key::P => self.pause(),
+                Key::F3 => self.debug_overlay = !self.debug_overlay,
+                Key::F11 => self.toggle_fullscreen(),
+                Key::Return => self.chat = Some(String::new()),
+                Key::C => {
+                    self.pause();
+                    self.open_screen(Screen::Credits);
+                },
+                Key::Dash => {
+                    let volume = self.settings.volume - 10.0;
+                    self.set_volume(volume);
+                },
+                Key::Equal => {
+                    let volume = self.settings.volume + 10.0;
+                    self.set_volume(volume);
+                },
+                Key::Tilde => {
+                    self.pause();
+                    self.console = Some(String::new());
+                    self.update_console_text();
+                },
+                #[cfg(feature = "debug-tools")]
+                Key::F4 => self.hitboxes = !self.hitboxes,
+                #[cfg(feature = "debug-tools")]
+                Key::F5 => self.heatmap_shown = !self.heatmap_shown,
+                Key::Escape => self.request_quit(),
+                // restart with the same seed (shift) or with a new one
+                Key::R if shift => {
+                    let seed = self.board.seed;
+                    self.restart(seed);
+                },
+                Key::R => self.restart(thread_rng().gen()),
+                _ => ()
+            }
+        };
+    }
+
+    /// Turns a snake as the player asked (with a key or a swipe), taking
+    /// over the demo run, starting a new run if over and playing it.
+    fn steer(&mut self, input: Input) {
+        // the player takes over the demo run
+        if self.demo {
+            info!("demo stopped");
+            self.demo = false;
+        }
+        // start a new run if necessary
+        if let State::GameOver | State::Victory = self.state {
+            self.restart(thread_rng().gen());
+        }
+        // queue the direction change (if going backwards is allowed)
+        if self.apply(input) {
+            self.advance_tutorial(TutorialStep::Turn);
+            if !matches!(self.state, State::Play) {
+                debug!("state changed: {:?} -> Play", self.state);
+            }
+            self.state = State::Play;
+        }
+    }
+
+    /// Starts or stops boosting the snake, only started while playing (and
+    /// not typing).
+    fn boost(&mut self, boosting: bool) {
+        let typing = self.console.is_some() || self.chat.is_some();
+        if boosting && (typing || self.demo || !matches!(self.state, State::Play)) {
+            return;
+        }
+        if self.board.boosting != boosting {
+            self.apply(Input::Boost(boosting));
+        }
+    }
+
+    /// Starts a swipe where the mouse button was pressed (or the screen
+    /// touched), if the swipes are enabled.
+    fn start_swipe(&mut self, position: Vector2i) {
+        if let Some(swipe) = self.swipe.as_mut() {
+            swipe.press(Vector2f::new(position.x as f32, position.y as f32));
+        }
+    }
+
+    /// Moves the swipe in progress to the given window position, turning
+    /// the snake toward the direction of the board closest to the one of
+    /// the swipe once long enough. Nothing is swiped while a screen, a
+    /// dialog or a text line is open.
+    fn swipe_to(&mut self, position: Vector2i) {
+        let offset = match self.swipe.as_mut() {
+            Some(swipe) => swipe.drag(Vector2f::new(position.x as f32, position.y as f32)),
+            None => return
+        };
+        let offset = match offset {
+            Some(offset) if self.screens.is_empty() && !self.quit_dialog && self.console.is_none() && self.chat.is_none() => offset,
+            _ => return
+        };
+        // the angles are clockwise from up, like the headings
+        let angle = offset.x.atan2(-offset.y).to_degrees();
+        let layout = self.batch.layout;
+        let gap = |direction: &&Direction| {
+            let gap = (layout.heading(**direction) - angle).rem_euclid(360.0);
+            gap.min(360.0 - gap)
+        };
+        let closest = self.board.rules.grid.directions().iter().min_by(|a, b| gap(a).total_cmp(&gap(b)));
+        if let Some(direction) = closest {
+            self.steer(Input::Turn(*direction));
+        }
+    }
+
+    /// Ends the swipe in progress, the mouse button being released (or the
+    /// finger lifted).
+    fn end_swipe(&mut self) {
+        if let Some(swipe) = self.swipe.as_mut() {
+            swipe.release();
+        }
+    }
+
+    /// Toggles the modifier of the given cheat, starting a new run with it
+    /// (the rules never change while playing).
+    fn toggle_cheat(&mut self, cheat: Cheat) {
+        let modifiers = &mut self.board.rules.modifiers;
+        let flag = match cheat {
+            Cheat::Rainbow => &mut modifiers.rainbow,
+            Cheat::BigHead => &mut modifiers.big_head,
+            Cheat::Invincible => &mut modifiers.invincible,
+        };
+        *flag = !*flag;
+        info!("cheat toggled: {:?} = {}", cheat, *flag);
+        self.restart(thread_rng().gen());
+    }
+
+    /// Opens the given screen on top of the others.
+    fn open_screen(&mut self, screen: Screen) {
+        debug!("screen opened: {:?}", screen);
+        self.screens.push(screen);
+        self.screen_time = 0.0;
+    }
+
+    /// Closes the screen on top of the others, showing the one below (if
+    /// any) from the start.
+    fn close_screen(&mut self) {
+        if let Some(screen) = self.screens.pop() {
+            debug!("screen closed: {:?}", screen);
+        }
+        self.screen_time = 0.0;
+    }
+
+    /// Advances the fade of the screen on top by the given frame time,
+    /// closing it once its duration is over.
+    fn update_screens(&mut self, frame_time: Time) {
+        if let Some(screen) = self.screens.last().cloned() {
+            self.screen_time += frame_time.as_seconds();
+            if screen.duration().is_some_and(|duration| self.screen_time >= duration) {
+                self.close_screen();
+                self.redraw = true;
+            }
+        }
+    }
+
+    /// Returns true if nothing changes on screen unless an event occurs, as
+    /// when paused or once the run is over.
+    fn idle(&self) -> bool {
+        // the screens, the chat lines and the trail change while fading
+        if !self.chat_lines.is_empty() || !self.trail.is_empty() {
+            return false;
+        }
+        if let Some(screen) = self.screens.last() {
+            if screen.duration().is_some() || self.screen_time < FADE_SECONDS {
+                return false;
+            }
+        }
+        match self.state {
+            State::Play => false,
+            // the debug overlay and the slow motion change at every frame
+            _ => !self.debug_overlay && !self.rewinding && self.slow_motion_scale >= 1.0,
+        }
+    }
+
+    /// Returns true if an overlay covers the board: while paused (unless
+    /// rewinding), once the run is over or with a dialog open.
+    fn covered(&self) -> bool {
+        #[cfg(feature = "online")]
+        {
+            if self.leaderboard_open {
+                return true;
+            }
+        }
+        self.quit_dialog || match self.state {
+            State::Pause => !self.rewinding,
+            // the heatmap of the run over is left uncovered
+            State::GameOver | State::Victory => !self.heatmap,
+            State::Play => false,
+        }
+    }
+
+    /// Returns true if a run is in progress (even if paused).
+    fn in_run(&self) -> bool {
+        match self.state {
+            State::Play => true,
+            State::Pause => self.board.snake.direction().is_some(),
+            State::GameOver | State::Victory => false,
+        }
+    }
+
+    /// Closes the window, asking for confirmation if a run is in progress.
+    fn request_quit(&mut self) {
+        if self.in_run() {
+            // the game is paused under the dialog
+            self.pause();
+            self.quit_dialog = true;
+        } else {
+            self.close();
+        }
+    }
+
+    /// Sets the game state to pause, if playing (the networked runs going
+    /// on without the player).
+    fn pause(&mut self) {
+        if self.networked() {
+            return;
+        }
+        if let State::Play = self.state {
+            self.apply(Input::Stop);
+            self.state = State::Pause;
+            debug!("state changed: Play -> Pause, tick = {}", self.board.ticks);
+            self.update_run_text();
+            self.advance_tutorial(TutorialStep::Pause);
+        }
+    }
+
+    /// Resumes the run paused by the player, going on in the same direction
+    /// (a run not started yet waits for a direction instead).
+    fn resume(&mut self) {
+        if matches!(self.state, State::Pause) && self.in_run() {
+            self.state = State::Play;
+            debug!("state changed: Pause -> Play, tick = {}", self.board.ticks);
+        }
+    }
+
+    /// Handles a click of the left mouse button at the given window
+    /// position, on the screens and the overlays: the runs are only played
+    /// with the keyboard.
+    fn click(&mut self, position: Vector2i) {
+        if !self.screens.is_empty() {
+            if !self.in_lobby() {
+                self.close_screen();
+            }
+            return;
+        }
+        if self.quit_dialog {
+            match self.dialog_option(position) {
+                Some(Key::Y) => self.close(),
+                Some(_) => self.quit_dialog = false,
+                None => ()
+            };
+            return;
+        }
+        #[cfg(feature = "online")]
+        {
+            if self.leaderboard_open {
+                self.leaderboard_open = false;
+                return;
+            }
+        }
+        if self.console.is_some() || self.chat.is_some() {
+            return;
+        }
+        match self.state {
+            State::Pause => self.resume(),
+            State::GameOver | State::Victory if !self.demo => self.restart(thread_rng().gen()),
+            _ => ()
+        };
+    }
+
+    /// Highlights the option of the quit dialog under the mouse at the
+    /// given window position, if any.
+    fn hover(&mut self, position: Vector2i) {
+        let hovered = if self.quit_dialog { self.dialog_option(position) } else { None };
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.redraw = true;
+        }
+    }
+
+    /// Gets the key of the option of the quit dialog (`Y` or `N`) at the
+    /// given window position, if any.
+    fn dialog_option(&self, position: Vector2i) -> Option<Key> {
+        let position = self.window.map_pixel_to_coords(&position, &self.hud_view);
+        [Key::Y, Key::N].iter().cloned()
+            .find(|key| self.option_bounds(*key).contains(position))
+    }
+
+    /// Gets the bounds of the given option of the quit dialog (the letter
+    /// of its key in the text), in window coordinates.
+    fn option_bounds(&self, key: Key) -> FloatRect {
+        // the text is "QUIT? Y/N"
+        let index = if key == Key::Y { 6 } else { 8 };
+        let start = self.quit_text.find_character_pos(index);
+        let end = self.quit_text.find_character_pos(index + 1);
+        let height = self.quit_text.character_size() as f32;
+        // a little margin around the letter
+        let margin = height / 4.0;
+        FloatRect::new(start.x - margin, start.y - margin, end.x - start.x + 2.0 * margin, height + 2.0 * margin)
+    }
+
+    /// Sets the game state to Game Over.
+    fn game_over(&mut self) {
+        self.state = State::GameOver;
+        info!("game over: score = {}, ticks = {}, seed = {}", self.board.score, self.board.ticks, self.board.seed);
+        self.over_sound.play();
+        self.stats.record(self.board.score, false);
+        self.adapt(false);
+        self.update_summary();
+        self.save_replay();
+        #[cfg(feature = "online")]
+        self.submit_score();
+        self.finish_round();
+        // the card is saved once the final board is drawn
+        self.card_pending = self.summary_card && !self.demo;
+    }
+
+    /// Sets the game state to Victory.
+    fn victory(&mut self) {
+        self.state = State::Victory;
+        info!("victory: score = {}, ticks = {}, seed = {}", self.board.score, self.board.ticks, self.board.seed);
+        self.victory_sound.play();
+        self.stats.record(self.board.score, true);
+        self.adapt(true);
+        self.update_summary();
+        self.save_replay();
+        #[cfg(feature = "online")]
+        self.submit_score();
+        self.finish_round();
+        self.card_pending = self.summary_card && !self.demo;
+    }
+
+    /// Nudges the difficulty of the next runs after the one that just ended
+    /// (won or not), if adaptive. The demo runs do not count.
+    fn adapt(&mut self, victory: bool) {
+        if self.adaptive.is_none() || self.demo {
+            return;
+        }
+        let difficulty = &mut self.settings.difficulty;
+        let change = difficulty.record(&self.board, victory);
+        info!("difficulty updated: level = {} ({:+}), food interval = {:.1}, run seconds = {:.1}",
+            difficulty.level, change, difficulty.food_interval, difficulty.run_seconds);
+    }
+
+    /// Records the run that just ended as a round of the versus match (if
+    /// any), showing the scoreboard or, once the match is over, its winner
+    /// (a new match starts with the next run).
+    fn finish_round(&mut self) {
+        let versus_match = match self.versus_match.as_mut() {
+            Some(versus_match) => versus_match,
+            None => return
+        };
+        versus_match.record(self.board.score, self.board.rival_score);
+        let scoreboard = versus_match.scoreboard();
+        let (content, screen) = match versus_match.result() {
+            Some(result) => {
+                info!("match over: result = {:?}, rounds = {}", result, versus_match.scores.len());
+                let (title, score) = match result {
+                    MatchResult::Won => ("YOU WIN THE MATCH", 1.0),
+                    MatchResult::Lost => ("THE AI WINS THE MATCH", 0.0),
+                    MatchResult::Draw => ("THE MATCH IS A DRAW", 0.5),
+                };
+                *versus_match = Match::new(versus_match.rounds);
+                let settings = &mut self.settings;
+                let change = rating::update(&mut settings.rating, &mut settings.rival_rating, score);
+                info!("ratings updated: rating = {:.0}, rival rating = {:.0}", settings.rating, settings.rival_rating);
+                (format!("{}\n\n{}\n\nRATING {:.0} ({:+.0})", title, scoreboard, settings.rating, change), Screen::MatchOver)
+            },
+            None => {
+                let round = versus_match.scores.len() + 1;
+                (format!("NEXT: ROUND {} OF {}\n\n{}", round, versus_match.rounds, scoreboard), Screen::Scoreboard)
+            },
+        };
+        self.match_text.set_string(&content);
+        let size = self.window.size();
+        let bounds = self.match_text.local_bounds();
+        self.match_text.set_position((size.x as f32 / 2.0 - bounds.width / 2.0, size.y as f32 / 2.0 - bounds.height / 2.0));
+        self.open_screen(screen);
+    }
+
+    /// Gets the replay of the current run.
+    fn replay(&self) -> Replay {
+        Replay {
+            rules: self.board.rules.clone(),
+            seed: self.board.seed,
+            inputs: self.inputs.clone(),
+            ticks: self.board.ticks,
+            score: self.board.score,
+        }
+    }
+
+    /// Saves the replay of the run that just ended, if it beats the best
+    /// replay saved for the game mode.
+    fn save_replay(&self) {
+        // runs changed from the console or rewound cannot be replayed
+        if self.unranked {
+            return;
+        }
+        let replay = self.replay();
+        // the runs with mutators are ranked separately
+        let mut name = format!("best-{}", replay.rules.mode.name());
+        for mutator in replay.rules.mutators.names() {
+            name = name + "-" + mutator;
+        }
+        let path = PathBuf::from(REPLAY_DIRECTORY).join(name + ".replay");
+        let best_score = Replay::load(&path).map(|best| best.score).unwrap_or(0);
+        if replay.score > best_score {
+            match replay.save(&path) {
+                Ok(()) => info!("new best score, replay saved: path = {}", path.display()),
+                Err(err) => error!("unable to save the replay: path = {}, error = {}", path.display(), err),
+            };
+        }
+    }
+
+    /// Saves the summary card of the run that just ended to the screenshots
+    /// directory: the final board (as in the batch) below the score, the
+    /// snake length, the mode and the date.
+    fn save_card(&self) {
+        let board_size = self.camera.board_size;
+        let character_size = (self.text_size / 2).max(1);
+        let header = character_size as f32 * 2.0;
+        let mut card = match RenderTexture::new(board_size.x as u32, (board_size.y + header) as u32, false) {
+            Some(card) => card,
+            None => {
+                error!("unable to create the summary card");
+                return;
+            }
+        };
+        card.clear(&self.background());
+        // the board is drawn below the header
+        card.set_view(&View::from_rect(&FloatRect::new(0.0, -header, board_size.x, board_size.y + header)));
+        self.batch.draw(&mut card);
+        let view = card.default_view().to_owned();
+        card.set_view(&view);
+        let (year, month, day) = ambience::date(ambience::local_time(self.utc_offset) / 86_400);
+        let mut text = self.summary_text.clone();
+        text.set_string(&format!("SCORE {}  LENGTH {}  {}  {}-{:02}-{:02}",
+            self.board.score, self.board.snake.segments().count(), self.board.rules.mode.name().to_uppercase(), year, month, day));
+        text.set_character_size(character_size);
+        text.set_fill_color(&self.text_color);
+        text.set_position((character_size as f32 / 2.0, character_size as f32 / 2.0));
+        card.draw(&text);
+        card.display();
+        let name = format!("run-{}-{:02}-{:02}-{}.png", year, month, day, self.board.seed);
+        let path = PathBuf::from(SCREENSHOT_DIRECTORY).join(name);
+        let saved = fs::create_dir_all(SCREENSHOT_DIRECTORY).is_ok()
+            && card.texture().copy_to_image().is_some_and(|image| image.save_to_file(&path.to_string_lossy()));
+        if saved {
+            info!("summary card saved: path = {}", path.display());
+        } else {
+            error!("unable to save the summary card: path = {}", path.display());
+        }
+    }
+
+    /// Submits the score of the run that just ended to the online leaderboard.
+    #[cfg(feature = "online")]
+    fn submit_score(&self) {
+        if let Some(leaderboard) = &self.leaderboard {
+            if self.board.score > 0 && !self.unranked {
+                leaderboard.submit(self.replay());
+            }
+        }
+    }
+
+    /// Opens the leaderboard screen (pausing the game), fetching the best
+    /// scores of the current game mode.
+    #[cfg(feature = "online")]
+    fn open_leaderboard(&mut self) {
+        self.pause();
+        if let Some(leaderboard) = &self.leaderboard {
+            leaderboard.fetch(self.board.rules.mode);
+            self.leaderboard_text.set_string("LOADING...");
+            self.leaderboard_open = true;
+        }
+    }
+
+    /// Shows the leaderboard entries as soon as they are received, as many
+    /// as fit the window.
+    #[cfg(feature = "online")]
+    fn poll_leaderboard(&mut self) {
+        let response = match &self.leaderboard {
+            Some(leaderboard) => leaderboard.poll(),
+            None => return
+        };
+        let content = match response {
+            Some(Ok(entries)) => {
+                let line_height = self.leaderboard_text.character_size() as f32 * 1.5;
+                let available = self.window.size().y as f32 - self.leaderboard_text.position().y;
+                let lines: Vec<String> = entries.iter()
+                    .take((available / line_height).max(1.0) as usize)
+                    .enumerate()
+                    .map(|(i, entry)| format!("{:>3}. {:<16} {}", i + 1, entry.player, entry.score))
+                    .collect();
+                let mut content = if lines.is_empty() { String::from("NO SCORES YET") } else { lines.join("\n") };
+                if self.board.rules.mode.variant().has_rival() {
+                    let _ = write!(content, "\n\nYOUR RATING {:.0}  AI RATING {:.0}", self.settings.rating, self.settings.rival_rating);
+                }
+                content
+            },
+            Some(Err(err)) => err.to_uppercase(),
+            None => return
+        };
+        self.leaderboard_text.set_string(&content);
+        self.redraw = true;
+    }
+
+    /// Returns true if a networked run is being played (even if over).
+    fn networked(&self) -> bool {
+        self.session.as_ref().is_some_and(|session| session.client.is_some())
+    }
+
+    /// Returns true if the lobby screen is on top of the others.
+    fn in_lobby(&self) -> bool {
+        self.screens.last() == Some(&Screen::Lobby)
+    }
+
+    /// Joins the lobby of the networked play with the given session, shown
+    /// once the splash screen closes.
+    fn go_online(&mut self, session: Session) {
+        self.session = Some(session);
+        self.screens.insert(0, Screen::Lobby);
+        self.update_lobby_text();
+    }
+
+    /// Applies the updates received from the server of the networked play
+    /// (if any). Once disconnected, the game goes on offline.
+    fn poll_session(&mut self) {
+        let updates = match self.session.as_mut().map(Session::poll) {
+            Some(Ok(updates)) => updates,
+            Some(Err(err)) => {
+                error!("networked play stopped: {}", err);
+                self.session = None;
+                self.screens.retain(|screen| *screen != Screen::Lobby);
+                self.restart(thread_rng().gen());
+                self.redraw = true;
+                return;
+            },
+            None => return
+        };
+        for update in updates {
+            self.redraw = true;
+            match update {
+                Update::Lobby => self.update_lobby_text(),
+                Update::Chat(player, text) => {
+                    let own = self.session.as_ref().is_some_and(|session| session.player == player);
+                    self.add_chat_line(format!("{}: {}", if own { "YOU" } else { &player }, text));
+                },
+                Update::Started => self.start_networked_run(),
+                Update::Tick(events) => self.play_networked_tick(events),
+                Update::RoundTrip(round_trip) => self.update_latency(round_trip),
+            };
+        }
+    }
+
+    /// Starts playing the networked run whose snapshot was just received,
+    /// on the board of the server.
+    fn start_networked_run(&mut self) {
+        let board = match self.session.as_ref().and_then(|session| session.client.as_ref()) {
+            Some(client) => client.board.clone(),
+            None => return
+        };
+        info!("networked run started: seed = {}, mode = {}", board.seed, board.rules.mode.name());
+        self.screens.retain(|screen| *screen != Screen::Lobby);
+        // the board of the server can be larger or of another shape
+        let grid = board.rules.grid;
+        self.batch.layout.topology = grid.topology;
+        self.camera = Camera::new(self.batch.layout.board_size(grid), self.camera.minimap.is_some());
+        self.board = board;
+        self.inputs.clear();
+        self.history.clear();
+        self.trail.clear();
+        self.latency_text.set_string("");
+        // the networked runs are neither saved nor submitted
+        self.unranked = true;
+        self.layout();
+        self.update_timer();
+        self.update_score();
+        self.state = State::Play;
+    }
+
+    /// Gets the cells of the snake before a tick, to find the ones it left
+    /// (only if the trail fades).
+    fn trail_cells(&self) -> Vec<GridPos> {
+        if self.trail_fade { self.board.snake.segments().cloned().collect() } else { Vec::new() }
+    }
+
+    /// Plays the tick the board just played: notifies the hooks, plays the
+    /// sounds of its events and updates the trail and the texts, given the
+    /// cells of the snake, the score and the run seconds before the tick.
+    fn play_tick(&mut self, events: Vec<TickEvent>, segments: Vec<GridPos>, score: u32, seconds: u32) {
+        hooks::notify(&mut self.hooks, &self.board, &events, score);
+        let snake = &self.board.snake;
+        self.trail.extend(segments.into_iter().filter(|cell| !snake.collision(*cell)).map(|cell| (cell, 0.0)));
+        for event in events {
+            match event {
+                TickEvent::Food | TickEvent::Poison => {
+                    debug!("{:?} eaten: score = {}, food = {:?}, poison = {:?}",
+                        event, self.board.score, self.board.foods, self.board.poison);
+                    self.eat_sound.play();
+                    // the snake flicks its tongue
+                    self.batch.flick_tongue();
+                    if event == TickEvent::Food {
+                        self.advance_tutorial(TutorialStep::Eat);
+                    }
+                },
+                TickEvent::RivalFood => {
+                    debug!("food eaten by the rival: rival score = {}, food = {:?}", self.board.rival_score, self.board.foods);
+                    self.update_score();
+                },
+                TickEvent::Magnet => {
+                    debug!("magnet picked up: food = {:?}", self.board.foods);
+                    self.eat_sound.play();
+                },
+                TickEvent::Star => {
+                    debug!("star picked up: ticks = {}", self.board.star_ticks);
+                    self.eat_sound.play();
+                },
+                TickEvent::TailCut => {
+                    debug!("tail cut: score = {}, rival score = {}", self.board.score, self.board.rival_score);
+                    self.update_score();
+                },
+                TickEvent::GameOver => self.game_over(),
+                TickEvent::Victory => self.victory(),
+            };
+        }
+        if self.board.score != score {
+            self.update_score();
+        }
+        // slow down as soon as the head gets close to an obstacle
+        let near_miss = !self.board.over && self.board.near_miss();
+        if self.slow_motion && near_miss && !self.near_miss {
+            self.slow_motion_left = Time::seconds(SLOW_MOTION_SECONDS);
+        }
+        self.near_miss = near_miss;
+        // the run time only advances while playing
+        if self.board.seconds() != seconds {
+            self.update_timer();
+        }
+    }
+
+    /// Plays the tick of the networked run the client just applied, given
+    /// its events.
+    fn play_networked_tick(&mut self, events: Vec<TickEvent>) {
+        let board = match self.session.as_ref().and_then(|session| session.client.as_ref()) {
+            Some(client) => client.board.clone(),
+            None => return
+        };
+        let (score, seconds, segments) = (self.board.score, self.board.seconds(), self.trail_cells());
+        self.board = board;
+        self.play_tick(events, segments, score, seconds);
+    }
+
+    /// Goes back to the lobby once the networked run is over.
+    fn return_to_lobby(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.leave_run();
+        }
+        self.update_lobby_text();
+        self.open_screen(Screen::Lobby);
+    }
+
+    /// Handles the keys of the lobby screen: creating, joining and leaving
+    /// the rooms, picking the color, getting ready, starting the match and
+    /// opening the chat.
+    fn lobby_input(&mut self, key: Key) {
+        match key {
+            Key::Escape => self.request_quit(),
+            Key::Return => self.chat = Some(String::new()),
+            _ => ()
+        };
+        if key == Key::Escape || key == Key::Return {
+            return;
+        }
+        let session = match self.session.as_mut() {
+            Some(session) => session,
+            None => return
+        };
+        let player = &session.player;
+        let member = session.room.as_ref().and_then(|room| room.members.iter().find(|member| member.player == *player));
+        let message = match key {
+            Key::F5 => Message::ListRooms,
+            Key::C => Message::CreateRoom { room: player.clone() },
+            Key::L => Message::LeaveRoom,
+            Key::R => Message::Ready { ready: !member.is_some_and(|member| member.ready) },
+            Key::S => Message::StartMatch,
+            // the next color not taken by the other players
+            Key::Tab => {
+                let (room, member) = match (&session.room, member) {
+                    (Some(room), Some(member)) => (room, member),
+                    _ => return
+                };
+                let current = lobby::COLORS.iter().position(|color| *color == member.color).unwrap_or(0);
+                let next = (1..lobby::COLORS.len())
+                    .map(|offset| lobby::COLORS[(current + offset) % lobby::COLORS.len()])
+                    .find(|color| room.members.iter().all(|other| other.color != *color));
+                match next {
+                    Some(color) => Message::PickColor { color: color.to_string() },
+                    None => return
+                }
+            },
+            // the rooms listed are joined by their number
+            _ => {
+                let keys = [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9];
+                match keys.iter().position(|other| *other == key).and_then(|index| session.rooms.get(index)) {
+                    Some((room, _)) => Message::JoinRoom { room: room.clone() },
+                    None => return
+                }
+            },
+        };
+        session.request(message);
+        self.update_lobby_text();
+    }
+
+    /// Updates the text of the lobby screen: the rooms listed or the room
+    /// of the player, the keys to press and the last request refused.
+    fn update_lobby_text(&mut self) {
+        let session = match &self.session {
+            Some(session) => session,
+            None => return
+        };
+        let mut content = format!("ONLINE AS {}\n\n", session.player);
+        match &session.room {
+            Some(room) => {
+                let _ = writeln!(content, "ROOM {}", room.name);
+                for (i, member) in room.members.iter().enumerate() {
+                    let ready = if member.ready { "READY" } else { "NOT READY" };
+                    let host = if i == 0 { " (HOST)" } else { "" };
+                    let _ = writeln!(content, "{:<8} {}{} - {}", member.color, member.player, host, ready);
+                }
+                content.push_str("\nTAB: COLOR  R: READY  S: START  L: LEAVE  ENTER: CHAT");
+            },
+            None => {
+                content.push_str("ROOMS\n");
+                if session.rooms.is_empty() {
+                    content.push_str("NONE YET\n");
+                }
+                for (i, (name, players)) in session.rooms.iter().take(9).enumerate() {
+                    let _ = writeln!(content, "{}: {} ({}/{})", i + 1, name, players, lobby::COLORS.len());
+                }
+                content.push_str("\n1-9: JOIN  C: CREATE  F5: REFRESH  ENTER: CHAT");
+            },
+        };
+        content.push_str("  ESC: QUIT");
+        if let Some(reason) = &session.refusal {
+            let _ = write!(content, "\n\n{}", reason);
+        }
+        self.lobby_text.set_string(&content.to_uppercase());
+    }
+
+    /// Returns true if the heatmap is shown: once the run is over if
+    /// enabled, or at any time if toggled with the debug tools.
+    fn heatmap_visible(&self) -> bool {
+        #[cfg(feature = "debug-tools")]
+        {
+            if self.heatmap_shown {
+                return true;
+            }
+        }
+        self.heatmap && matches!(self.state, State::GameOver | State::Victory)
+    }
+
+    /// Adds to the batch the heatmap of the run, over every cell the snake
+    /// head entered (the hottest being the most visited one).
+    fn add_heatmap(&mut self) {
+        let grid = self.board.rules.grid;
+        let cells: Vec<GridPos> = (0..grid.rows).flat_map(|y| (0..grid.columns).map(move |x| GridPos::new(x, y))).collect();
+        let hottest = cells.iter().map(|cell| self.board.visits(*cell)).max().unwrap_or(0);
+        if hottest == 0 {
+            return;
+        }
+        for cell in cells {
+            let visits = self.board.visits(cell);
+            if visits > 0 {
+                self.batch.add_shape(cell, 1.0, 0.0, heat_color(visits as f32 / hottest as f32));
+            }
+        }
+    }
+
+    /// Adds to the batch the grid lines, the outline of the cell occupied
+    /// by every entity and the highlighted snake head.
+    #[cfg(feature = "debug-tools")]
+    fn add_hitboxes(&mut self) {
+        let grid = self.board.rules.grid;
+        let cell_size = self.batch.layout.cell_size;
+        let board_size = self.camera.board_size;
+        let line_color = Color::rgba(255, 255, 255, 48);
+        // the lines only split the square cells
+        if grid.topology != Topology::Hex {
+            for x in 0..=grid.columns {
+                let position = Vector2f::new(x as f32 * cell_size, 0.0);
+                self.batch.add_quad(position, Vector2f::new(1.0, board_size.y), line_color);
+            }
+            for y in 0..=grid.rows {
+                let position = Vector2f::new(0.0, y as f32 * cell_size);
+                self.batch.add_quad(position, Vector2f::new(board_size.x, 1.0), line_color);
+            }
+        }
+        // every entity collides with the others only on its own cell
+        let mut cells: Vec<GridPos> = self.board.snake.segments().cloned().collect();
+        cells.extend(&self.board.foods);
+        cells.extend(self.board.poison);
+        cells.extend(self.board.magnet);
+        cells.extend(self.board.star);
+        cells.extend(self.board.enemies.iter().map(|enemy| enemy.position()));
+        cells.extend(self.board.portals.iter().flat_map(|portal| portal.positions.to_vec()));
+        for cell in cells {
+            self.batch.add_outline(cell, Color::rgb(255, 128, 0));
+        }
+        let head = self.batch.layout.position(self.board.snake.head_position());
+        self.batch.add_quad(head, Vector2f::new(cell_size, cell_size), Color::rgba(255, 255, 0, 128));
+    }
+
+    /// Updates the slow motion scale of the game, which is slowed down for
+    /// a moment after a near miss.
+    /// * `frame_time` - Time elapsed since the previous frame.
+    fn update_slow_motion(&mut self, frame_time: Time) {
+        self.slow_motion_left = if self.slow_motion_left > frame_time {
+            self.slow_motion_left - frame_time
+        } else {
+            Time::ZERO
+        };
+        // half speed first, then ramp back to the normal speed
+        let left = self.slow_motion_left.as_seconds() / SLOW_MOTION_SECONDS;
+        self.slow_motion_scale = if left > 0.5 { 0.5 } else { 1.0 - left };
+    }
+
+    /// Brings the board back by a single tick, if there is any tick left
+    /// in the history. The game is paused until the next direction key.
+    fn rewind(&mut self) {
+        if let Some(board) = self.history.pop_back() {
+            self.board = board;
+            self.state = State::Pause;
+            self.update_score();
+            self.update_timer();
+        }
+    }
+
+    /// Adds the given character to the chat or console line, if open.
+    fn enter_text(&mut self, character: char) {
+        if character.is_control() {
+            return;
+        }
+        if let Some(line) = self.chat.as_mut() {
+            if line.chars().count() < CHAT_LENGTH {
+                line.push(character);
+            }
+            return;
+        }
+        // the key opening the console is not part of the command
+        if character == '`' || character == '~' {
+            return;
+        }
+        if let Some(line) = self.console.as_mut() {
+            line.push(character);
+            self.update_console_text();
+        }
+    }
+
+    /// Posts the line typed in the chat (if not empty), closing it. Online
+    /// the line is sent to the server, which relays it to the other players
+    /// and back.
+    fn post_chat(&mut self) {
+        if let Some(line) = self.chat.take() {
+            let line = line.trim();
+            match &self.session {
+                Some(session) if !line.is_empty() => session.chat(line),
+                None if !line.is_empty() => self.add_chat_line(format!("YOU: {}", line)),
+                _ => ()
+            };
+        }
+    }
+
+    /// Shows a new line of the chat above the board, replacing the oldest
+    /// one if there are too many.
+    fn add_chat_line(&mut self, line: String) {
+        debug!("chat: {}", line);
+        if self.chat_lines.len() == CHAT_LINES {
+            self.chat_lines.pop_front();
+        }
+        self.chat_lines.push_back((line, 0.0));
+    }
+
+    /// Ages the chat lines by the given frame time, removing the expired ones.
+    fn update_chat(&mut self, frame_time: Time) {
+        for (_, age) in &mut self.chat_lines {
+            *age += frame_time.as_seconds();
+        }
+        self.chat_lines.retain(|(_, age)| *age < CHAT_SECONDS);
+    }
+
+    /// Ages the cells left by the snake tail, forgetting the ones faded out.
+    fn update_trail(&mut self, frame_time: Time) {
+        for (_, age) in &mut self.trail {
+            *age += frame_time.as_seconds();
+        }
+        self.trail.retain(|(_, age)| *age < TRAIL_SECONDS);
+    }
+
+    /// Runs the command typed in the console, showing the outcome.
+    fn run_console_command(&mut self) {
+        let line = self.console.replace(String::new()).unwrap_or_default();
+        let result = line.parse().and_then(|command| self.execute(command));
+        self.console_message = match result {
+            Ok(()) => format!("OK: {}", line),
+            Err(err) => format!("{}: {}", err, line),
+        };
+    }
+
+    /// Executes a console command changing the game state. The run is not
+    /// saved nor submitted anymore.
+    fn execute(&mut self, command: Command) -> Result<(), &'static str> {
+        match command {
+            Command::SpawnFood(position) => {
+                let grid = self.board.rules.grid;
+                if position.x < 0 || position.y < 0 || position.x >= grid.columns || position.y >= grid.rows {
+                    return Err("The cell is outside the board");
+                }
+                if self.board.is_taken(position) {
+                    return Err("The cell is already taken");
+                }
+                // the closest food moves there
+                let nearest = self.board.nearest_food(position);
+                match self.board.foods.iter_mut().find(|food| Some(**food) == nearest) {
+                    Some(food) => *food = position,
+                    None => self.board.foods.push(position),
+                }
+            },
+            Command::SetScore(score) => {
+                self.board.score = score;
+                self.update_score();
+            },
+            Command::Speed(speed) => {
+                if speed == 0 || speed > 240 {
+                    return Err("The speed must be between 1 and 240");
+                }
+                self.board.rules.ticks_per_second = speed;
+                self.time_per_frame = Time::seconds(1.0 / self.board.rules.tick_rate() as f32);
+            },
+            Command::TimeScale(factor) => self.time_scale = TimeScale::new(factor)?,
+            // exporting the board leaves the run as is
+            Command::ExportBoard => {
+                fs::write(BOARD_FILE, self.board.to_ascii()).map_err(|_| "Unable to write the board file")?;
+                info!("board exported: path = {}", BOARD_FILE);
+                return Ok(());
+            },
+            Command::ImportBoard => {
+                let content = fs::read_to_string(BOARD_FILE).map_err(|_| "Unable to read the board file")?;
+                diagram::decode(&mut self.board, &content)?;
+                info!("board imported: path = {}", BOARD_FILE);
+            },
+            // a new run starts, it can be replayed
+            Command::Seed(seed) => {
+                self.restart(seed);
+                return Ok(());
+            },
+            Command::State(name) => match name {
+                StateName::Pause => self.pause(),
+                StateName::Play if !self.board.over => self.state = State::Play,
+                StateName::Play => return Err("The run is over"),
+                StateName::GameOver | StateName::Victory if self.board.over => return Err("The run is over"),
+                // the run must be marked before it ends, so that it is not saved
+                StateName::GameOver => {
+                    self.unranked = true;
+                    self.board.over = true;
+                    self.game_over();
+                },
+                StateName::Victory => {
+                    self.unranked = true;
+                    self.board.over = true;
+                    self.victory();
+                },
+            },
+        };
+        self.unranked = true;
+        Ok(())
+    }
+
+    /// Updates the console shown at the bottom of the window.
+    fn update_console_text(&mut self) {
+        let content = match &self.console {
+            Some(line) => format!("{}\n~ {}_", self.console_message, line),
+            None => String::new(),
+        };
+        self.console_text.set_string(&content.to_uppercase());
+        let margin = self.entity_size as f32 * self.hud_scale;
+        let height = self.console_text.character_size() as f32 * 3.0;
+        self.console_text.set_position((margin, self.window.size().y as f32 - margin - height));
+    }
+
+    /// Moves the tutorial to the next step if the given one is the current
+    /// one, marking the tutorial as done after the last step.
+    fn advance_tutorial(&mut self, done: TutorialStep) {
+        if self.tutorial != Some(done) {
+            return;
+        }
+        self.tutorial = done.next(self.board.rules.mode);
+        if self.tutorial.is_none() {
+            info!("tutorial completed");
+            self.settings.tutorial_done = true;
+        }
+        self.update_tutorial_text();
+    }
+
+    /// Updates the tutorial text, centered at the bottom of the window.
+    fn update_tutorial_text(&mut self) {
+        let layout = self.key_layout;
+        let message = self.tutorial.map(|step| step.message(layout)).unwrap_or("");
+        self.tutorial_text.set_string(message);
+        let size = self.window.size();
+        let bounds = self.tutorial_text.local_bounds();
+        let margin = self.entity_size as f32 * self.hud_scale;
+        let x = size.x as f32 / 2.0 - bounds.width / 2.0;
+        let y = size.y as f32 - margin * 2.0 - bounds.height;
+        self.tutorial_text.set_position((x, y));
+    }
+
+    /// Updates the debug overlay with the current diagnostics.
+    fn update_debug_text(&mut self) {
+        let diagnostics = &self.diagnostics;
+        let content = format!(
+            "FPS {:.0}\nTICKS/S {:.1} (TARGET {})\nTIME SCALE {:.2}X\nUPDATE {:.3} MS\nSEGMENTS {}\nSTATE {:?}",
+            diagnostics.render_fps,
+            diagnostics.tick_rate,
+            self.board.rules.tick_rate(),
+            self.time_scale.factor() * self.slow_motion_scale,
+            diagnostics.update_time.as_microseconds() as f32 / 1000.0,
+            self.board.snake.segments().len(),
+            self.state);
+        self.debug_text.set_string(&content.to_uppercase());
+    }
+
+    /// Updates the window title with the game mode, the score and the state
+    /// of the run.
+    fn update_title(&mut self) {
+        let state = match self.state {
+            State::Pause => " (paused)",
+            State::Play => "",
+            State::GameOver => " (game over)",
+            State::Victory => " (victory)",
+        };
+        let title = format!("Snake — {} — {} pts{}", self.board.rules.mode.name(), self.board.score, state);
+        self.window_meta.set_title(&mut self.window, title);
+    }
+
+    /// Publishes the current activity to Discord: the game mode, the score
+    /// and the time elapsed since the run started, or the menu shown.
+    #[cfg(feature = "discord")]
+    fn update_presence(&mut self) {
+        let presence = match self.presence.as_mut() {
+            Some(presence) => presence,
+            None => return
+        };
+        let activity = if !self.screens.is_empty() {
+            Activity { details: String::from("In menu"), state: String::new(), start: None }
+        } else {
+            let state = match self.state {
+                State::Pause => String::from("Paused"),
+                State::Play => format!("{} pts", self.board.score),
+                State::GameOver => format!("Game over, {} pts", self.board.score),
+                State::Victory => format!("Victory, {} pts", self.board.score),
+            };
+            // the elapsed time is only shown while playing
+            let start = match self.state {
+                State::Play => Some(Activity::seconds_ago(self.board.seconds())),
+                _ => None,
+            };
+            Activity { details: format!("Playing {}", self.board.rules.mode.name()), state, start }
+        };
+        presence.publish(activity);
+    }
+
+    /// Updates the run timer shown at the top of the window.
+    fn update_timer(&mut self) {
+        // the runs limited in time show the time left instead
+        let seconds = match self.board.rules.mode.variant().time_limit() {
+            Some(limit) => limit.saturating_sub(self.board.seconds()),
+            None => self.board.seconds(),
+        };
+        self.timer_text.set_string(&format_time(seconds));
+        let x = self.window.size().x as f32 / 2.0 - self.timer_text.local_bounds().width / 2.0;
+        self.timer_text.set_position((x, 10.0 * self.hud_scale));
+    }
+
+    /// Updates the round-trip time to the server (in milliseconds), shown
+    /// below the timer during the networked runs.
+    fn update_latency(&mut self, round_trip: u64) {
+        self.latency_text.set_string(&format!("RTT {} MS", round_trip));
+        let x = self.window.size().x as f32 / 2.0 - self.latency_text.local_bounds().width / 2.0;
+        let y = 10.0 * self.hud_scale + self.timer_text.character_size() as f32 * 1.2;
+        self.latency_text.set_position((x, y));
+    }
+
+    /// Updates the summary of the last run, shown below the game over and
+    /// victory texts.
+    fn update_summary(&mut self) {
+        let mut summary = format!("TIME {}", format_time(self.board.seconds()));
+        if self.board.rules.mode.variant().has_food() {
+            // the pace is meaningless for runs shorter than a second
+            let seconds = self.board.ticks as f32 / self.board.rules.tick_rate() as f32;
+            let minutes = seconds / 60.0;
+            let pace = if seconds >= 1.0 { self.board.food_count as f32 / minutes } else { 0.0 };
+            summary += &format!("  FOOD/MIN {:.1}", pace);
+        }
+        self.summary_text.set_string(&summary);
+        let size = self.window.size();
+        let bounds = self.summary_text.local_bounds();
+        let x = size.x as f32 / 2.0 - bounds.width / 2.0;
+        let y = size.y as f32 / 2.0 + self.over_text.character_size() as f32 * 1.5;
+        self.summary_text.set_position((x, y));
+    }
+
+    /// Updates the statistics of the current run, shown below the pause
+    /// overlay (they do not change while paused).
+    fn update_run_text(&mut self) {
+        let board = &self.board;
+        let mut stats = format!("CURRENT RUN\nLENGTH {}", board.snake.segments().count());
+        if board.rules.mode.variant().has_food() {
+            // the average is meaningless before the first food
+            let seconds = board.ticks as f32 / board.rules.tick_rate() as f32;
+            let per_food = if board.food_count > 0 { format!("{:.1} S", seconds / board.food_count as f32) } else { String::from("-") };
+            stats += &format!("\nFOODS {}\nTIME PER FOOD {}", board.food_count, per_food);
+        }
+        stats += &format!("\nTIME {}\nDISTANCE {} CELLS", format_time(board.seconds()), board.snake.distance());
+        self.run_text.set_string(&stats);
+        // centered below the pause text
+        let size = self.window.size();
+        let bounds = self.run_text.local_bounds();
+        let pause_bounds = self.pause_text.global_bounds();
+        let x = size.x as f32 / 2.0 - bounds.width / 2.0;
+        let y = pause_bounds.top + pause_bounds.height + self.run_text.character_size() as f32 * 2.0;
+        self.run_text.set_position((x, y));
+    }
+
+    /// Updates the score shown at the top right of the window.
+    fn update_score(&mut self) {
+        // the string is reused to avoid allocating it at every update
+        format_thousands(self.board.score, &mut self.score_string);
+        self.score_text.set_string(&self.score_string);
+        // align the right edge of the glyphs to the window margin
+        let margin = 10.0 * self.hud_scale;
+        let right = self.window.size().x as f32 - margin;
+        SnakeGame::align_right(&mut self.score_text, right, margin);
+        // the rival score is shown below the player one
+        format_thousands(self.board.rival_score, &mut self.score_string);
+        self.score_string.insert_str(0, "AI ");
+        self.rival_text.set_string(&self.score_string);
+        let y = margin + self.score_text.character_size() as f32 * 1.2;
+        SnakeGame::align_right(&mut self.rival_text, right, y);
+        self.update_intensity();
+    }
+
+    /// Draws the pause overlay: the pause image, or the board dimmed behind
+    /// two bars in the middle of the window and the way to resume. The
+    /// statistics of the run are shown below, once started.
+    fn draw_pause(&mut self) {
+        if self.in_run() {
+            self.window.draw(&self.run_text);
+        }
+        if let Some(sprite) = &self.pause_sprite {
+            self.window.draw(sprite);
+            return;
+        }
+        let size = self.window.size();
+        let center = Vector2f::new(size.x as f32, size.y as f32) / 2.0;
+        // the bars are as tall as the score
+        let height = self.text_size as f32 * self.hud_scale * 1.5;
+        let mut bar = RectangleShape::with_size(Vector2f::new(height / 3.0, height));
+        bar.set_fill_color(&Color::WHITE);
+        for side in &[-1.0, 1.0] {
+            let x = center.x + side * height / 3.0 - height / 6.0;
+            bar.set_position((x, center.y - height / 2.0));
+            self.window.draw(&bar);
+        }
+        self.window.draw(&self.pause_text);
+    }
+
+    /// Places the given text so that the right edge of its glyphs is at
+    /// the given horizontal position.
+    fn align_right(text: &mut Text, right: f32, y: f32) {
+        text.set_position((0.0, y));
+        let bounds = text.global_bounds();
+        text.set_position((right - (bounds.left + bounds.width), y));
+    }
+
+}
+
+impl<'a> Game for SnakeGame<'a> {
+
+    /// Runs the game.
     fn run(&mut self) {
-        println!("Hello from Snake!");
+        info!("Hello from Snake!");
         let mut clock = Clock::start();
         let mut time_since_last_update = Time::ZERO;
         // run main loop
         while self.window.is_open() {
             self.process_events();
-            time_since_last_update += clock.restart();
+            let frame_time = clock.restart();
+            self.update_slow_motion(frame_time);
+            self.update_screens(frame_time);
+            self.update_chat(frame_time);
+            self.update_trail(frame_time);
+            time_since_last_update += self.time_scale.scale(frame_time) * self.slow_motion_scale;
             let tpf = self.time_per_frame;
             // fixed time steps
             while time_since_last_update > tpf {
                 time_since_last_update -= tpf;
                 self.process_events();
-                self.update(tpf);
+                let update_clock = Clock::start();
+                self.update();
+                self.diagnostics.update_time = update_clock.elapsed_time();
+            }
+            self.diagnostics.record_frame(frame_time, self.board.ticks);
+            if self.debug_overlay {
+                self.update_debug_text();
+            }
+            #[cfg(feature = "online")]
+            self.poll_leaderboard();
+            self.poll_session();
+            self.update_title();
+            self.play_music();
+            #[cfg(feature = "discord")]
+            self.update_presence();
+            // all the atlas animations are advanced by the frame time
+            self.batch.animate(frame_time);
+            // while idle only the frames changed by an event are drawn, and
+            // the loop sleeps instead of spinning
+            if self.idle() && !self.redraw {
+                system::sleep(Time::milliseconds(IDLE_FRAME_TIME));
+            } else {
+                self.render();
+                self.redraw = false;
             }
-            self.render();
         }
+        info!("games = {}, victories = {}, best score = {}",
+            self.stats.games, self.stats.victories, self.stats.best_score);
     }
 
     /// Processes the window events.
     fn process_events(&mut self) {
         while let Some(event) = self.window.poll_event() {
+            self.redraw = true;
             match event {
-                Event::Closed => self.window.close(),
-                Event::KeyPressed { code, .. } => self.handle_input(code),
+                Event::Closed => self.request_quit(),
+                // the snake boosts while shift is held
+                Event::KeyPressed { code: Key::LShift, .. } | Event::KeyPressed { code: Key::RShift, .. } => self.boost(true),
+                Event::KeyReleased { code: Key::LShift, .. } | Event::KeyReleased { code: Key::RShift, .. } => self.boost(false),
+                Event::KeyPressed { code, shift, .. } => self.handle_input(code, shift),
+                Event::Resized { .. } => self.layout(),
+                Event::TextEntered { unicode } => self.enter_text(unicode),
+                Event::MouseButtonPressed { button: mouse::Button::Left, x, y } => {
+                    self.start_swipe(Vector2i::new(x, y));
+                    self.click(Vector2i::new(x, y));
+                },
+                Event::MouseMoved { x, y } => {
+                    self.hover(Vector2i::new(x, y));
+                    self.swipe_to(Vector2i::new(x, y));
+                },
+                Event::MouseButtonReleased { button: mouse::Button::Left, .. } => self.end_swipe(),
+                // only the first finger touching the screen swipes
+                Event::TouchBegan { finger: 0, x, y } => {
+                    self.start_swipe(Vector2i::new(x, y));
+                    self.click(Vector2i::new(x, y));
+                },
+                Event::TouchMoved { finger: 0, x, y } => self.swipe_to(Vector2i::new(x, y)),
+                Event::TouchEnded { finger: 0, .. } => self.end_swipe(),
+                Event::LostFocus => {
+                    // the release of shift is not received without focus
+                    self.boost(false);
+                    if self.auto_pause {
+                        self.pause();
+                    }
+                },
                 _ => ()
             };
         }
     }
 
     /// Update the game state.
-    fn update(&mut self, _time: Time) {
+    fn update(&mut self) {
+        // the networked runs tick with the updates of the server
+        if self.session.is_some() {
+            return;
+        }
+        // in practice mode holding backspace rewinds the run, even once over
+        self.rewinding = self.practice && self.console.is_none() && self.chat.is_none() && Key::BackSpace.is_pressed();
+        if self.rewinding {
+            self.rewind();
+            self.redraw = true;
+            return;
+        }
+        if self.demo {
+            self.play_demo();
+        }
         // check current game state
         match self.state {
-            State::Pause | State::GameOver => return,
+            State::Pause | State::GameOver | State::Victory => return,
             _ => ()
         };
-        // update the player position
-        self.player.advance(self.viewport);
-        // check collision with itself
-        if self.player.self_collision() {
-            self.game_over();
-        } else {
-            // check collision with food
-            match self.player.area().intersection(&self.food.area()) {
-                Some(_) => { 
-                    // increase snake length
-                    self.player.grow();
-                    // update food position
-                    let mut food_position = SnakeGame::random_position(self.viewport, self.entity_size);
-                    let mut food_area = FloatRect::new(
-                        food_position.x, food_position.y,
-                        self.entity_size as f32, self.entity_size as f32);
-                    // try a new position if the new one collides with the snake
-                    while self.player.collision(&food_area, 0) {
-                        food_position = SnakeGame::random_position(self.viewport, self.entity_size);
-                        food_area.left = food_position.x;
-                        food_area.top = food_position.y;
-                    }
-                    self.food.set_position(food_position);
-                    // increase score
-                    let new_score = self.score + 10;
-                    self.set_score(new_score);
-                    self.eat_sound.play();
-                },
-                None => ()
-            };
+        self.redraw = true;
+        let (score, seconds) = (self.board.score, self.board.seconds());
+        if self.practice {
+            // keep the last few seconds of the run
+            if self.history.len() as u32 >= REWIND_SECONDS * self.board.rules.tick_rate() {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.board.clone());
         }
+        let segments = self.trail_cells();
+        let events = self.board.tick();
+        self.play_tick(events, segments, score, seconds);
+        crash::record_tick(self.board.ticks, self.board.score);
     }
 
     /// Draws all the game entities.
     fn render(&mut self) {
-        self.window.clear(&self.back_color);
+        self.window.clear(&self.background());
         // draw entities
-        self.food.draw(&mut self.window);
-        self.player.draw(&mut self.window);
-        self.window.draw(&mut self.score_text);
-        self.window.draw(&mut self.border);
+        // all the entities are drawn with a single draw call
+        self.batch.clear();
+        let mutators = self.board.rules.mutators;
+        let head = self.board.snake.head_position();
+        self.batch.fog = if mutators.contains(Mutators::FOG_OF_WAR) { Some((self.board.rules.grid, head)) } else { None };
+        // the cells left by the tail fade out and shrink (unless the tail
+        // is invisible)
+        if !mutators.contains(Mutators::INVISIBLE_TAIL) {
+            for (cell, age) in &self.trail {
+                let fade = 1.0 - age / TRAIL_SECONDS;
+                let color = Color { a: (f32::from(self.snake_color.a) * fade * 0.5) as u8, ..self.snake_color };
+                self.batch.add_plain_cell(*cell, 0.5 + fade * 0.5, color);
+            }
+        }
+        for (i, portal) in self.board.portals.iter().enumerate() {
+            // each pair has its own color
+            portal.draw(&mut self.batch, &PORTAL_COLORS[i % PORTAL_COLORS.len()]);
+        }
+        if self.board.rules.mode.variant().has_food() {
+            for food in &self.board.foods {
+                self.batch.add_image(*food, "food", &self.food_color);
+            }
+            if let Some(poison) = self.board.poison {
+                self.batch.add_image(poison, "poison", &self.poison_color);
+            }
+            if let Some(magnet) = self.board.magnet {
+                self.batch.add_image(magnet, "magnet", &MAGNET_COLOR);
+            }
+            // the food pulled by the magnet is linked to the snake head
+            let head = self.board.snake.head_position();
+            let pulled = self.board.nearest_food(head).filter(|_| self.board.magnet_ticks > 0);
+            if let Some(food) = pulled {
+                let (from, to) = (self.batch.layout.center(head), self.batch.layout.center(food));
+                let thickness = (self.batch.layout.cell_size / 8.0).max(1.0);
+                self.batch.add_line(from, to, thickness, MAGNET_COLOR);
+            }
+        }
+        if let Some(star) = self.board.star {
+            self.batch.add_image(star, "star", &STAR_COLOR);
+        }
+        for enemy in &self.board.enemies {
+            enemy.draw(&mut self.batch, &self.enemy_color);
+        }
+        // the snakes of the other players move smoothly between the updates
+        // of the server, from the board before the last one
+        let (previous, progress, seat) = match self.session.as_ref() {
+            Some(session) => (session.client.as_ref().and_then(|client| client.previous.as_ref()), session.interpolation(), session.seat),
+            None => (None, 1.0, None)
+        };
+        if let Some(rival) = &self.board.rival {
+            self.batch.add_moving_snake(rival, previous.and_then(|board| board.rival.as_ref()), progress, &RIVAL_COLOR);
+        }
+        if let Some(partner) = &self.board.partner {
+            let previous = previous.filter(|_| seat != Some(1)).and_then(|board| board.partner.as_ref());
+            self.batch.add_moving_snake(partner, previous, progress, &PARTNER_COLOR);
+        }
+        let modifiers = self.board.rules.modifiers;
+        // the snake flashes while the star lasts
+        let flash = self.board.star_ticks > 0 && (self.board.star_ticks / 2).is_multiple_of(2);
+        let snake_color = if flash { STAR_COLOR } else { self.snake_color };
+        // with the invisible tail only the head is shown
+        let tail_hidden = mutators.contains(Mutators::INVISIBLE_TAIL);
+        if modifiers.rainbow {
+            let shown = if tail_hidden { 1 } else { usize::MAX };
+            for (i, segment) in self.board.snake.segments().take(shown).enumerate() {
+                self.batch.add_cell(*segment, &rainbow_color(i));
+            }
+        } else if tail_hidden {
+            self.batch.add_head(&self.board.snake, &snake_color);
+        } else if let Some(previous) = previous.filter(|_| seat != Some(0)) {
+            self.batch.add_moving_snake(&self.board.snake, Some(&previous.snake), progress, &snake_color);
+        } else {
+            self.board.snake.draw(&mut self.batch, &snake_color);
+        }
+        if modifiers.big_head {
+            let color = if modifiers.rainbow { rainbow_color(0) } else { snake_color };
+            self.batch.add_scaled_cell(head, 1.6, &color);
+        }
+        if self.heatmap_visible() {
+            self.add_heatmap();
+        }
+        if self.card_pending {
+            self.card_pending = false;
+            self.save_card();
+        }
+        #[cfg(feature = "debug-tools")]
+        {
+            if self.hitboxes {
+                self.add_hitboxes();
+            }
+        }
+        // the camera follows the center of the snake head
+        self.camera.follow(self.batch.layout.center(head));
+        self.window.set_view(&self.camera.view);
+        // behind the overlays the board is drawn from a snapshot, blurred if
+        // possible
+        let (background, covered) = (self.background(), self.covered());
+        match self.snapshot.as_mut().filter(|_| covered) {
+            Some(snapshot) => {
+                snapshot.clear(&background);
+                snapshot.set_view(&self.camera.view);
+                self.batch.draw(snapshot);
+                snapshot.display();
+                self.window.set_view(&self.hud_view);
+                let states = RenderStates { shader: self.blur.as_ref(), ..Default::default() };
+                self.window.draw_with_renderstates(&Sprite::with_texture(snapshot.texture()), states);
+                self.window.set_view(&self.camera.view);
+            },
+            None => self.batch.draw(&mut self.window),
+        };
+        // tint the board while in slow motion
+        if self.slow_motion_scale < 1.0 {
+            let mut tint = RectangleShape::with_size(self.camera.board_size);
+            let alpha = ((1.0 - self.slow_motion_scale) * 120.0) as u8;
+            tint.set_fill_color(&Color::rgba(0, 0, 64, alpha));
+            self.window.draw(&tint);
+        }
+        // draw the whole board and the region shown by the camera in the minimap
+        if let Some(minimap) = &self.camera.minimap {
+            self.window.set_view(minimap);
+            let mut background = RectangleShape::with_size(self.camera.board_size);
+            background.set_fill_color(&Color::rgba(0, 0, 0, 160));
+            self.window.draw(&background);
+            self.batch.draw(&mut self.window);
+            let mut frame = RectangleShape::with_size(self.camera.view.size());
+            frame.set_position(self.camera.view.center() - self.camera.view.size() / 2.0);
+            frame.set_fill_color(&Color::TRANSPARENT);
+            frame.set_outline_color(&Color::WHITE);
+            frame.set_outline_thickness(self.batch.layout.cell_size / 2.0);
+            self.window.draw(&frame);
+        }
+        // the HUD is drawn in window coordinates, over the board dimmed
+        // behind the overlays
+        self.window.set_view(&self.hud_view);
+        if self.covered() {
+            self.window.draw(&self.dim);
+        }
+        self.window.draw(&self.score_text);
+        if self.board.rival.is_some() {
+            self.window.draw(&self.rival_text);
+        }
+        self.window.draw(&self.timer_text);
+        if self.networked() {
+            self.window.draw(&self.latency_text);
+        }
+        self.window.draw(&self.border);
         match self.state {
-            State::Pause => self.window.draw(&mut self.pause_sprite),
-            State::GameOver => self.window.draw(&mut self.over_text),
+            State::Pause if self.rewinding => self.window.draw(&self.rewind_text),
+            State::Pause => self.draw_pause(),
+            State::GameOver => {
+                self.window.draw(&self.over_text);
+                self.window.draw(&self.summary_text);
+            },
+            State::Victory => {
+                self.window.draw(&self.victory_text);
+                self.window.draw(&self.summary_text);
+            },
             _ => ()
         };
+        if self.debug_overlay {
+            self.window.draw(&self.debug_text);
+        }
+        if self.console.is_some() {
+            self.window.draw(&self.console_text);
+        }
+        if self.tutorial.is_some() {
+            self.window.draw(&self.tutorial_text);
+        }
+        // draw the bounds of the area where the board is shown
+        #[cfg(feature = "debug-tools")]
+        {
+            if self.hitboxes {
+                let size = self.window.size();
+                let margin = self.entity_size as f32 * self.hud_scale;
+                let viewport = SnakeGame::viewport(Vector2f::new(size.x as f32, size.y as f32), margin);
+                let mut bounds = RectangleShape::with_size(Vector2f::new(viewport.width, viewport.height));
+                bounds.set_position(Vector2f::new(viewport.left, viewport.top));
+                bounds.set_fill_color(&Color::TRANSPARENT);
+                bounds.set_outline_color(&Color::MAGENTA);
+                bounds.set_outline_thickness(2.0);
+                self.window.draw(&bounds);
+            }
+        }
+        #[cfg(feature = "online")]
+        {
+            if self.leaderboard_open {
+                self.window.draw(&self.leaderboard_text);
+            }
+        }
+        if self.quit_dialog {
+            // the option under the mouse is highlighted
+            if let Some(key) = self.hovered {
+                let bounds = self.option_bounds(key);
+                let mut highlight = RectangleShape::with_size(Vector2f::new(bounds.width, bounds.height));
+                highlight.set_position((bounds.left, bounds.top));
+                highlight.set_fill_color(&Color::rgba(255, 255, 255, 96));
+                self.window.draw(&highlight);
+            }
+            self.window.draw(&self.quit_text);
+        }
+        // the screen on top covers the whole window
+        if let Some(screen) = self.screens.last().cloned() {
+            let opacity = screen.opacity(self.screen_time);
+            let fade = |color: Color| Color { a: (f32::from(color.a) * opacity) as u8, ..color };
+            let size = self.window.size();
+            let mut background = RectangleShape::with_size(Vector2f::new(size.x as f32, size.y as f32));
+            background.set_fill_color(&fade(self.back_color));
+            self.window.draw(&background);
+            let text = match screen {
+                Screen::Splash => &mut self.splash_text,
+                Screen::Credits => &mut self.credits_text,
+                Screen::Scoreboard | Screen::MatchOver => &mut self.match_text,
+                Screen::Lobby => &mut self.lobby_text,
+            };
+            text.set_fill_color(&fade(self.text_color));
+            self.window.draw(text);
+        }
+        // the chat lines fade out above the board (below the lobby text),
+        // followed by the line being typed
+        let margin = self.entity_size as f32 * self.hud_scale;
+        let line_height = self.chat_text.character_size() as f32 * 1.5;
+        let typing = self.chat.as_ref().map(|line| (format!("> {}_", line), 0.0));
+        let lines = self.chat_lines.len() + typing.iter().count();
+        let top = if self.in_lobby() { self.window.size().y as f32 - margin - lines as f32 * line_height } else { margin };
+        for (i, (line, age)) in self.chat_lines.iter().chain(typing.iter()).enumerate() {
+            let opacity = ((CHAT_SECONDS - age) / FADE_SECONDS).min(1.0);
+            self.chat_text.set_string(&line.to_uppercase());
+            self.chat_text.set_fill_color(&Color { a: (f32::from(self.text_color.a) * opacity) as u8, ..self.text_color });
+            self.chat_text.set_position((margin, top + i as f32 * line_height));
+            self.window.draw(&self.chat_text);
+        }
         self.window.display();
     }
 
 }
 
+/// Formats the given number of seconds as minutes and seconds (e.g. `1:05`).
+fn format_time(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Gets the color of the snake segment with the given index in rainbow
+/// mode, cycling through the hues along the snake.
+fn rainbow_color(index: usize) -> Color {
+    let hue = (index as f32 * 30.0) % 360.0 / 60.0;
+    let x = ((1.0 - (hue % 2.0 - 1.0).abs()) * 255.0) as u8;
+    match hue as u32 {
+        0 => Color::rgb(255, x, 0),
+        1 => Color::rgb(x, 255, 0),
+        2 => Color::rgb(0, 255, x),
+        3 => Color::rgb(0, x, 255),
+        4 => Color::rgb(x, 0, 255),
+        _ => Color::rgb(255, 0, x),
+    }
+}
+
+/// Gets the color of the heatmap for the given heat (from 0 to 1): from
+/// blue for the cells seldom visited, through green and yellow, to red for
+/// the most visited ones.
+fn heat_color(heat: f32) -> Color {
+    let hue = (1.0 - heat.clamp(0.0, 1.0)) * 4.0;
+    let x = ((1.0 - (hue % 2.0 - 1.0).abs()) * 255.0) as u8;
+    let color = match hue as u32 {
+        0 => Color::rgb(255, x, 0),
+        1 => Color::rgb(x, 255, 0),
+        2 => Color::rgb(0, 255, x),
+        _ => Color::rgb(0, x, 255),
+    };
+    Color { a: 160, ..color }
+}
+
+/// Formats the given number with thousands separators (e.g. `1,230`) into
+/// the given string, replacing its content.
+fn format_thousands(n: u32, string: &mut String) {
+    string.clear();
+    let _ = write!(string, "{}", n);
+    let mut i = string.len();
+    while i > 3 {
+        i -= 3;
+        string.insert(i, ',');
+    }
+}
+
 /// Runs the Snake game.
-pub fn run(config: Config) -> Result<(), Box<Error>> {
-    let resources = Resources::new();
-    let mut game = SnakeGame::new(&config, &resources);
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    run_with_hooks(config, Vec::new())
+}
+
+/// Runs the Snake game, notifying the given hooks of the ticks of every
+/// run.
+pub fn run_with_hooks(config: Config, hooks: Vec<Box<dyn GameHooks>>) -> Result<(), Box<dyn Error>> {
+    // the RUST_LOG variable can be used to filter the events by module
+    let mut logger = Builder::from_env(Env::default().default_filter_or("info"));
+    if let Some(level) = config.log_level {
+        logger.filter_level(level);
+    }
+    if let Some(path) = &config.log_file {
+        logger.target(Target::Pipe(Box::new(File::create(path)?)));
+    }
+    logger.init();
+    crash::install(format!("{:#?}", config));
+    // create the window, where it was in the last session, to show the
+    // loading screen
+    let manifest = Manifest::load(Path::new(&config.resources))?;
+    let mut assets = manifest.required()?;
+    assets.extend(manifest.optional("font")?);
+    assets.extend(manifest.optional("atlas")?);
+    assets.extend(manifest.optional("atlas-regions")?);
+    for stem in &MUSIC_STEMS {
+        assets.extend(manifest.optional(stem)?);
+    }
+    let settings = Settings::load();
+    let monitor = match config.monitor {
+        Some(index) => Some(monitors::find(index, SnakeGame::main_monitor())?),
+        None => None
+    };
+    let mut window = SnakeGame::open_window(SnakeGame::window_size(&config), &settings, config.borderless, monitor);
+    let resources = Resources::load(&mut window, assets, &config)?;
+    let mut game = SnakeGame::new(&config, &resources, settings, window);
+    game.hooks = hooks;
+    game.monitor = monitor;
+    // the networked play joins the server hosted by the game (playing with
+    // its rules), or the one given
+    if let Some(port) = config.host {
+        let server = Server::bind(port, game.board.rules.clone())?;
+        thread::spawn(move || {
+            if let Err(err) = server.run() {
+                error!("server stopped: {}", err);
+            }
+        });
+    }
+    let address = config.host.map(|port| format!("127.0.0.1:{}", port)).or_else(|| config.connect.clone());
+    if let Some(address) = address {
+        game.go_online(Session::open(&address, &config.player)?);
+    }
     game.run();
     Ok(())
 }
+
+/// Verifies that the replay saved in the given file gives the score it
+/// claims, simulating the whole run without opening any window.
+pub fn verify(path: &str) -> Result<u32, Box<dyn Error>> {
+    let replay = Replay::load(Path::new(path))?;
+    Ok(replay.verify()?)
+}
+
+/// Runs the server of the networked play on the given port without opening
+/// any window: the players meet in its lobby, and the runs of their matches
+/// are played in co-op mode.
+pub fn serve(port: u16) -> Result<(), Box<dyn Error>> {
+    Builder::from_env(Env::default().default_filter_or("info")).init();
+    let rules = Rules::new(Mode::Coop, Grid::new(SERVER_COLUMNS, SERVER_ROWS));
+    Server::bind(port, rules)?.run()?;
+    Ok(())
+}
+
+/// Outcome of a headless simulation.
+pub struct Simulation {
+    pub ticks: u32,     // number of ticks simulated
+    pub score: u32,     // score at the last tick
+    pub elapsed: f64,   // time spent simulating (in seconds)
+}
+
+impl Simulation {
+
+    /// Gets the number of ticks simulated per second.
+    pub fn ticks_per_second(&self) -> f64 {
+        if self.elapsed > 0.0 { self.ticks as f64 / self.elapsed } else { 0.0 }
+    }
+
+}
+
+/// Simulates the run of the replay saved in the given file as fast as
+/// possible, without any window nor frame limit.
+/// * `max_ticks` - Maximum number of ticks to simulate.
+pub fn simulate(path: &str, max_ticks: Option<u32>) -> Result<Simulation, Box<dyn Error>> {
+    let replay = Replay::load(Path::new(path))?;
+    let start = Instant::now();
+    let board = replay.simulate_until(max_ticks.unwrap_or(replay.ticks), |_| ());
+    Ok(Simulation {
+        ticks: board.ticks,
+        score: board.score,
+        elapsed: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// Headless benchmark of the core: a snake moving along a row of the
+/// board, long enough for it never to run into itself, ticking as fast as
+/// possible.
+pub struct Bench {
+    board: Board,                   // board grown to the length of the snake
+    ticks: u32,                     // number of ticks to play
+    hooks: Vec<Box<dyn GameHooks>>, // hooks notified of the ticks played
+}
+
+impl Bench {
+
+    /// Prepares the benchmark of the given number of ticks, growing the
+    /// snake to the given length first (not to be measured).
+    pub fn new(ticks: u32, snake_length: u32) -> Result<Bench, &'static str> {
+        if snake_length == 0 {
+            return Err("The snake length must be positive");
+        }
+        if snake_length > MAX_BENCH_SNAKE_LENGTH {
+            return Err("The snake length is too large for the benchmark");
+        }
+        // the snake moves along the first row, twice as long as it
+        let columns = snake_length.checked_mul(2)
+            .filter(|columns| *columns <= i32::MAX as u32)
+            .map(|columns| (columns as i32).max(BENCH_COLUMNS))
+            .filter(|columns| columns.checked_mul(BENCH_ROWS).is_some() && snake_length < *columns as u32);
+        let columns = match columns {
+            Some(columns) => columns,
+            None => return Err("The snake does not fit the benchmark board")
+        };
+        let mut rules = Rules::new(Mode::Classic, Grid::new(columns, BENCH_ROWS));
+        rules.growth_per_food = 0;
+        rules.start_column = Some(0);
+        rules.start_row = Some(0);
+        rules.start_direction = Some(Direction::Right);
+        let mut board = Board::new(rules, 0);
+        board.snake.grow(snake_length as i32 - 1);
+        while (board.snake.segments().count() as u32) < snake_length {
+            board.tick();
+        }
+        Ok(Bench { board, ticks, hooks: Vec::new() })
+    }
+
+    /// Notifies the given hooks of the ticks played by the benchmark (their
+    /// time being measured too).
+    pub fn with_hooks(mut self, hooks: Vec<Box<dyn GameHooks>>) -> Bench {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Plays the ticks of the benchmark.
+    pub fn run(mut self) -> Benchmark {
+        let collision_checks = board::collision_checks();
+        let start = Instant::now();
+        for _ in 0..self.ticks {
+            hooks::tick(&mut self.board, &mut self.hooks);
+        }
+        Benchmark {
+            ticks: self.ticks,
+            elapsed: start.elapsed().as_secs_f64(),
+            collision_checks: board::collision_checks() - collision_checks,
+        }
+    }
+
+}
+
+/// Outcome of a headless benchmark.
+pub struct Benchmark {
+    pub ticks: u32,             // number of ticks played
+    pub elapsed: f64,           // time spent playing them (in seconds)
+    pub collision_checks: u64,  // number of cells checked for collisions with the snakes
+}
+
+impl Benchmark {
+
+    /// Gets the number of ticks played per second.
+    pub fn ticks_per_second(&self) -> f64 {
+        if self.elapsed > 0.0 { self.ticks as f64 / self.elapsed } else { 0.0 }
+    }
+
+}
+
+/// Exports the run of the replay saved in the given file as an animated
+/// GIF, rendered offline.
+pub fn export(path: &str, output: &str) -> Result<(), Box<dyn Error>> {
+    let replay = Replay::load(Path::new(path))?;
+    export::write_gif(&replay, Path::new(output))
+}
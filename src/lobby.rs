@@ -0,0 +1,219 @@
+//! Rooms where the players of a networked match meet before it starts:
+//! each player picks a color and gets ready, then the player hosting the
+//! room starts the match.
+
+use protocol::Message;
+
+
+/// Colors the players can pick, one per player of a room.
+pub const COLORS: [&str; 4] = ["green", "blue", "orange", "magenta"];
+
+
+/// Player waiting in a room.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub player: String, // player name
+    pub color: String,  // color of the player snake (one of `COLORS`)
+    pub ready: bool,    // whether the player is ready to start
+}
+
+
+/// Room of the lobby, hosted by its first member.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Room {
+    pub name: String,           // room name, unique in the lobby
+    pub members: Vec<Member>,   // players in the room, the first one hosting it
+}
+
+impl Room {
+
+    /// Gets the message describing the room to its members.
+    pub fn state(&self) -> Message {
+        Message::RoomState { room: self.name.clone(), members: self.members.clone() }
+    }
+
+}
+
+
+/// Rooms hosted by the server, each player being in one room at most.
+#[derive(Debug, Default)]
+pub struct Lobby {
+    rooms: Vec<Room>,   // rooms waiting for their match to start
+}
+
+impl Lobby {
+
+    /// Handles a message received from the given player, returning the
+    /// message to send back: the rooms when listed, otherwise the state of
+    /// the room of the player (to be sent to all its members). The matches
+    /// are started with `start` instead.
+    pub fn receive(&mut self, player: &str, message: Message) -> Result<Option<Message>, &'static str> {
+        match message {
+            Message::ListRooms => return Ok(Some(self.list())),
+            Message::CreateRoom { room } => {
+                if room.trim().is_empty() {
+                    return Err("The room name cannot be empty");
+                }
+                if self.rooms.iter().any(|other| other.name == room) {
+                    return Err("A room with the same name already exists");
+                }
+                self.leave(player);
+                let member = Member { player: player.to_string(), color: COLORS[0].to_string(), ready: false };
+                info!("room created: room = {}, host = {}", room, player);
+                self.rooms.push(Room { name: room, members: vec![member] });
+            },
+            Message::JoinRoom { room } => {
+                let target = self.rooms.iter().find(|other| other.name == room).ok_or("The room does not exist")?;
+                // joining the room the player is already in changes nothing
+                if target.members.iter().any(|member| member.player == player) {
+                    return Ok(Some(target.state()));
+                }
+                // each player gets the first color not taken, the player
+                // leaving its room only once there is room for it
+                let color = COLORS.iter()
+                    .find(|color| target.members.iter().all(|member| member.color != **color))
+                    .ok_or("The room is full")?;
+                self.leave(player);
+                let target = self.rooms.iter_mut().find(|other| other.name == room).ok_or("The room does not exist")?;
+                target.members.push(Member { player: player.to_string(), color: color.to_string(), ready: false });
+            },
+            Message::LeaveRoom => {
+                self.leave(player);
+                return Ok(None);
+            },
+            Message::PickColor { color } => {
+                if !COLORS.contains(&color.as_str()) {
+                    return Err("Invalid color: <green|blue|orange|magenta>");
+                }
+                let room = self.room_of(player).ok_or("The player is not in a room")?;
+                if room.members.iter().any(|member| member.color == color && member.player != player) {
+                    return Err("The color is taken by another player");
+                }
+                room.members.iter_mut().filter(|member| member.player == player).for_each(|member| member.color = color.clone());
+            },
+            Message::Ready { ready } => {
+                let room = self.room_of(player).ok_or("The player is not in a room")?;
+                room.members.iter_mut().filter(|member| member.player == player).for_each(|member| member.ready = ready);
+            },
+            _ => return Err("Unexpected message in the lobby")
+        };
+        Ok(self.room_of(player).map(|room| room.state()))
+    }
+
+    /// Removes the given player from its room (if any), as when leaving it
+    /// or disconnecting. The next member hosts the room if the player was
+    /// hosting it, and the room is closed once empty.
+    pub fn leave(&mut self, player: &str) {
+        for room in &mut self.rooms {
+            room.members.retain(|member| member.player != player);
+        }
+        self.rooms.retain(|room| !room.members.is_empty());
+    }
+
+    /// Starts the match of the room hosted by the given player, once all
+    /// its members are ready, closing the room. Returns the room, whose
+    /// members then join the run.
+    pub fn start(&mut self, player: &str) -> Result<Room, &'static str> {
+        let index = self.rooms.iter()
+            .position(|room| room.members[0].player == player)
+            .ok_or("Only the player hosting the room can start the match")?;
+        if !self.rooms[index].members.iter().all(|member| member.ready) {
+            return Err("Not all the players are ready");
+        }
+        let room = self.rooms.remove(index);
+        info!("match started: room = {}, players = {}", room.name, room.members.len());
+        Ok(room)
+    }
+
+    /// Gets the message listing the rooms, with their number of players.
+    pub fn list(&self) -> Message {
+        let rooms = self.rooms.iter().map(|room| (room.name.clone(), room.members.len() as u32)).collect();
+        Message::Rooms { rooms }
+    }
+
+    /// Gets the room of the given player, if any.
+    pub fn room(&self, player: &str) -> Option<&Room> {
+        self.rooms.iter().find(|room| room.members.iter().any(|member| member.player == player))
+    }
+
+    /// Gets the room of the given player to change it, if any.
+    fn room_of(&mut self, player: &str) -> Option<&mut Room> {
+        self.rooms.iter_mut().find(|room| room.members.iter().any(|member| member.player == player))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(lobby: &mut Lobby, player: &str, message: Message) -> Result<Option<Message>, &'static str> {
+        lobby.receive(player, message)
+    }
+
+    fn create(lobby: &mut Lobby, player: &str, room: &str) {
+        send(lobby, player, Message::CreateRoom { room: room.to_string() }).unwrap();
+    }
+
+    fn join(lobby: &mut Lobby, player: &str, room: &str) -> Result<Option<Message>, &'static str> {
+        send(lobby, player, Message::JoinRoom { room: room.to_string() })
+    }
+
+    fn members(lobby: &Lobby, room: &str) -> Vec<String> {
+        lobby.rooms.iter().find(|other| other.name == room)
+            .map(|room| room.members.iter().map(|member| member.player.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn players_keep_their_room_when_unable_to_join() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, "alice", "den");
+        assert!(join(&mut lobby, "alice", "nowhere").is_err());
+        assert!(send(&mut lobby, "alice", Message::CreateRoom { room: " ".to_string() }).is_err());
+        assert_eq!(members(&lobby, "den"), vec!["alice"]);
+        // a full room
+        create(&mut lobby, "bob", "full");
+        for player in &["carol", "dave", "erin"] {
+            join(&mut lobby, player, "full").unwrap();
+        }
+        assert!(join(&mut lobby, "alice", "full").is_err());
+        assert_eq!(members(&lobby, "den"), vec!["alice"]);
+    }
+
+    #[test]
+    fn rejoining_a_room_changes_nothing() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, "alice", "den");
+        join(&mut lobby, "bob", "den").unwrap();
+        send(&mut lobby, "bob", Message::Ready { ready: true }).unwrap();
+        let state = lobby.rooms[0].state();
+        assert_eq!(join(&mut lobby, "alice", "den"), Ok(Some(state.clone())));
+        assert_eq!(join(&mut lobby, "bob", "den"), Ok(Some(state)));
+        assert_eq!(members(&lobby, "den"), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn players_move_between_rooms() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, "alice", "den");
+        create(&mut lobby, "bob", "cave");
+        join(&mut lobby, "carol", "den").unwrap();
+        send(&mut lobby, "carol", Message::PickColor { color: "orange".to_string() }).unwrap();
+        // the room left empty is closed, the player getting a free color
+        join(&mut lobby, "bob", "den").unwrap();
+        assert_eq!(members(&lobby, "cave"), Vec::<String>::new());
+        assert_eq!(lobby.rooms.len(), 1);
+        assert_eq!(lobby.rooms[0].members[2].color, "blue");
+        // the next member hosts the room
+        send(&mut lobby, "alice", Message::LeaveRoom).unwrap();
+        assert!(lobby.start("alice").is_err());
+        assert!(lobby.start("carol").is_err());
+        for player in &["carol", "bob"] {
+            send(&mut lobby, player, Message::Ready { ready: true }).unwrap();
+        }
+        assert_eq!(lobby.start("carol").map(|room| room.members.len()), Ok(2));
+    }
+
+}
@@ -3,8 +3,160 @@ extern crate snake;
 use std::env;
 use std::process;
 
+
+/// Allocator counting the allocations, to report them in the benchmark.
+#[cfg(feature = "count-allocations")]
+mod allocations {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Number of allocations made so far.
+    pub static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counting the allocations.
+    pub struct Counter;
+
+    unsafe impl GlobalAlloc for Counter {
+
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+    }
+
+    /// Gets the number of allocations made so far.
+    pub fn count() -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: allocations::Counter = allocations::Counter;
+
+/// Gets the number of allocations made so far, if counted.
+fn allocations() -> Option<u64> {
+    #[cfg(feature = "count-allocations")]
+    return Some(allocations::count());
+    #[cfg(not(feature = "count-allocations"))]
+    None
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
+    // verify one or more replays without running the game
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let paths = &args[2..];
+        if paths.is_empty() {
+            eprintln!("Usage: snake verify <replay>...");
+            process::exit(1);
+        }
+        let mut valid = true;
+        for path in paths {
+            // each result is prefixed by its file when verifying several ones
+            let prefix = if paths.len() > 1 { format!("{}: ", path) } else { String::new() };
+            match snake::verify(path) {
+                Ok(score) => println!("{}Valid replay, score {}.", prefix, score),
+                Err(err) => {
+                    eprintln!("{}Invalid replay: {}.", prefix, err);
+                    valid = false;
+                }
+            };
+        }
+        if !valid {
+            process::exit(1);
+        }
+        return;
+    }
+    // simulate a replay as fast as possible without running the game
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        let usage = || -> ! {
+            eprintln!("Usage: snake simulate <replay> [--max-ticks <n>]");
+            process::exit(1);
+        };
+        let (path, max_ticks) = match (args.get(2), args.get(3).map(String::as_str), args.get(4)) {
+            (Some(path), None, None) => (path, None),
+            (Some(path), Some("--max-ticks"), Some(count)) => (path, Some(count.parse().unwrap_or_else(|_| usage()))),
+            _ => usage()
+        };
+        match snake::simulate(path, max_ticks) {
+            Ok(simulation) => println!("Simulated {} ticks in {:.3} s ({:.0} ticks/s), score {}.",
+                simulation.ticks, simulation.elapsed, simulation.ticks_per_second(), simulation.score),
+            Err(err) => {
+                eprintln!("Unable to simulate the replay: {}.", err);
+                process::exit(1);
+            }
+        };
+        return;
+    }
+    // benchmark the headless core without running the game
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let usage = || -> ! {
+            eprintln!("Usage: snake bench [--ticks <n>] [--snake-len <n>]");
+            process::exit(1);
+        };
+        let (mut ticks, mut snake_length) = (1_000_000, 100);
+        for option in args[2..].chunks(2) {
+            // the numbers may have underscores as separators (e.g. 1_000_000)
+            let value = option.get(1).and_then(|value| value.replace('_', "").parse().ok()).unwrap_or_else(|| usage());
+            match option[0].as_str() {
+                "--ticks" => ticks = value,
+                "--snake-len" => snake_length = value,
+                _ => usage()
+            };
+        }
+        let bench = snake::Bench::new(ticks, snake_length).unwrap_or_else(|err| {
+            eprintln!("Unable to run the benchmark: {}.", err);
+            process::exit(1);
+        });
+        let allocations_before = allocations();
+        let benchmark = bench.run();
+        println!("Benchmarked {} ticks of a snake of length {} in {:.3} s ({:.0} ticks/s).",
+            benchmark.ticks, snake_length, benchmark.elapsed, benchmark.ticks_per_second());
+        let per_tick = |count: u64| count as f64 / f64::from(benchmark.ticks.max(1));
+        println!("Collision checks: {} ({:.1} per tick).", benchmark.collision_checks, per_tick(benchmark.collision_checks));
+        if let (Some(before), Some(after)) = (allocations_before, allocations()) {
+            println!("Allocations: {} ({:.2} per tick).", after - before, per_tick(after - before));
+        }
+        return;
+    }
+    // export a replay as an animation without running the game
+    if args.get(1).map(String::as_str) == Some("replay") {
+        let (path, output) = match (args.get(2), args.get(3).map(String::as_str), args.get(4)) {
+            (Some(path), Some("--export"), Some(output)) => (path, output),
+            _ => {
+                eprintln!("Usage: snake replay <replay> --export <out.gif>");
+                process::exit(1);
+            }
+        };
+        if let Err(err) = snake::export(path, output) {
+            eprintln!("Unable to export the replay: {}.", err);
+            process::exit(1);
+        }
+        return;
+    }
+    // host the lobby of the networked play without running the game
+    if args.get(1).map(String::as_str) == Some("server") {
+        let usage = || -> ! {
+            eprintln!("Usage: snake server [--port <port>]");
+            process::exit(1);
+        };
+        let port = match (args.get(2).map(String::as_str), args.get(3)) {
+            (None, None) => snake::net::DEFAULT_PORT,
+            (Some("--port"), Some(port)) => port.parse().unwrap_or_else(|_| usage()),
+            _ => usage()
+        };
+        if let Err(err) = snake::serve(port) {
+            eprintln!("Unable to run the server: {}.", err);
+            process::exit(1);
+        }
+        return;
+    }
     // parse config from arguments
     let config = snake::Config::new(&args).unwrap_or_else(|err| {
         eprintln!("Error parsing arguments: {}.", err);
@@ -0,0 +1,141 @@
+//! Manifest of the resources, listing their checksums to validate them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// Name of the manifest file, within the resources directory.
+const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Names of the resources required by the game, in the order they are loaded
+/// (the font is optional, since the game embeds a default one, and so is the
+/// pause image, the pause overlay being drawn without it).
+pub const REQUIRED: [&str; 2] = ["eat-sound", "over-sound"];
+
+
+/// A resource listed in the manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Asset {
+    pub name: String,           // name of the resource (e.g. `font`)
+    pub path: PathBuf,          // path of the resource file
+    pub crc32: Option<u32>,     // expected checksum of the file (not checked if none)
+    pub credit: Option<String>, // author and license of the resource, shown in the credits
+}
+
+impl Asset {
+
+    /// Gets the line listing the resource in the credits.
+    pub fn credit_line(&self) -> String {
+        let file = self.path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        match &self.credit {
+            Some(credit) => format!("{}: {} - {}", self.name, file, credit),
+            None => format!("{}: {}", self.name, file),
+        }
+    }
+
+    /// Checks that the given file content matches the expected checksum.
+    pub fn verify(&self, content: &[u8]) -> Result<(), String> {
+        match self.crc32 {
+            Some(expected) if crc32(content) != expected => {
+                Err(format!("{} is corrupted (checksum mismatch)", self.path.display()))
+            },
+            _ => Ok(())
+        }
+    }
+
+}
+
+
+/// List of the resources of an asset pack, made of a section for each
+/// resource with its file, optional checksum and optional credit:
+///
+/// ```toml
+/// [font]
+/// file = "joystix.ttf"
+/// crc32 = "a35b364a"
+/// credit = "Joystix by Typodermic Fonts"
+/// ```
+pub struct Manifest {
+    assets: Vec<Asset>, // resources listed, in order
+}
+
+impl Manifest {
+
+    /// Loads the manifest of the resources in the given directory.
+    pub fn load(directory: &Path) -> Result<Manifest, String> {
+        let path = directory.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        let mut assets: Vec<Asset> = Vec::new();
+        for (i, line) in content.lines().map(str::trim).enumerate() {
+            let error = |message: &str| format!("{}:{}: {}", path.display(), i + 1, message);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // each section starts a new resource
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                assets.push(Asset { name: name.trim().to_string(), path: PathBuf::new(), crc32: None, credit: None });
+                continue;
+            }
+            let asset = assets.last_mut().ok_or_else(|| error("Expected a [<resource>] section"))?;
+            let (key, value) = line.split_once('=').ok_or_else(|| error("Invalid line: <key> = \"<value>\""))?;
+            let value = value.trim().trim_matches('"');
+            match key.trim() {
+                "file" => asset.path = directory.join(value),
+                "crc32" => {
+                    let crc = u32::from_str_radix(value, 16).map_err(|_| error("Invalid CRC-32, expected 8 hex digits"))?;
+                    asset.crc32 = Some(crc);
+                },
+                "credit" => asset.credit = Some(value.to_string()),
+                _ => return Err(error("Unknown key, expected file, crc32 or credit"))
+            };
+        }
+        if let Some(asset) = assets.iter().find(|asset| asset.path.as_os_str().is_empty()) {
+            return Err(format!("{}: the file of {} is missing", path.display(), asset.name));
+        }
+        Ok(Manifest { assets })
+    }
+
+    /// Gets the resources required by the game, in the order they are
+    /// loaded, reporting all the ones missing in the manifest or on disk.
+    pub fn required(&self) -> Result<Vec<Asset>, String> {
+        let mut missing = Vec::new();
+        let mut assets = Vec::new();
+        for name in &REQUIRED {
+            match self.assets.iter().find(|asset| asset.name == *name) {
+                Some(asset) if asset.path.is_file() => assets.push(asset.clone()),
+                Some(asset) => missing.push(format!("{} ({}) is missing", asset.path.display(), name)),
+                None => missing.push(format!("{} is not listed in the manifest", name)),
+            };
+        }
+        if missing.is_empty() {
+            Ok(assets)
+        } else {
+            Err(format!("Missing resources: {}", missing.join(", ")))
+        }
+    }
+
+    /// Gets the optional resource with the given name, if listed in the
+    /// manifest (its file must exist in that case).
+    pub fn optional(&self, name: &str) -> Result<Option<Asset>, String> {
+        match self.assets.iter().find(|asset| asset.name == name) {
+            Some(asset) if asset.path.is_file() => Ok(Some(asset.clone())),
+            Some(asset) => Err(format!("Missing resources: {} ({}) is missing", asset.path.display(), name)),
+            None => Ok(None),
+        }
+    }
+
+}
+
+
+/// Computes the CRC-32 (IEEE) checksum of the given data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,72 @@
+//! Geometry of the monitors, queried from `xrandr` where available (SFML
+//! only reports the size of the main one).
+
+use std::process::Command;
+
+
+/// Position and size of a monitor on the desktop (in pixels).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Monitor {
+    pub x: i32,         // left edge
+    pub y: i32,         // top edge
+    pub width: u32,     // number of columns of pixels
+    pub height: u32,    // number of rows of pixels
+}
+
+
+/// Gets the monitor with the given index, the main one (0) first and the
+/// others in the order listed by `xrandr`. Without `xrandr` only the main
+/// monitor is known, with the given geometry.
+pub fn find(index: u32, main: Monitor) -> Result<Monitor, &'static str> {
+    let monitors = Command::new("xrandr").arg("--listmonitors").output().ok()
+        .filter(|output| output.status.success())
+        .map_or_else(Vec::new, |output| parse(&String::from_utf8_lossy(&output.stdout)));
+    match monitors.get(index as usize) {
+        Some(monitor) => Ok(*monitor),
+        None if index == 0 => Ok(main),
+        None => Err("The monitor does not exist (only the main one is known without xrandr)")
+    }
+}
+
+/// Parses the monitors listed by `xrandr --listmonitors`: a line for each
+/// monitor after the header, e.g. `1: +*DP-1 2560/597x1440/336+1920+0 DP-1`
+/// (the main one marked with `*`). The main monitor is put first.
+fn parse(listing: &str) -> Vec<Monitor> {
+    let mut monitors: Vec<(bool, Monitor)> = listing.lines().skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let main = fields.next()?.contains('*');
+            // the size in pixels and in millimeters, then the position
+            let mut geometry = fields.next()?.split('+');
+            let mut size = geometry.next()?.split('x').map(|length| length.split('/').next()?.parse().ok());
+            let (width, height) = (size.next()??, size.next()??);
+            let (x, y) = (geometry.next()?.parse().ok()?, geometry.next()?.parse().ok()?);
+            Some((main, Monitor { x, y, width, height }))
+        })
+        .collect();
+    // stable, keeping the order of the others
+    monitors.sort_by_key(|(main, _)| !main);
+    monitors.into_iter().map(|(_, monitor)| monitor).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitors_are_parsed_main_first() {
+        let listing = "Monitors: 3
+ 0: +HDMI-1 2560/597x1440/336+0+0  HDMI-1
+ 1: +*eDP-1 1920/344x1080/193+2560+360  eDP-1
+ 2: +DP-2 1280/338x1024/270+0+1440  DP-2
+";
+        assert_eq!(parse(listing), [
+            Monitor { x: 2560, y: 360, width: 1920, height: 1080 },
+            Monitor { x: 0, y: 0, width: 2560, height: 1440 },
+            Monitor { x: 0, y: 1440, width: 1280, height: 1024 },
+        ]);
+        assert_eq!(parse("Monitors: 0\n"), []);
+    }
+
+}
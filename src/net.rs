@@ -0,0 +1,697 @@
+//! TCP transport of the networked runs: the server hosting the lobby and
+//! the runs of the matches started in it, and the session of a game
+//! connected to a server.
+//!
+//! The messages of the `protocol` are written as they are, each one ended
+//! by an empty line. The server reads the messages of every client on its
+//! own thread and handles them in order on a single one, which also ticks
+//! the runs at the pace of their rules.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::prelude::*;
+
+use board::{Input, Rules, TickEvent};
+use lobby::{Lobby, Room};
+use protocol::{Client, Host, Message, VERSION};
+
+
+/// Port the server listens on by default.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Maximum length of a message (in bytes): the connections sending longer
+/// ones are closed, as no valid message is that long.
+const MAX_MESSAGE_LENGTH: usize = 1 << 20;
+
+/// Time the server waits for a message while no run is being played.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of milliseconds between two pings of the server during a run.
+const PING_INTERVAL: u64 = 1000;
+
+
+/// Writes the given message to a stream, ended by an empty line.
+fn write_message(mut stream: &TcpStream, message: &Message) -> io::Result<()> {
+    stream.write_all(format!("{}\n", message).as_bytes())
+}
+
+/// Reads the messages of a stream until it is closed, passing each one to
+/// the given function as long as it returns true. The invalid messages are
+/// skipped, while a message too long closes the stream.
+fn read_messages<F: FnMut(Message) -> bool>(stream: TcpStream, mut handle: F) {
+    let mut reader = BufReader::new(stream);
+    let (mut content, mut line) = (String::new(), String::new());
+    loop {
+        line.clear();
+        // a line is read up to the length left, plus one byte to tell
+        let limit = (MAX_MESSAGE_LENGTH - content.len()) as u64 + 1;
+        match (&mut reader).take(limit).read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => ()
+        };
+        if content.len() + line.len() > MAX_MESSAGE_LENGTH {
+            warn!("message too long, connection closed");
+            return;
+        }
+        if !line.trim().is_empty() {
+            content.push_str(&line);
+            continue;
+        }
+        if content.is_empty() {
+            continue;
+        }
+        match content.parse() {
+            Ok(message) => {
+                if !handle(message) {
+                    return;
+                }
+            },
+            Err(err) => warn!("invalid message: {}", err),
+        };
+        content.clear();
+    }
+}
+
+
+/// What happens on the connections of the server, handled in order.
+enum Event {
+    Opened(u32, TcpStream), // a client connected, with the stream to write to
+    Received(u32, Message), // a client sent a message
+    Closed(u32),            // a client disconnected
+}
+
+
+/// Client connected to the server.
+struct Peer {
+    stream: TcpStream,      // stream the messages are written to
+    player: Option<String>, // name the client joined with (none until it joins)
+}
+
+
+/// Run of a match started in the lobby.
+struct Run {
+    host: Host,             // authoritative state of the run
+    players: Vec<String>,   // players of the match, in the order of their seats
+    started: Instant,       // time the run started at
+    next_tick: Instant,     // time the run ticks next
+}
+
+impl Run {
+
+    /// Gets the time between two ticks of the run.
+    fn interval(&self) -> Duration {
+        Duration::from_millis(1000 / u64::from(self.host.board.rules.tick_rate().max(1)))
+    }
+
+}
+
+
+/// Sends a message to the client of the given player, if connected. The
+/// clients that cannot be written to are dropped once their stream closes.
+fn send(peers: &HashMap<u32, Peer>, player: &str, message: &Message) {
+    let peer = peers.values().find(|peer| peer.player.as_ref().is_some_and(|name| name == player));
+    if let Some(peer) = peer {
+        if let Err(err) = write_message(&peer.stream, message) {
+            debug!("unable to send a message: player = {}, error = {}", player, err);
+        }
+    }
+}
+
+
+/// Server hosting the lobby where the players meet, and the runs of the
+/// matches started in it.
+pub struct Server {
+    listener: TcpListener,      // socket accepting the clients
+    rules: Rules,               // rules of the runs (played with a random seed)
+    peers: HashMap<u32, Peer>,  // clients connected, by connection
+    lobby: Lobby,               // rooms waiting for their match to start
+    runs: Vec<Run>,             // runs being played
+}
+
+impl Server {
+
+    /// Creates a server listening on the given port (any free one if 0),
+    /// whose runs are played with the given rules.
+    pub fn bind(port: u16, rules: Rules) -> io::Result<Server> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        Ok(Server { listener, rules, peers: HashMap::new(), lobby: Lobby::default(), runs: Vec::new() })
+    }
+
+    /// Gets the port the server listens on.
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Serves the clients until the listener fails.
+    pub fn run(mut self) -> io::Result<()> {
+        info!("server started: port = {}", self.port()?);
+        let (sender, events) = mpsc::channel();
+        let listener = self.listener.try_clone()?;
+        thread::spawn(move || Server::accept(&listener, &sender));
+        loop {
+            // the events are waited for until the next tick of a run
+            let now = Instant::now();
+            let timeout = self.runs.iter()
+                .map(|run| run.next_tick.saturating_duration_since(now))
+                .min()
+                .unwrap_or(IDLE_TIMEOUT);
+            match events.recv_timeout(timeout) {
+                Ok(event) => self.handle(event),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            };
+            self.tick_runs();
+        }
+    }
+
+    /// Accepts the clients, reading the messages of each one on its own
+    /// thread.
+    fn accept(listener: &TcpListener, events: &Sender<Event>) {
+        for (id, stream) in (0..).zip(listener.incoming()) {
+            let (stream, reader) = match stream.and_then(|stream| stream.try_clone().map(|reader| (stream, reader))) {
+                Ok(streams) => streams,
+                Err(err) => {
+                    warn!("unable to accept a client: {}", err);
+                    continue;
+                }
+            };
+            // the updates are small and must not wait for more
+            let _ = stream.set_nodelay(true);
+            if events.send(Event::Opened(id, stream)).is_err() {
+                return;
+            }
+            let events = events.clone();
+            thread::spawn(move || {
+                read_messages(reader, |message| events.send(Event::Received(id, message)).is_ok());
+                let _ = events.send(Event::Closed(id));
+            });
+        }
+    }
+
+    /// Handles an event of the connections.
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Opened(id, stream) => {
+                info!("client connected: id = {}, address = {:?}", id, stream.peer_addr());
+                self.peers.insert(id, Peer { stream, player: None });
+            },
+            Event::Received(id, message) => {
+                if let Err(reason) = self.receive(id, message) {
+                    debug!("message refused: id = {}, reason = {}", id, reason);
+                    if let Some(peer) = self.peers.get(&id) {
+                        let _ = write_message(&peer.stream, &Message::Refused { reason: reason.to_string() });
+                    }
+                }
+            },
+            Event::Closed(id) => {
+                let player = self.peers.remove(&id).and_then(|peer| peer.player);
+                info!("client disconnected: id = {}, player = {:?}", id, player);
+                if let Some(player) = player {
+                    // the players of a run keep their seat to join it again,
+                    // the runs left by all their players are dropped
+                    let peers = &self.peers;
+                    let online = |player: &String| peers.values().any(|peer| peer.player.as_ref() == Some(player));
+                    self.runs.retain(|run| run.players.iter().any(online));
+                    self.lobby.leave(&player);
+                    self.update_lobby();
+                }
+            },
+        };
+    }
+
+    /// Handles a message received from the given client.
+    fn receive(&mut self, id: u32, message: Message) -> Result<(), &'static str> {
+        let player = match (self.peers.get(&id).and_then(|peer| peer.player.clone()), &message) {
+            (Some(player), _) => player,
+            // the first message names the player
+            (None, Message::Join { version, player, .. }) => {
+                if *version != VERSION {
+                    return Err("The client protocol version is not supported");
+                }
+                if player.trim().is_empty() || player.contains(char::is_whitespace) {
+                    return Err("The player name cannot be empty or have spaces");
+                }
+                if self.peers.values().any(|peer| peer.player.as_ref() == Some(player)) {
+                    return Err("The player name is taken");
+                }
+                info!("player joined: id = {}, player = {}", id, player);
+                if let Some(peer) = self.peers.get_mut(&id) {
+                    peer.player = Some(player.clone());
+                }
+                player.clone()
+            },
+            (None, _) => return Err("The client must join first"),
+        };
+        let run = self.runs.iter().position(|run| run.players.contains(&player));
+        match (message, run) {
+            // the players of a run join it again after dropping
+            (join @ Message::Join { .. }, Some(index)) => {
+                if let Some(snapshot) = self.runs[index].host.receive(&player, join)? {
+                    send(&self.peers, &player, &snapshot);
+                }
+            },
+            (Message::Join { .. }, None) => self.update_lobby(),
+            (input @ Message::Input { .. }, Some(index)) => {
+                self.runs[index].host.receive(&player, input)?;
+            },
+            // the inputs sent while the run ended come too late
+            (Message::Input { .. }, None) => (),
+            (ping @ Message::Ping { .. }, _) => send(&self.peers, &player, &ping),
+            // the chat lines are signed with the name the player joined
+            // with, and relayed by the host of the run (if any)
+            (Message::Chat { text, .. }, run) => {
+                let chat = Message::Chat { player: player.clone(), text };
+                let chat = match run {
+                    Some(index) => self.runs[index].host.receive(&player, chat)?,
+                    None => Some(chat)
+                };
+                if let Some(chat) = chat {
+                    for listener in self.audience(&player) {
+                        send(&self.peers, &listener, &chat);
+                    }
+                }
+            },
+            (_, Some(_)) => return Err("Unexpected message during a run"),
+            (Message::StartMatch, None) => {
+                let room = self.lobby.start(&player)?;
+                self.start(room);
+                self.update_lobby();
+            },
+            (message, None) => {
+                match self.lobby.receive(&player, message)? {
+                    Some(rooms @ Message::Rooms { .. }) => send(&self.peers, &player, &rooms),
+                    _ => self.update_lobby(),
+                };
+            },
+        };
+        Ok(())
+    }
+
+    /// Starts the run of the match of the given room, its members taking
+    /// their seats in order.
+    fn start(&mut self, room: Room) {
+        let mut host = Host::new(self.rules.clone(), thread_rng().gen());
+        let players: Vec<String> = room.members.into_iter().map(|member| member.player).collect();
+        for player in &players {
+            let join = Message::Join { version: VERSION, player: player.clone(), sequence: None };
+            if let Ok(Some(snapshot)) = host.receive(player, join) {
+                send(&self.peers, player, &snapshot);
+            }
+        }
+        info!("run started: room = {}, players = {:?}, seed = {}", room.name, players, host.board.seed);
+        let now = Instant::now();
+        let mut run = Run { host, players, started: now, next_tick: now };
+        run.next_tick += run.interval();
+        self.runs.push(run);
+    }
+
+    /// Ticks the runs whose time has come, sending the updates to their
+    /// players. The players of the runs over are back in the lobby.
+    fn tick_runs(&mut self) {
+        let now = Instant::now();
+        for run in &mut self.runs {
+            while run.next_tick <= now && !run.host.board.over {
+                let time = now.duration_since(run.started).as_millis() as u64;
+                let delta = run.host.tick(time);
+                for player in &run.players {
+                    send(&self.peers, player, &delta);
+                }
+                run.next_tick += run.interval();
+            }
+        }
+        if self.runs.iter().any(|run| run.host.board.over) {
+            self.runs.retain(|run| {
+                if run.host.board.over {
+                    info!("run over: players = {:?}, score = {}", run.players, run.host.board.score);
+                }
+                !run.host.board.over
+            });
+            self.update_lobby();
+        }
+    }
+
+    /// Gets the players the chat lines of the given one are sent to: the
+    /// players of its run or of its room, or else the players in the lobby
+    /// not in a room.
+    fn audience(&self, player: &str) -> Vec<String> {
+        if let Some(run) = self.runs.iter().find(|run| run.players.iter().any(|other| other == player)) {
+            return run.players.clone();
+        }
+        if let Some(room) = self.lobby.room(player) {
+            return room.members.iter().map(|member| member.player.clone()).collect();
+        }
+        self.peers.values()
+            .filter_map(|peer| peer.player.clone())
+            .filter(|other| self.lobby.room(other).is_none() && self.runs.iter().all(|run| !run.players.contains(other)))
+            .collect()
+    }
+
+    /// Sends the players in the lobby the state of their room, or the list
+    /// of the rooms to the ones not in a room.
+    fn update_lobby(&self) {
+        for player in self.peers.values().filter_map(|peer| peer.player.as_ref()) {
+            if self.runs.iter().any(|run| run.players.contains(player)) {
+                continue;
+            }
+            let message = self.lobby.room(player).map_or_else(|| self.lobby.list(), Room::state);
+            send(&self.peers, player, &message);
+        }
+    }
+
+}
+
+
+/// What a session received from the server, for the game to show.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Update {
+    Lobby,                  // the rooms, the room of the player or the last refusal changed
+    Chat(String, String),   // line of the chat: the player who sent it and its text
+    Started,                // a run started, the client having its board
+    Tick(Vec<TickEvent>),   // the run ticked, with the events of the tick
+    RoundTrip(u64),         // the server echoed a ping after the given number of milliseconds
+}
+
+
+/// Connection of a game to the server, with what the player knows of the
+/// lobby and of the run being played.
+pub struct Session {
+    stream: TcpStream,                  // stream the messages are written to
+    messages: Receiver<Message>,        // messages read from the server
+    pub player: String,                 // name the player joined with
+    pub rooms: Vec<(String, u32)>,      // rooms listed by the server, and their number of players
+    pub room: Option<Room>,             // room of the player (kept during its run)
+    pub refusal: Option<String>,        // reason of the last message refused (if any)
+    pub client: Option<Client>,         // copy of the run being played (if any)
+    pub seat: Option<usize>,            // index of the snake steered in the run (none to watch it)
+    started: Instant,                   // time the session started at
+    inputs: u32,                        // number of inputs sent
+    pinged_at: Option<u64>,             // session time of the last ping sent (in milliseconds)
+}
+
+impl Session {
+
+    /// Connects to the server at the given address (`host:port`), joining
+    /// the lobby with the given player name.
+    pub fn open(address: &str, player: &str) -> io::Result<Session> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || read_messages(reader, |message| sender.send(message).is_ok()));
+        info!("connected to the server: address = {}, player = {}", address, player);
+        let session = Session {
+            stream,
+            messages,
+            player: player.to_string(),
+            rooms: Vec::new(),
+            room: None,
+            refusal: None,
+            client: None,
+            seat: None,
+            started: Instant::now(),
+            inputs: 0,
+            pinged_at: None,
+        };
+        session.send(&Message::Join { version: VERSION, player: player.to_string(), sequence: None });
+        Ok(session)
+    }
+
+    /// Sends a message to the server. The messages that cannot be sent are
+    /// lost, the connection being closed.
+    pub fn send(&self, message: &Message) {
+        if let Err(err) = write_message(&self.stream, message) {
+            warn!("unable to send a message to the server: {}", err);
+        }
+    }
+
+    /// Sends a message to the lobby, forgetting the last refusal. The room
+    /// left is forgotten right away.
+    pub fn request(&mut self, message: Message) {
+        self.refusal = None;
+        if message == Message::LeaveRoom {
+            self.room = None;
+        }
+        self.send(&message);
+    }
+
+    /// Sends a line of the chat, relayed by the server to the players of the
+    /// run or of the room (or to the players in the lobby not in a room).
+    pub fn chat(&self, text: &str) {
+        self.send(&Message::Chat { player: self.player.clone(), text: text.to_string() });
+    }
+
+    /// Sends an input of the player to the run.
+    pub fn input(&mut self, input: Input) {
+        self.inputs += 1;
+        self.send(&Message::Input { sequence: self.inputs, input });
+    }
+
+    /// Gets the round-trip time of the last ping of the run echoed by the
+    /// server (in milliseconds), if any.
+    pub fn round_trip(&self) -> Option<u64> {
+        self.client.as_ref().and_then(Client::round_trip)
+    }
+
+    /// Gets how far the snakes are drawn from the board before the last
+    /// update of the run to the current one (from 0 to 1).
+    pub fn interpolation(&self) -> f32 {
+        self.client.as_ref().map_or(1.0, |client| client.interpolation(self.now()))
+    }
+
+    /// Gets the time since the session started (in milliseconds).
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Goes back to the lobby once the run is over, listing the rooms.
+    pub fn leave_run(&mut self) {
+        self.client = None;
+        self.pinged_at = None;
+        self.seat = None;
+        self.room = None;
+        self.request(Message::ListRooms);
+    }
+
+    /// Applies the messages received from the server since the last poll,
+    /// returning what changed. Returns an error once the server closed the
+    /// connection.
+    pub fn poll(&mut self) -> Result<Vec<Update>, &'static str> {
+        // the server is pinged during the runs to measure the round trip
+        let now = self.now();
+        let ping = match self.client.as_mut() {
+            Some(client) if self.pinged_at.is_none_or(|time| now >= time + PING_INTERVAL) => Some(client.ping(now)),
+            _ => None
+        };
+        if let Some(ping) = ping {
+            self.pinged_at = Some(now);
+            self.send(&ping);
+        }
+        let mut updates = Vec::new();
+        loop {
+            let message = match self.messages.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => return Ok(updates),
+                Err(TryRecvError::Disconnected) => return Err("The connection to the server was closed"),
+            };
+            let now = self.now();
+            match message {
+                Message::Rooms { rooms } => {
+                    self.rooms = rooms;
+                    updates.push(Update::Lobby);
+                },
+                Message::RoomState { room, members } => {
+                    self.room = Some(Room { name: room, members });
+                    updates.push(Update::Lobby);
+                },
+                Message::Chat { player, text } => updates.push(Update::Chat(player, text)),
+                ping @ Message::Ping { .. } => {
+                    if let Some(client) = self.client.as_mut() {
+                        if let (Ok(_), Some(round_trip)) = (client.receive(ping, now), client.round_trip()) {
+                            updates.push(Update::RoundTrip(round_trip));
+                        }
+                    }
+                },
+                Message::Refused { reason } => {
+                    warn!("message refused by the server: {}", reason);
+                    self.refusal = Some(reason);
+                    updates.push(Update::Lobby);
+                },
+                snapshot @ Message::Snapshot { .. } => match self.client.as_mut() {
+                    // the client out of sync starts over from the snapshot
+                    Some(client) => {
+                        if let Err(err) = client.receive(snapshot, now) {
+                            warn!("unable to resync: {}", err);
+                        }
+                    },
+                    None => match Client::new(snapshot, now) {
+                        Ok(client) => {
+                            let player = &self.player;
+                            self.seat = self.room.as_ref().and_then(|room| room.members.iter().position(|member| member.player == *player));
+                            self.client = Some(client);
+                            updates.push(Update::Started);
+                        },
+                        Err(err) => warn!("unable to start the run: {}", err),
+                    },
+                },
+                delta @ Message::Delta { .. } => {
+                    // the updates of a run left are ignored
+                    let client = match self.client.as_mut() {
+                        Some(client) => client,
+                        None => continue
+                    };
+                    match client.receive(delta, now) {
+                        Ok(events) => updates.push(Update::Tick(events)),
+                        // an update was missed, the client joins again to
+                        // be sent a snapshot
+                        Err(_) => {
+                            let rejoin = client.rejoin(&self.player);
+                            self.send(&rejoin);
+                        },
+                    };
+                },
+                message => debug!("unexpected message from the server: {:?}", message),
+            };
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::Mode;
+    use grid::{Direction, Grid};
+
+    /// Starts a server on a free port, returning its address.
+    fn serve() -> String {
+        let mut rules = Rules::new(Mode::Coop, Grid::new(20, 15));
+        rules.ticks_per_second = 50;
+        let server = Server::bind(0, rules).unwrap();
+        let address = format!("127.0.0.1:{}", server.port().unwrap());
+        thread::spawn(move || server.run());
+        address
+    }
+
+    /// Polls the session until the given condition holds, returning the
+    /// updates received meanwhile.
+    fn wait<F: Fn(&Session) -> bool>(session: &mut Session, condition: F) -> Vec<Update> {
+        let mut updates = Vec::new();
+        for _ in 0..500 {
+            updates.extend(session.poll().unwrap());
+            if condition(session) {
+                return updates;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("condition not met in time: player = {}", session.player);
+    }
+
+    /// Polls the session until the given update is received, returning
+    /// false if it is not in time.
+    fn wait_for(session: &mut Session, update: &Update) -> bool {
+        for _ in 0..500 {
+            if session.poll().unwrap().contains(update) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn members(session: &Session) -> usize {
+        session.room.as_ref().map_or(0, |room| room.members.len())
+    }
+
+    #[test]
+    fn players_meet_in_a_room_and_play_its_run() {
+        let address = serve();
+        let mut alice = Session::open(&address, "alice").unwrap();
+        let mut bob = Session::open(&address, "bob").unwrap();
+        alice.request(Message::CreateRoom { room: "den".to_string() });
+        wait(&mut bob, |bob| bob.rooms == vec![("den".to_string(), 1)]);
+        bob.request(Message::JoinRoom { room: "den".to_string() });
+        wait(&mut alice, |alice| members(alice) == 2);
+        // the match starts once everyone is ready
+        alice.request(Message::StartMatch);
+        wait(&mut alice, |alice| alice.refusal.is_some());
+        for session in &mut [&mut alice, &mut bob] {
+            session.request(Message::Ready { ready: true });
+        }
+        wait(&mut alice, |alice| alice.room.as_ref().is_some_and(|room| room.members.iter().all(|member| member.ready)));
+        alice.request(Message::StartMatch);
+        assert!(wait(&mut alice, |alice| alice.client.is_some()).contains(&Update::Started));
+        wait(&mut bob, |bob| bob.client.is_some());
+        assert_eq!((alice.seat, bob.seat), (Some(0), Some(1)));
+        // the inputs of each player steer its snake on both copies
+        alice.input(Input::Turn(Direction::Up));
+        bob.input(Input::Turn(Direction::Down));
+        let moved = |session: &Session| session.client.as_ref().is_some_and(|client| {
+            let board = &client.board;
+            board.snake.direction().is_some() && board.partner.as_ref().is_some_and(|partner| partner.direction().is_some())
+        });
+        wait(&mut alice, moved);
+        wait(&mut bob, moved);
+        let ticks = |session: &Session| session.client.as_ref().map_or(0, |client| client.board.ticks);
+        let target = ticks(&alice).max(ticks(&bob)) + 2;
+        let updates = wait(&mut alice, |alice| ticks(alice) >= target);
+        assert!(updates.iter().all(|update| matches!(update, Update::Tick(_) | Update::RoundTrip(_))));
+        wait(&mut bob, |bob| ticks(bob) >= target);
+        let board = |session: &Session, tick: u32| {
+            let client = session.client.as_ref().unwrap();
+            assert!(client.board.ticks >= tick);
+            client.board.snake.direction()
+        };
+        assert_eq!(board(&alice, target), board(&bob, target));
+        // the server echoes the pings to measure the round trip
+        wait(&mut bob, |bob| bob.round_trip().is_some());
+        assert!(bob.round_trip() < Some(PING_INTERVAL));
+        assert!((0.0..=1.0).contains(&bob.interpolation()));
+    }
+
+    #[test]
+    fn chat_lines_are_relayed_to_the_players_around() {
+        let address = serve();
+        let mut sessions: Vec<Session> = ["alice", "bob", "carol"].iter().map(|player| Session::open(&address, player).unwrap()).collect();
+        let chat = |player: &str, text: &str| Update::Chat(player.to_string(), text.to_string());
+        // the players who joined are all in the lobby
+        for session in &mut sessions {
+            assert!(wait_for(session, &Update::Lobby));
+        }
+        sessions[0].chat("hello");
+        for session in &mut sessions {
+            assert!(wait_for(session, &chat("alice", "hello")));
+        }
+        // the lines of a room stay in the room, signed by the server
+        sessions[0].request(Message::CreateRoom { room: "den".to_string() });
+        wait(&mut sessions[0], |alice| members(alice) == 1);
+        sessions[1].request(Message::JoinRoom { room: "den".to_string() });
+        wait(&mut sessions[1], |bob| members(bob) == 2);
+        sessions[1].send(&Message::Chat { player: "mallory".to_string(), text: "hi".to_string() });
+        assert!(wait_for(&mut sessions[0], &chat("bob", "hi")));
+        assert!(wait_for(&mut sessions[1], &chat("bob", "hi")));
+        sessions[2].chat("anyone?");
+        assert!(wait_for(&mut sessions[2], &chat("carol", "anyone?")));
+        thread::sleep(Duration::from_millis(50));
+        assert!(sessions[0].poll().unwrap().iter().all(|update| !matches!(update, Update::Chat(..))));
+    }
+
+    #[test]
+    fn requests_are_refused_with_a_reason() {
+        let address = serve();
+        let mut alice = Session::open(&address, "alice").unwrap();
+        alice.request(Message::JoinRoom { room: "nowhere".to_string() });
+        wait(&mut alice, |alice| alice.refusal.is_some());
+        assert_eq!(alice.refusal.as_deref(), Some("The room does not exist"));
+        // the names are unique
+        let mut other = Session::open(&address, "alice").unwrap();
+        wait(&mut other, |other| other.refusal.is_some());
+        assert_eq!(other.refusal.as_deref(), Some("The player name is taken"));
+    }
+
+}
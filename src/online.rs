@@ -0,0 +1,217 @@
+//! Client of the online leaderboard, queuing the scores while offline.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use ureq::{Agent, AgentBuilder};
+
+use board::Mode;
+use replay::Replay;
+
+
+/// Directory where the scores not submitted yet are queued.
+const PENDING_DIRECTORY: &str = "replays/pending";
+
+/// Maximum number of entries fetched from the leaderboard.
+const TOP_COUNT: usize = 100;
+
+/// Delay before submitting the queued scores again, doubled after every
+/// failure up to the maximum delay.
+const FIRST_RETRY: Duration = Duration::from_secs(15);
+const MAX_RETRY: Duration = Duration::from_secs(15 * 60);
+
+
+/// An entry of the leaderboard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub player: String, // player name
+    pub score: u32,     // verified score
+}
+
+
+/// Requests handled by the leaderboard background thread.
+enum Request {
+    Submit(Replay), // submit the score of a finished run
+    Fetch(Mode),    // fetch the best scores of a game mode
+}
+
+
+/// Client of the online leaderboard. The requests are sent from a background
+/// thread so that the game never waits for the network, and the scores that
+/// cannot be submitted are queued on disk and submitted again later, backing
+/// off exponentially while the leaderboard is unreachable.
+pub struct Leaderboard {
+    requests: Sender<Request>,                              // requests to the background thread
+    responses: Receiver<Result<Vec<Entry>, &'static str>>,  // fetched leaderboards
+}
+
+impl Leaderboard {
+
+    /// Creates a new client of the leaderboard at the given URL, submitting
+    /// the scores with the given player name.
+    pub fn new(url: &str, player: &str) -> Leaderboard {
+        let (requests, receiver) = mpsc::channel();
+        let (sender, responses) = mpsc::channel();
+        let client = Client {
+            agent: AgentBuilder::new().timeout(Duration::from_secs(10)).build(),
+            url: url.trim_end_matches('/').to_string(),
+            player: player.to_string(),
+        };
+        thread::spawn(move || {
+            // submit the scores queued while offline, retrying after a delay
+            // (if any) while some are left in the queue
+            let mut retry = if client.flush() { None } else { Some(FIRST_RETRY) };
+            loop {
+                let request = match retry {
+                    Some(delay) => match receiver.recv_timeout(delay) {
+                        Ok(request) => Some(request),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    },
+                    None => match receiver.recv() {
+                        Ok(request) => Some(request),
+                        Err(_) => return
+                    },
+                };
+                match request {
+                    Some(Request::Submit(replay)) => {
+                        retry = if client.submit(&replay) { None } else { retry.or(Some(FIRST_RETRY)) };
+                    },
+                    Some(Request::Fetch(mode)) => {
+                        if sender.send(client.fetch(mode)).is_err() {
+                            return;
+                        }
+                    },
+                    None => {
+                        retry = if client.flush() { None } else { retry.map(|delay| (delay * 2).min(MAX_RETRY)) };
+                        if let Some(delay) = retry {
+                            debug!("queued scores not submitted, next retry in {} s", delay.as_secs());
+                        }
+                    },
+                };
+            }
+        });
+        Leaderboard { requests, responses }
+    }
+
+    /// Submits the score of a finished run, given its replay.
+    pub fn submit(&self, replay: Replay) {
+        // the background thread never stops before the client
+        let _ = self.requests.send(Request::Submit(replay));
+    }
+
+    /// Fetches the best scores of the given game mode, they can be read
+    /// with `poll` once received.
+    pub fn fetch(&self, mode: Mode) {
+        let _ = self.requests.send(Request::Fetch(mode));
+    }
+
+    /// Gets the last leaderboard fetched, if received since the last call.
+    pub fn poll(&self) -> Option<Result<Vec<Entry>, &'static str>> {
+        self.responses.try_recv().ok()
+    }
+
+}
+
+
+/// HTTP client used by the leaderboard background thread.
+struct Client {
+    agent: Agent,   // HTTP agent (shared connections)
+    url: String,    // leaderboard URL, without trailing slash
+    player: String, // player name
+}
+
+impl Client {
+
+    /// Submits the score of a finished run once verified, queuing it if
+    /// the leaderboard cannot be reached. Returns false if some scores are
+    /// left in the queue.
+    fn submit(&self, replay: &Replay) -> bool {
+        // runs that cannot be simulated again are never submitted
+        if replay.verify().is_err() {
+            warn!("the run could not be verified and was not submitted: seed = {}", replay.seed);
+            return true;
+        }
+        if self.post(replay) {
+            self.flush()
+        } else {
+            self.queue(replay);
+            false
+        }
+    }
+
+    /// Posts the replay of a run to the leaderboard, which simulates it
+    /// again to verify the score, along with the score, the seed and the
+    /// hash of the replay. Returns false if the request failed.
+    fn post(&self, replay: &Replay) -> bool {
+        let content = replay.to_string();
+        self.agent.post(&format!("{}/scores", self.url))
+            .query("player", &self.player)
+            .query("score", &replay.score.to_string())
+            .query("seed", &replay.seed.to_string())
+            .query("hash", &format!("{:016x}", hash(&content)))
+            .send_string(&content)
+            .is_ok()
+    }
+
+    /// Queues on disk a score that cannot be submitted now.
+    fn queue(&self, replay: &Replay) {
+        let path = PathBuf::from(PENDING_DIRECTORY)
+            .join(format!("{}-{}.replay", replay.seed, replay.ticks));
+        match replay.save(&path) {
+            Ok(()) => warn!("the leaderboard is unreachable, score queued: path = {}", path.display()),
+            Err(err) => error!("unable to queue the score: error = {}", err),
+        };
+    }
+
+    /// Submits all the queued scores, stopping at the first failure.
+    /// Returns false if some scores are left in the queue.
+    fn flush(&self) -> bool {
+        let entries = match fs::read_dir(PENDING_DIRECTORY) {
+            Ok(entries) => entries,
+            Err(_) => return true
+        };
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            // unreadable files are discarded
+            if let Ok(replay) = Replay::load(&path) {
+                if !self.post(&replay) {
+                    return false;
+                }
+            }
+            let _ = fs::remove_file(&path);
+        }
+        true
+    }
+
+    /// Fetches the best scores of the given game mode, sorted from the best.
+    fn fetch(&self, mode: Mode) -> Result<Vec<Entry>, &'static str> {
+        let body = self.agent.get(&format!("{}/scores", self.url))
+            .query("mode", mode.name())
+            .query("limit", &TOP_COUNT.to_string())
+            .call()
+            .map_err(|_| "Unable to reach the leaderboard")?
+            .into_string()
+            .map_err(|_| "Invalid leaderboard response")?;
+        // each line is made of the player name and the score, tab separated
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(TOP_COUNT)
+            .map(|line| {
+                let (player, score) = line.rsplit_once('\t').ok_or("Invalid leaderboard entry")?;
+                let score = score.trim().parse().map_err(|_| "Invalid leaderboard score")?;
+                Ok(Entry { player: player.to_string(), score })
+            })
+            .collect()
+    }
+
+}
+
+
+/// Hashes the given content (64-bit FNV-1a), so that the leaderboard can
+/// tell the replays already submitted apart.
+fn hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
@@ -0,0 +1,184 @@
+//! Discord Rich Presence: the current activity is published to the local
+//! Discord client through its IPC socket, so that friends can see what is
+//! being played.
+
+use std::env;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+/// Minimum time between two activity updates (Discord allows 5 updates
+/// every 20 seconds).
+const UPDATE_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Opcodes of the IPC frames.
+const HANDSHAKE: u32 = 0;
+const FRAME: u32 = 1;
+
+
+/// Activity shown on the Discord profile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Activity {
+    pub details: String,        // first line (e.g. the game mode)
+    pub state: String,          // second line (e.g. the score)
+    pub start: Option<u64>,     // UNIX time the run started at, to show the elapsed time
+}
+
+impl Activity {
+
+    /// Gets the UNIX time of the given number of seconds ago.
+    pub fn seconds_ago(seconds: u32) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+        now.saturating_sub(u64::from(seconds))
+    }
+
+}
+
+
+/// Publisher of the activity. The updates are sent from a background thread
+/// so that the game never waits for Discord, and only the last one is sent
+/// if they come faster than Discord accepts them.
+pub struct Presence {
+    updates: Sender<Activity>,  // activities to the background thread
+    last: Option<Activity>,     // last activity published
+}
+
+impl Presence {
+
+    /// Creates the publisher of the activity of the Discord application
+    /// with the given ID.
+    pub fn new(application: &str) -> Presence {
+        let (updates, receiver) = mpsc::channel::<Activity>();
+        let application = application.to_string();
+        thread::spawn(move || {
+            let mut connection: Option<Box<dyn Stream>> = None;
+            let mut nonce = 0u32;
+            while let Ok(mut activity) = receiver.recv() {
+                // skip the activities already outdated
+                while let Ok(next) = receiver.try_recv() {
+                    activity = next;
+                }
+                if connection.is_none() {
+                    connection = connect(&application)
+                        .map_err(|err| debug!("unable to connect to Discord: {}", err))
+                        .ok();
+                }
+                if let Some(stream) = connection.as_mut() {
+                    nonce += 1;
+                    if let Err(err) = set_activity(stream, &activity, nonce) {
+                        warn!("unable to publish the Discord activity: {}", err);
+                        connection = None;
+                    }
+                }
+                thread::sleep(UPDATE_INTERVAL);
+            }
+        });
+        Presence { updates, last: None }
+    }
+
+    /// Publishes the given activity, unless unchanged.
+    pub fn publish(&mut self, activity: Activity) {
+        if self.last.as_ref() != Some(&activity) {
+            // the background thread never stops before the publisher
+            let _ = self.updates.send(activity.clone());
+            self.last = Some(activity);
+        }
+    }
+
+}
+
+
+/// Connection to the Discord IPC socket.
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+
+/// Connects to the first Discord IPC socket available, completing the
+/// handshake.
+fn connect(application: &str) -> io::Result<Box<dyn Stream>> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "Discord is not running");
+    for i in 0..10 {
+        match open_socket(i) {
+            Ok(mut stream) => {
+                let handshake = format!(r#"{{"v":1,"client_id":"{}"}}"#, escape(application));
+                write_frame(&mut stream, HANDSHAKE, &handshake)?;
+                read_frame(&mut stream)?;
+                info!("connected to Discord");
+                return Ok(stream);
+            },
+            Err(err) => last_error = err,
+        };
+    }
+    Err(last_error)
+}
+
+/// Opens the Discord IPC socket with the given index.
+#[cfg(unix)]
+fn open_socket(index: u32) -> io::Result<Box<dyn Stream>> {
+    use std::os::unix::net::UnixStream;
+    let directory = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].iter()
+        .find_map(|name| env::var(name).ok())
+        .unwrap_or_else(|| String::from("/tmp"));
+    let path = format!("{}/discord-ipc-{}", directory.trim_end_matches('/'), index);
+    Ok(Box::new(UnixStream::connect(path)?))
+}
+
+/// Opens the Discord IPC pipe with the given index.
+#[cfg(windows)]
+fn open_socket(index: u32) -> io::Result<Box<dyn Stream>> {
+    use std::fs::OpenOptions;
+    let path = format!(r"\\?\pipe\discord-ipc-{}", index);
+    Ok(Box::new(OpenOptions::new().read(true).write(true).open(path)?))
+}
+
+/// Sets the activity shown on the Discord profile.
+fn set_activity(stream: &mut Box<dyn Stream>, activity: &Activity, nonce: u32) -> io::Result<()> {
+    let timestamps = match activity.start {
+        Some(start) => format!(r#","timestamps":{{"start":{}}}"#, start),
+        None => String::new(),
+    };
+    let payload = format!(
+        r#"{{"cmd":"SET_ACTIVITY","args":{{"pid":{},"activity":{{"details":"{}","state":"{}"{}}}}},"nonce":"{}"}}"#,
+        std::process::id(), escape(&activity.details), escape(&activity.state), timestamps, nonce);
+    write_frame(stream, FRAME, &payload)?;
+    read_frame(stream)?;
+    Ok(())
+}
+
+/// Writes a frame made of its opcode, the length of its JSON payload and
+/// the payload itself.
+fn write_frame<W: Write + ?Sized>(stream: &mut W, opcode: u32, payload: &str) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&opcode.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+/// Reads a frame, returning its JSON payload.
+fn read_frame<R: Read + ?Sized>(stream: &mut R) -> io::Result<String> {
+    let mut header = [0; 8];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut payload = vec![0; length as usize];
+    stream.read_exact(&mut payload)?;
+    Ok(String::from_utf8_lossy(&payload).into_owned())
+}
+
+/// Escapes the given string to be written in a JSON string.
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        };
+    }
+    escaped
+}
@@ -0,0 +1,620 @@
+//! Messages exchanged by the host and the clients of a networked run, and
+//! the state each side keeps to stay in sync.
+//!
+//! The host owns the authoritative board and numbers every update it sends
+//! (a full `Snapshot` or the `Delta` of a single tick). A client applies the
+//! updates in order on its own copy of the board: as the board is
+//! deterministic, the inputs of each tick are enough to keep the copies
+//! identical. A client missing an update, or reconnecting after dropping,
+//! joins again with the last sequence number it received and is sent a full
+//! snapshot to resync from.
+//!
+//! The updates carry the time of the host (in milliseconds) they were made
+//! at, so that a client can draw the snakes moving smoothly between two of
+//! them, and the clients ping the host to measure the round-trip time.
+//!
+//! Before the run starts, the clients meet in the rooms of the `Lobby`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use board::{Board, Input, Rules, TickEvent};
+use lobby::Member;
+use replay::Replay;
+
+
+/// Version of the protocol, the host only accepts clients with the same one.
+pub const VERSION: u32 = 1;
+
+
+/// Message sent by either side, written as `key = value` lines (like the
+/// replays) starting with the `message` kind. Messages are separated by an
+/// empty line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    // client to host: join (or rejoin after dropping) the run, giving the
+    // sequence number of the last update received (if any); it is also the
+    // first message of a client connecting to the server of the lobby
+    Join { version: u32, player: String, sequence: Option<u32> },
+    // client to host: player input, numbered by the client so that the
+    // inputs resent after reconnecting are applied only once
+    Input { sequence: u32, input: Input },
+    // host to client: whole state of the run, as the replay of its ticks
+    Snapshot { sequence: u32, time: u64, replay: Replay },
+    // host to client: inputs applied right before the next tick
+    Delta { sequence: u32, time: u64, inputs: Vec<Input> },
+    // client to host: keeps the connection alive, echoed by the host to
+    // measure the round-trip time from the client time it was sent at
+    Ping { sequence: u32, time: u64 },
+    // either side: line of the chat, relayed by the host to all the clients
+    Chat { player: String, text: String },
+    // client to lobby: list the rooms
+    ListRooms,
+    // lobby to client: name and number of players of each room
+    Rooms { rooms: Vec<(String, u32)> },
+    // client to lobby: create a room, hosted by the client
+    CreateRoom { room: String },
+    // client to lobby: join a room
+    JoinRoom { room: String },
+    // client to lobby: leave the room
+    LeaveRoom,
+    // client to lobby: pick the color of the snake
+    PickColor { color: String },
+    // client to lobby: set whether the player is ready to start
+    Ready { ready: bool },
+    // host client to lobby: start the match once everyone is ready
+    StartMatch,
+    // lobby to client: the players in the room, the first one hosting it
+    RoomState { room: String, members: Vec<Member> },
+    // host or lobby to client: the last message was refused, and why
+    Refused { reason: String },
+}
+
+impl FromStr for Message {
+    type Err = &'static str;
+
+    /// Parses a message made of `key = value` lines. The lines that are not
+    /// part of the message header are the replay of a snapshot.
+    fn from_str(content: &str) -> Result<Message, &'static str> {
+        // parse a number, whatever its type
+        fn number<T: FromStr>(value: &str) -> Result<T, &'static str> {
+            value.parse().map_err(|_| "Invalid number in the message")
+        }
+        let (mut kind, mut version, mut player, mut sequence, mut time) = (None, None, None, None, None);
+        let (mut input, mut inputs) = (None, Vec::new());
+        let (mut room, mut color, mut ready, mut text, mut reason) = (None, None, None, None, None);
+        let (mut rooms, mut members) = (Vec::new(), Vec::new());
+        let mut replay = String::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or("Invalid message line: <key> = <value>")?;
+            let value = value.trim();
+            match key.trim() {
+                "message" => kind = Some(value),
+                "version" => version = Some(number(value)?),
+                "player" => player = Some(value.to_string()),
+                "sequence" => sequence = Some(number(value)?),
+                "time" => time = Some(number(value)?),
+                "input" => input = Some(Input::from_name(value)?),
+                "tick-inputs" => {
+                    for name in value.split_whitespace() {
+                        inputs.push(Input::from_name(name)?);
+                    }
+                },
+                "text" => text = Some(value.to_string()),
+                "reason" => reason = Some(value.to_string()),
+                "room" => room = Some(value.to_string()),
+                "color" => color = Some(value.to_string()),
+                "ready" => ready = Some(value.parse().map_err(|_| "The ready flag must be true or false")?),
+                // the name is last, as it is the only value with spaces
+                "listed-room" => {
+                    let (players, name) = value.split_once(' ').ok_or("Invalid room: <players> <name>")?;
+                    rooms.push((name.to_string(), number(players)?));
+                },
+                "member" => {
+                    let mut words = value.splitn(3, ' ');
+                    let (color, ready, player) = match (words.next(), words.next(), words.next()) {
+                        (Some(color), Some(ready), Some(player)) => (color, ready, player),
+                        _ => return Err("Invalid member: <color> <ready> <player>")
+                    };
+                    let ready = ready.parse().map_err(|_| "The ready flag must be true or false")?;
+                    members.push(Member { player: player.to_string(), color: color.to_string(), ready });
+                },
+                _ => {
+                    replay.push_str(line);
+                    replay.push('\n');
+                }
+            };
+        }
+        let kind = kind.ok_or("The message kind is missing")?;
+        if kind != "snapshot" && !replay.is_empty() {
+            return Err("Unknown message key");
+        }
+        let sequence = || sequence.ok_or("The message sequence number is missing");
+        let room = || room.clone().ok_or("The room name is missing");
+        let time = || time.ok_or("The message time is missing");
+        let message = match kind {
+            "join" => Message::Join {
+                version: version.ok_or("The protocol version is missing")?,
+                player: player.ok_or("The player name is missing")?,
+                sequence: sequence().ok(),
+            },
+            "input" => Message::Input { sequence: sequence()?, input: input.ok_or("The input is missing")? },
+            "snapshot" => Message::Snapshot { sequence: sequence()?, time: time()?, replay: replay.parse()? },
+            "delta" => Message::Delta { sequence: sequence()?, time: time()?, inputs },
+            "ping" => Message::Ping { sequence: sequence()?, time: time()? },
+            "chat" => Message::Chat {
+                player: player.ok_or("The player name is missing")?,
+                text: text.ok_or("The chat text is missing")?,
+            },
+            "list-rooms" => Message::ListRooms,
+            "rooms" => Message::Rooms { rooms },
+            "create-room" => Message::CreateRoom { room: room()? },
+            "join-room" => Message::JoinRoom { room: room()? },
+            "leave-room" => Message::LeaveRoom,
+            "pick-color" => Message::PickColor { color: color.ok_or("The color is missing")? },
+            "ready" => Message::Ready { ready: ready.ok_or("The ready flag is missing")? },
+            "start-match" => Message::StartMatch,
+            "room-state" => Message::RoomState { room: room()?, members },
+            "refused" => Message::Refused { reason: reason.ok_or("The reason is missing")? },
+            _ => return Err("Invalid message kind, see the protocol module for the list of messages")
+        };
+        Ok(message)
+    }
+
+}
+
+impl fmt::Display for Message {
+
+    /// Writes the message as `key = value` lines.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Join { version, player, sequence } => {
+                writeln!(f, "message = join")?;
+                writeln!(f, "version = {}", version)?;
+                writeln!(f, "player = {}", player)?;
+                if let Some(sequence) = sequence {
+                    writeln!(f, "sequence = {}", sequence)?;
+                }
+                Ok(())
+            },
+            Message::Input { sequence, input } => {
+                writeln!(f, "message = input")?;
+                writeln!(f, "sequence = {}", sequence)?;
+                writeln!(f, "input = {}", input.name())
+            },
+            Message::Snapshot { sequence, time, replay } => {
+                writeln!(f, "message = snapshot")?;
+                writeln!(f, "sequence = {}", sequence)?;
+                writeln!(f, "time = {}", time)?;
+                write!(f, "{}", replay)
+            },
+            Message::Delta { sequence, time, inputs } => {
+                let names: Vec<&str> = inputs.iter().map(|input| input.name()).collect();
+                writeln!(f, "message = delta")?;
+                writeln!(f, "sequence = {}", sequence)?;
+                writeln!(f, "time = {}", time)?;
+                writeln!(f, "tick-inputs = {}", names.join(" "))
+            },
+            Message::Ping { sequence, time } => {
+                writeln!(f, "message = ping")?;
+                writeln!(f, "sequence = {}", sequence)?;
+                writeln!(f, "time = {}", time)
+            },
+            Message::Chat { player, text } => {
+                writeln!(f, "message = chat")?;
+                writeln!(f, "player = {}", player)?;
+                // the text is a single line
+                writeln!(f, "text = {}", text.lines().next().unwrap_or_default())
+            },
+            Message::ListRooms => writeln!(f, "message = list-rooms"),
+            Message::Rooms { rooms } => {
+                writeln!(f, "message = rooms")?;
+                for (name, players) in rooms {
+                    writeln!(f, "listed-room = {} {}", players, name)?;
+                }
+                Ok(())
+            },
+            Message::CreateRoom { room } => {
+                writeln!(f, "message = create-room")?;
+                writeln!(f, "room = {}", room)
+            },
+            Message::JoinRoom { room } => {
+                writeln!(f, "message = join-room")?;
+                writeln!(f, "room = {}", room)
+            },
+            Message::LeaveRoom => writeln!(f, "message = leave-room"),
+            Message::PickColor { color } => {
+                writeln!(f, "message = pick-color")?;
+                writeln!(f, "color = {}", color)
+            },
+            Message::Ready { ready } => {
+                writeln!(f, "message = ready")?;
+                writeln!(f, "ready = {}", ready)
+            },
+            Message::StartMatch => writeln!(f, "message = start-match"),
+            Message::RoomState { room, members } => {
+                writeln!(f, "message = room-state")?;
+                writeln!(f, "room = {}", room)?;
+                for member in members {
+                    writeln!(f, "member = {} {} {}", member.color, member.ready, member.player)?;
+                }
+                Ok(())
+            },
+            Message::Refused { reason } => {
+                writeln!(f, "message = refused")?;
+                writeln!(f, "reason = {}", reason)
+            },
+        }
+    }
+
+}
+
+
+/// Player of a networked run, as seen by the host.
+#[derive(Clone, Debug)]
+struct Seat {
+    player: String,             // name the client joined with
+    last_input: Option<u32>,    // sequence number of the last input received from the client
+}
+
+impl Seat {
+
+    /// Gets the input steering the snake of the player sitting at the given
+    /// seat, if the player steers one: the first player steers the player
+    /// snake, the second one the partner snake (in co-op mode), and the
+    /// others only watch.
+    fn route(index: usize, input: Input) -> Option<Input> {
+        match (index, input) {
+            (0, Input::PartnerTurn(_)) => None,
+            (0, input) => Some(input),
+            (1, Input::Turn(direction)) => Some(Input::PartnerTurn(direction)),
+            _ => None
+        }
+    }
+
+}
+
+
+/// Host side of a networked run, owning the authoritative board.
+#[derive(Clone)]
+pub struct Host {
+    pub board: Board,               // authoritative board
+    inputs: Vec<(u32, Input)>,      // inputs applied so far and the tick they were given at
+    pending: Vec<Input>,            // inputs received since the last tick
+    sequence: u32,                  // sequence number of the last update
+    time: u64,                      // time of the last update (in milliseconds)
+    seats: Vec<Seat>,               // players who joined the run, in order
+}
+
+impl Host {
+
+    /// Creates the host of a run with the given rules and seed.
+    pub fn new(rules: Rules, seed: u64) -> Host {
+        Host {
+            board: Board::new(rules, seed),
+            inputs: Vec::new(),
+            pending: Vec::new(),
+            sequence: 0,
+            time: 0,
+            seats: Vec::new(),
+        }
+    }
+
+    /// Handles a message received from the client of the given player (the
+    /// name it joined with), returning the message to send back (if any), to
+    /// all the clients for the chat lines. The inputs of each player steer
+    /// the snake of the player, in the order the player numbered them.
+    pub fn receive(&mut self, player: &str, message: Message) -> Result<Option<Message>, &'static str> {
+        match message {
+            Message::Join { version, player, sequence } => {
+                if version != VERSION {
+                    return Err("The client protocol version is not supported");
+                }
+                info!("client joined: player = {}, sequence = {:?}", player, sequence);
+                // a player joining again keeps its seat, and the inputs it
+                // already sent
+                if self.seats.iter().all(|seat| seat.player != player) {
+                    self.seats.push(Seat { player, last_input: None });
+                }
+                // a client up to date needs no snapshot
+                if sequence == Some(self.sequence) {
+                    Ok(None)
+                } else {
+                    Ok(Some(self.snapshot()))
+                }
+            },
+            Message::Input { sequence, input } => {
+                let index = self.seats.iter().position(|seat| seat.player == player).ok_or("The player has not joined the run")?;
+                let seat = &mut self.seats[index];
+                // the inputs resent after reconnecting are discarded
+                if seat.last_input.is_none_or(|last| sequence > last) {
+                    seat.last_input = Some(sequence);
+                    self.pending.extend(Seat::route(index, input));
+                }
+                Ok(None)
+            },
+            ping @ Message::Ping { .. } => Ok(Some(ping)),
+            // the chat lines are signed with the name the player joined with
+            Message::Chat { text, .. } => Ok(Some(Message::Chat { player: player.to_string(), text })),
+            _ => Err("Unexpected message from a client"),
+        }
+    }
+
+    /// Applies the inputs received since the last tick and advances the
+    /// board by a tick at the given time (in milliseconds), returning the
+    /// update to send to the clients.
+    pub fn tick(&mut self, time: u64) -> Message {
+        let mut applied = Vec::new();
+        for input in self.pending.drain(..) {
+            if self.board.apply(input) {
+                self.inputs.push((self.board.ticks, input));
+                applied.push(input);
+            }
+        }
+        self.board.tick();
+        self.sequence += 1;
+        self.time = time;
+        Message::Delta { sequence: self.sequence, time, inputs: applied }
+    }
+
+    /// Gets the snapshot of the whole run up to the last update.
+    pub fn snapshot(&self) -> Message {
+        let replay = Replay {
+            rules: self.board.rules.clone(),
+            seed: self.board.seed,
+            inputs: self.inputs.clone(),
+            ticks: self.board.ticks,
+            score: self.board.score,
+        };
+        Message::Snapshot { sequence: self.sequence, time: self.time, replay }
+    }
+
+}
+
+
+/// Client side of a networked run, keeping a copy of the host board.
+#[derive(Clone)]
+pub struct Client {
+    pub board: Board,               // copy of the host board
+    pub previous: Option<Board>,    // board before the last update, to interpolate from
+    sequence: u32,                  // sequence number of the last update applied
+    host_time: u64,                 // host time of the last update
+    interval: u64,                  // host time between the last two updates
+    received_at: u64,               // client time the last update was received at
+    pings: u32,                     // number of pings sent
+    round_trip: Option<u64>,        // round-trip time of the last ping echoed
+}
+
+impl Client {
+
+    /// Creates the client from the snapshot of the run, received at the
+    /// given time (in milliseconds).
+    pub fn new(snapshot: Message, now: u64) -> Result<Client, &'static str> {
+        match snapshot {
+            Message::Snapshot { sequence, time, replay } => {
+                let board = Client::resync(&replay)?;
+                Ok(Client {
+                    // until the next update the interval is the tick duration
+                    interval: 1000 / u64::from(board.rules.tick_rate().max(1)),
+                    board,
+                    previous: None,
+                    sequence,
+                    host_time: time,
+                    received_at: now,
+                    pings: 0,
+                    round_trip: None,
+                })
+            },
+            _ => Err("A snapshot is needed to join the run")
+        }
+    }
+
+    /// Gets the message to ping the host at the given time.
+    pub fn ping(&mut self, now: u64) -> Message {
+        self.pings += 1;
+        Message::Ping { sequence: self.pings, time: now }
+    }
+
+    /// Gets the round-trip time of the last ping echoed by the host (in
+    /// milliseconds), if any.
+    pub fn round_trip(&self) -> Option<u64> {
+        self.round_trip
+    }
+
+    /// Gets how far the board is drawn from the previous one to the current
+    /// one at the given time (from 0 to 1): the snakes move between the two
+    /// in as long as the host took between the two updates.
+    pub fn interpolation(&self, now: u64) -> f32 {
+        if self.previous.is_none() || self.interval == 0 {
+            return 1.0;
+        }
+        (now.saturating_sub(self.received_at) as f32 / self.interval as f32).min(1.0)
+    }
+
+    /// Gets the message to join the run again after dropping.
+    pub fn rejoin(&self, player: &str) -> Message {
+        Message::Join { version: VERSION, player: player.to_string(), sequence: Some(self.sequence) }
+    }
+
+    /// Applies a message received from the host at the given time (in
+    /// milliseconds), returning the events of the tick played (if any). An
+    /// update out of order leaves the board as is and returns an error: the
+    /// client must then join again to be sent a snapshot.
+    pub fn receive(&mut self, message: Message, now: u64) -> Result<Vec<TickEvent>, &'static str> {
+        let mut events = Vec::new();
+        match message {
+            Message::Snapshot { sequence, time, replay } => {
+                // the snakes are not interpolated across a resync
+                self.board = Client::resync(&replay)?;
+                self.previous = None;
+                self.sequence = sequence;
+                self.host_time = time;
+                self.received_at = now;
+            },
+            Message::Delta { sequence, time, inputs } => {
+                if sequence != self.sequence + 1 {
+                    warn!("update out of order: expected = {}, received = {}", self.sequence + 1, sequence);
+                    return Err("The client is out of sync, a snapshot is needed");
+                }
+                self.previous = Some(self.board.clone());
+                for input in inputs {
+                    self.board.apply(input);
+                }
+                events = self.board.tick();
+                self.sequence = sequence;
+                self.interval = time.saturating_sub(self.host_time);
+                self.host_time = time;
+                self.received_at = now;
+            },
+            Message::Ping { time, .. } => self.round_trip = Some(now.saturating_sub(time)),
+            // the chat lines are shown by the frontend
+            Message::Chat { .. } => (),
+            _ => return Err("Unexpected message from the host")
+        };
+        Ok(events)
+    }
+
+    /// Rebuilds the board of a snapshot simulating its replay, which must
+    /// reach the score of the host.
+    fn resync(replay: &Replay) -> Result<Board, &'static str> {
+        let board = replay.simulate();
+        if board.ticks != replay.ticks || board.score != replay.score {
+            return Err("The snapshot does not match its replay");
+        }
+        Ok(board)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::Mode;
+    use grid::{Direction, Grid};
+
+    fn host() -> Host {
+        let mut rules = Rules::new(Mode::Coop, Grid::new(20, 15));
+        rules.start_direction = Some(Direction::Right);
+        Host::new(rules, 7)
+    }
+
+    fn join(host: &mut Host, player: &str, sequence: Option<u32>) -> Option<Message> {
+        let message = Message::Join { version: VERSION, player: player.to_string(), sequence };
+        host.receive(player, message).unwrap()
+    }
+
+    fn input(host: &mut Host, player: &str, sequence: u32, input: Input) {
+        host.receive(player, Message::Input { sequence, input }).unwrap();
+    }
+
+    fn assert_same(client: &Client, host: &Host) {
+        assert_eq!(client.board.ticks, host.board.ticks);
+        assert_eq!(client.board.score, host.board.score);
+        assert_eq!(client.board.to_ascii(), host.board.to_ascii());
+    }
+
+    #[test]
+    fn updates_are_applied_in_order() {
+        let mut host = host();
+        let mut client = Client::new(join(&mut host, "alice", None).unwrap(), 0).unwrap();
+        let mut deltas = Vec::new();
+        for (tick, direction) in [Direction::Up, Direction::Left, Direction::Down].iter().enumerate() {
+            input(&mut host, "alice", tick as u32, Input::Turn(*direction));
+            deltas.push(host.tick(100 * (tick as u64 + 1)));
+        }
+        // an update skipping one is rejected and leaves the board as is
+        let before = client.board.to_ascii();
+        assert!(client.receive(deltas[1].clone(), 100).is_err());
+        assert_eq!(client.board.to_ascii(), before);
+        for (tick, delta) in deltas.into_iter().enumerate() {
+            client.receive(delta, 100 * (tick as u64 + 1)).unwrap();
+        }
+        assert_same(&client, &host);
+        assert!(client.previous.is_some());
+    }
+
+    #[test]
+    fn duplicate_inputs_are_applied_once() {
+        let mut host = host();
+        join(&mut host, "alice", None);
+        input(&mut host, "alice", 1, Input::Turn(Direction::Up));
+        input(&mut host, "alice", 1, Input::Turn(Direction::Up));
+        input(&mut host, "alice", 0, Input::Turn(Direction::Down));
+        assert_eq!(host.pending, vec![Input::Turn(Direction::Up)]);
+        // rejoining keeps the sequence of the inputs already sent
+        join(&mut host, "alice", Some(0));
+        input(&mut host, "alice", 1, Input::Turn(Direction::Up));
+        assert_eq!(host.pending.len(), 1);
+        assert!(host.receive("mallory", Message::Input { sequence: 2, input: Input::Stop }).is_err());
+    }
+
+    #[test]
+    fn inputs_of_each_client_steer_its_snake() {
+        let mut host = host();
+        join(&mut host, "alice", None);
+        join(&mut host, "bob", None);
+        join(&mut host, "carol", None);
+        // each client numbers its own inputs
+        input(&mut host, "alice", 5, Input::Turn(Direction::Up));
+        input(&mut host, "bob", 1, Input::Turn(Direction::Down));
+        input(&mut host, "bob", 2, Input::Boost(true));
+        input(&mut host, "carol", 1, Input::Turn(Direction::Left));
+        input(&mut host, "alice", 6, Input::PartnerTurn(Direction::Left));
+        assert_eq!(host.pending, vec![Input::Turn(Direction::Up), Input::PartnerTurn(Direction::Down)]);
+    }
+
+    #[test]
+    fn clients_resync_from_a_snapshot() {
+        let mut host = host();
+        let mut client = Client::new(join(&mut host, "alice", None).unwrap(), 0).unwrap();
+        input(&mut host, "alice", 0, Input::Turn(Direction::Up));
+        client.receive(host.tick(100), 100).unwrap();
+        // the client drops two updates
+        input(&mut host, "alice", 1, Input::Turn(Direction::Left));
+        host.tick(200);
+        host.tick(300);
+        let delta = host.tick(400);
+        assert!(client.receive(delta, 400).is_err());
+        // an up to date client is sent no snapshot
+        let snapshot = host.receive("alice", client.rejoin("alice")).unwrap().unwrap();
+        client.receive(snapshot, 400).unwrap();
+        assert_same(&client, &host);
+        assert!(client.previous.is_none());
+        assert_eq!(host.receive("alice", client.rejoin("alice")).unwrap(), None);
+        // the run goes on from the snapshot
+        client.receive(host.tick(500), 500).unwrap();
+        assert_same(&client, &host);
+    }
+
+    #[test]
+    fn chat_lines_are_signed_by_the_host() {
+        let mut host = host();
+        join(&mut host, "alice", None);
+        let chat = Message::Chat { player: "bob".to_string(), text: "hi".to_string() };
+        let relayed = Message::Chat { player: "alice".to_string(), text: "hi".to_string() };
+        assert_eq!(host.receive("alice", chat), Ok(Some(relayed)));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let mut host = host();
+        join(&mut host, "alice", None);
+        input(&mut host, "alice", 0, Input::Turn(Direction::Up));
+        let messages = vec![
+            Message::Join { version: VERSION, player: "alice".to_string(), sequence: Some(3) },
+            Message::Input { sequence: 4, input: Input::PartnerTurn(Direction::Down) },
+            host.tick(100),
+            host.snapshot(),
+            Message::Chat { player: "bob".to_string(), text: "hello there".to_string() },
+            Message::Refused { reason: "The room is full".to_string() },
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse::<Message>(), Ok(message.clone()));
+        }
+    }
+
+}
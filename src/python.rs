@@ -0,0 +1,226 @@
+//! Python bindings of the step by step environment (`import snake_env`),
+//! the observations being numpy arrays.
+
+use numpy::{PyArray1, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rand::prelude::*;
+
+use board::{Mode, Rules};
+use env::{self, Encoding, Env, RewardConfig, VecEnv};
+use grid::{Direction, Grid};
+use replay::MAX_CELLS;
+
+
+/// Environment playing a run one tick per step: `reset` returns the first
+/// observation, `step` takes an action (0 left, 1 up, 2 right, 3 down or
+/// None to keep going) and returns the next observation, the reward and
+/// whether the run is over. The rewards are given by a preset (`default`,
+/// `sparse`, `scaled` or `shaped`). The observations are given in the encoding of
+/// the environment: `grid` (cell codes, the default), `one-hot`, `window`
+/// (the cells within `window` cells from the head) or `features`.
+#[pyclass(name = "SnakeEnv")]
+struct SnakeEnv {
+    env: Env,   // environment of the current run
+}
+
+#[pymethods]
+impl SnakeEnv {
+
+    /// Creates the environment of runs with the given options (like the
+    /// game ones), rewards and observations encoding, starting the first
+    /// run with the given seed (random if none).
+    #[new]
+    #[pyo3(signature = (columns = 20, rows = 15, mode = "classic", enemies = 0, portals = 0, poison = 0, seed = None, rewards = "default", encoding = "grid", window = 5))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(columns: i32, rows: i32, mode: &str, enemies: u32, portals: u32, poison: i32, seed: Option<u64>, rewards: &str, encoding: &str, window: u32) -> PyResult<SnakeEnv> {
+        let rules = rules(columns, rows, mode, enemies, portals, poison)?;
+        let rewards = RewardConfig::from_name(rewards).map_err(PyValueError::new_err)?;
+        let mut env = Env::new(rules, seed.unwrap_or_else(|| thread_rng().gen()), rewards);
+        env.encoding = Encoding::from_name(encoding, window).map_err(PyValueError::new_err)?;
+        Ok(SnakeEnv { env })
+    }
+
+    /// Starts a new run with the given seed (random if none), returning the
+    /// first observation.
+    #[pyo3(signature = (seed = None))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: Option<u64>) -> PyResult<Bound<'py, PyAny>> {
+        self.env.reset(seed.unwrap_or_else(|| thread_rng().gen()));
+        self.observation(py)
+    }
+
+    /// Plays a tick with the given action, returning the observation, the
+    /// reward and whether the run is over.
+    #[pyo3(signature = (action = None))]
+    fn step<'py>(&mut self, py: Python<'py>, action: Option<usize>) -> PyResult<(Bound<'py, PyAny>, f32, bool)> {
+        let step = self.env.step(direction(action)?);
+        Ok((self.observation(py)?, step.reward, step.done))
+    }
+
+    /// Gets the observation of the board in the encoding of the environment:
+    /// a (rows, columns) array of the cell codes for `grid`, otherwise an
+    /// array of `float32` shaped like `shape`.
+    fn observation<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let shape = self.shape();
+        if self.env.encoding == Encoding::Grid {
+            return Ok(PyArray1::from_vec(py, self.env.observation()).reshape(shape)?.into_any());
+        }
+        Ok(PyArray1::from_vec(py, self.env.encode()).reshape(shape)?.into_any())
+    }
+
+    /// Shape of the observations.
+    #[getter]
+    fn shape(&self) -> Vec<usize> {
+        let grid = self.env.board.rules.grid;
+        self.env.encoding.shape(grid.columns, grid.rows)
+    }
+
+    /// Score of the current run.
+    #[getter]
+    fn score(&self) -> u32 {
+        self.env.board.score
+    }
+
+    /// Number of ticks played in the current run.
+    #[getter]
+    fn ticks(&self) -> u32 {
+        self.env.board.ticks
+    }
+
+    /// Seed of the current run.
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.env.board.seed
+    }
+
+}
+
+
+/// Observations, rewards and whether each run is over, returned by the
+/// steps of `SnakeVecEnv`.
+type Steps<'py> = (Bound<'py, PyAny>, Bound<'py, PyArray1<f32>>, Bound<'py, PyArray1<bool>>);
+
+
+/// Environments stepped together in parallel: `reset` returns the first
+/// observations, `step` takes an action for each environment and returns
+/// the observations, the rewards and whether each run is over, as arrays
+/// whose first dimension is the environment. The runs over start again
+/// right away. The options are the ones of `SnakeEnv`, the observations
+/// being `float32` arrays in every encoding.
+#[pyclass(name = "SnakeVecEnv")]
+struct SnakeVecEnv {
+    envs: VecEnv,   // environments stepped together
+}
+
+#[pymethods]
+impl SnakeVecEnv {
+
+    /// Creates the given number of environments, the first runs having
+    /// consecutive seeds starting from the given one (random if none).
+    #[new]
+    #[pyo3(signature = (count = 8, columns = 20, rows = 15, mode = "classic", enemies = 0, portals = 0, poison = 0, seed = None, rewards = "default", encoding = "grid", window = 5))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(count: usize, columns: i32, rows: i32, mode: &str, enemies: u32, portals: u32, poison: i32, seed: Option<u64>, rewards: &str, encoding: &str, window: u32) -> PyResult<SnakeVecEnv> {
+        let rules = rules(columns, rows, mode, enemies, portals, poison)?;
+        let rewards = RewardConfig::from_name(rewards).map_err(PyValueError::new_err)?;
+        let encoding = Encoding::from_name(encoding, window).map_err(PyValueError::new_err)?;
+        Ok(SnakeVecEnv { envs: VecEnv::new(rules, &seeds(count, seed), rewards, encoding) })
+    }
+
+    /// Starts a new run in every environment, with consecutive seeds
+    /// starting from the given one (random if none), returning the first
+    /// observations.
+    #[pyo3(signature = (seed = None))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: Option<u64>) -> PyResult<Bound<'py, PyAny>> {
+        self.envs.reset(&seeds(self.envs.envs.len(), seed));
+        self.observation(py)
+    }
+
+    /// Plays a tick in every environment with its action, returning the
+    /// observations, the rewards and whether each run is over.
+    fn step<'py>(&mut self, py: Python<'py>, actions: Vec<Option<usize>>) -> PyResult<Steps<'py>> {
+        if actions.len() != self.envs.envs.len() {
+            return Err(PyValueError::new_err("An action is needed for each environment"));
+        }
+        let directions = actions.into_iter().map(direction).collect::<PyResult<Vec<_>>>()?;
+        let steps = py.detach(|| self.envs.step(&directions));
+        let rewards = steps.iter().map(|step| step.reward).collect();
+        let done = steps.iter().map(|step| step.done).collect();
+        Ok((self.observation(py)?, PyArray1::from_vec(py, rewards), PyArray1::from_vec(py, done)))
+    }
+
+    /// Gets the observations of all the environments, as an array shaped
+    /// like `shape`.
+    fn observation<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let observations = py.detach(|| self.envs.encode());
+        Ok(PyArray1::from_vec(py, observations).reshape(self.shape())?.into_any())
+    }
+
+    /// Shape of the observations: the number of environments, then the
+    /// shape of the observations of each one.
+    #[getter]
+    fn shape(&self) -> Vec<usize> {
+        let mut shape = vec![self.envs.envs.len()];
+        if let Some(env) = self.envs.envs.first() {
+            let grid = env.board.rules.grid;
+            shape.extend(env.encoding.shape(grid.columns, grid.rows));
+        }
+        shape
+    }
+
+    /// Scores of the current runs.
+    #[getter]
+    fn scores(&self) -> Vec<u32> {
+        self.envs.envs.iter().map(|env| env.board.score).collect()
+    }
+
+}
+
+
+/// Creates the rules of the runs with the given options.
+fn rules(columns: i32, rows: i32, mode: &str, enemies: u32, portals: u32, poison: i32) -> PyResult<Rules> {
+    if columns <= 0 || rows <= 0 {
+        return Err(PyValueError::new_err("The number of columns and rows must be positive"));
+    }
+    if i64::from(columns) * i64::from(rows) > MAX_CELLS {
+        return Err(PyValueError::new_err("The board is too large"));
+    }
+    let mut rules = Rules::new(Mode::from_name(mode).map_err(PyValueError::new_err)?, Grid::new(columns, rows));
+    rules.enemy_count = enemies;
+    rules.portal_count = portals;
+    rules.poison_growth = poison;
+    Ok(rules)
+}
+
+/// Gets the direction of the given action (0 left, 1 up, 2 right, 3 down
+/// or None to keep going).
+fn direction(action: Option<usize>) -> PyResult<Option<Direction>> {
+    match action {
+        Some(action) => Ok(Some(*Direction::all().get(action).ok_or_else(|| PyValueError::new_err("The action must be 0, 1, 2, 3 or None"))?)),
+        None => Ok(None),
+    }
+}
+
+/// Gets the given number of consecutive seeds, starting from the given one
+/// (random if none).
+fn seeds(count: usize, first: Option<u64>) -> Vec<u64> {
+    let first = first.unwrap_or_else(|| thread_rng().gen());
+    (0..count as u64).map(|index| first.wrapping_add(index)).collect()
+}
+
+
+/// Python module of the environments and of the codes of the cells.
+#[pymodule]
+fn snake_env(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<SnakeEnv>()?;
+    module.add_class::<SnakeVecEnv>()?;
+    let codes = [
+        ("EMPTY", env::EMPTY), ("BODY", env::BODY), ("HEAD", env::HEAD), ("FOOD", env::FOOD),
+        ("POISON", env::POISON), ("ENEMY", env::ENEMY), ("PORTAL", env::PORTAL), ("RIVAL", env::RIVAL),
+        ("PARTNER", env::PARTNER),
+    ];
+    for (name, code) in &codes {
+        module.add(*name, *code)?;
+    }
+    Ok(())
+}
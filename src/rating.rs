@@ -0,0 +1,68 @@
+//! Elo ratings of the player and of the rival snake, updated after every
+//! versus match.
+
+
+/// Rating of a player who never played a match.
+pub const INITIAL_RATING: f32 = 1200.0;
+
+/// Maximum rating change after a single match.
+const K_FACTOR: f32 = 32.0;
+
+
+/// Gets the expected score (from 0 to 1) of a player with the given rating
+/// against an opponent with the other one.
+pub fn expected_score(rating: f32, opponent: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((opponent - rating) / 400.0))
+}
+
+/// Updates the ratings of both players after a match, given the score of
+/// the first one: 1 for a win, 0.5 for a draw and 0 for a loss. Returns the
+/// change of the first rating (the second one changes by its opposite).
+pub fn update(rating: &mut f32, opponent: &mut f32, score: f32) -> f32 {
+    let change = K_FACTOR * (score - expected_score(*rating, *opponent));
+    *rating += change;
+    *opponent -= change;
+    change
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_scores_are_symmetric_and_bounded() {
+        for (rating, opponent) in [(1200.0, 1200.0), (1500.0, 1100.0), (800.0, 2400.0)].iter().cloned() {
+            let (expected, opposite) = (expected_score(rating, opponent), expected_score(opponent, rating));
+            assert!((expected + opposite - 1.0).abs() < 1e-6);
+            assert!(expected > 0.0 && expected < 1.0);
+        }
+        assert_eq!(expected_score(1200.0, 1200.0), 0.5);
+        assert!(expected_score(1600.0, 1200.0) > expected_score(1400.0, 1200.0));
+    }
+
+    #[test]
+    fn known_elo_example() {
+        // 400 points more are 10 to 1 odds
+        assert!((expected_score(1600.0, 1200.0) - 10.0 / 11.0).abs() < 1e-6);
+        // a win between equals is worth half the K factor
+        let (mut rating, mut opponent) = (INITIAL_RATING, INITIAL_RATING);
+        assert_eq!(update(&mut rating, &mut opponent, 1.0), 16.0);
+        assert_eq!((rating, opponent), (1216.0, 1184.0));
+    }
+
+    #[test]
+    fn updates_are_zero_sum() {
+        let (mut rating, mut opponent) = (1350.0, 1275.0);
+        for score in [0.0, 0.5, 1.0, 1.0, 0.0].iter().cloned() {
+            let total = rating + opponent;
+            let change = update(&mut rating, &mut opponent, score);
+            assert!(change.abs() <= K_FACTOR);
+            assert!((rating + opponent - total).abs() < 1e-3);
+        }
+        // a draw against a weaker opponent loses points
+        let (mut rating, mut opponent) = (1500.0, 1300.0);
+        assert!(update(&mut rating, &mut opponent, 0.5) < 0.0);
+    }
+
+}
@@ -0,0 +1,269 @@
+//! Recording, verification and headless simulation of the runs.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use board::{Board, Input, Mode, Modifiers, Mutators, Rules, Scoring, FULL_SPEED};
+use grid::{Direction, Grid, Topology};
+use hooks::{self, GameHooks};
+use spawn::FoodSpawn;
+
+
+/// Largest board of a replay (in cells): the replays may come from anyone
+/// (e.g. submitted to the leaderboard), and the board allocates its cells.
+/// The boards created through the C and Python APIs are bounded alike.
+pub const MAX_CELLS: i64 = 1 << 20;
+
+/// Most foods, portal pairs or enemies of a replay.
+const MAX_ENTITIES: u32 = 1024;
+
+/// Longest run of a replay (in ticks): a day at the default speed.
+const MAX_TICKS: u32 = 24 * 3600 * 10;
+
+/// Highest speed of a replay (in ticks per second), like the game options.
+const MAX_SPEED: u32 = 240;
+
+/// Recording of a finished run: the rules, the seed and the player inputs
+/// needed to simulate it again, along with the score it claims.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub rules: Rules,               // rules of the run
+    pub seed: u64,                  // seed of the random number generator
+    pub inputs: Vec<(u32, Input)>,  // player inputs and the tick they were given at
+    pub ticks: u32,                 // length of the run
+    pub score: u32,                 // claimed score
+}
+
+impl Replay {
+
+    /// Loads a replay from the given file.
+    pub fn load(path: &Path) -> Result<Replay, &'static str> {
+        let content = fs::read_to_string(path).map_err(|_| "Unable to read the replay file")?;
+        content.parse()
+    }
+
+    /// Saves the replay to the given file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    /// Simulates the whole run without rendering it, applying each input
+    /// right before the tick it was given at.
+    pub fn simulate(&self) -> Board {
+        self.simulate_with(|_| ())
+    }
+
+    /// Simulates the whole run, calling `on_tick` with the initial board
+    /// and then after every tick.
+    pub fn simulate_with<F>(&self, on_tick: F) -> Board
+        where F: FnMut(&Board)
+    {
+        self.simulate_until(self.ticks, on_tick)
+    }
+
+    /// Simulates the whole run, notifying the given hooks of every tick.
+    pub fn simulate_with_hooks(&self, hooks: &mut [Box<dyn GameHooks>]) -> Board {
+        self.play(self.ticks, hooks, |_| ())
+    }
+
+    /// Simulates the run up to the given tick at most, calling `on_tick`
+    /// with the initial board and then after every tick.
+    pub fn simulate_until<F>(&self, last_tick: u32, on_tick: F) -> Board
+        where F: FnMut(&Board)
+    {
+        self.play(last_tick, &mut [], on_tick)
+    }
+
+    /// Plays the run up to the given tick at most, notifying the given
+    /// hooks of every tick, and calling `on_tick` with the initial board
+    /// and then after every tick.
+    fn play<F>(&self, last_tick: u32, hooks: &mut [Box<dyn GameHooks>], mut on_tick: F) -> Board
+        where F: FnMut(&Board)
+    {
+        let mut board = Board::new(self.rules.clone(), self.seed);
+        let mut inputs = self.inputs.iter().peekable();
+        on_tick(&board);
+        while board.ticks < last_tick.min(self.ticks) && !board.over {
+            while let Some((_, input)) = inputs.next_if(|(tick, _)| *tick <= board.ticks) {
+                board.apply(*input);
+            }
+            hooks::tick(&mut board, hooks);
+            on_tick(&board);
+        }
+        board
+    }
+
+    /// Verifies the replay simulating the whole run, which must end exactly
+    /// at its last tick with the claimed score. Returns the score.
+    pub fn verify(&self) -> Result<u32, &'static str> {
+        let sorted = self.inputs.windows(2).all(|pair| pair[0].0 <= pair[1].0);
+        if !sorted || self.inputs.last().is_some_and(|(tick, _)| *tick >= self.ticks) {
+            return Err("The replay inputs are not in chronological order");
+        }
+        let board = self.simulate();
+        if !board.over || board.ticks != self.ticks {
+            return Err("The simulated run does not end with the replay");
+        }
+        if board.score != self.score {
+            return Err("The simulated score does not match the claimed one");
+        }
+        Ok(board.score)
+    }
+
+}
+
+impl FromStr for Replay {
+    type Err = &'static str;
+
+    /// Parses a replay made of `key = value` lines, like the configuration
+    /// files. Empty lines and lines starting with `#` are ignored.
+    fn from_str(content: &str) -> Result<Replay, &'static str> {
+        // parse a number, whatever its type
+        fn number<T: FromStr>(value: &str) -> Result<T, &'static str> {
+            value.parse().map_err(|_| "Invalid number in the replay")
+        }
+        let mut rules = Rules::new(Mode::Classic, Grid::new(0, 0));
+        let (mut seed, mut ticks, mut score, mut scoring) = (None, None, None, "");
+        let mut inputs = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or("Invalid replay line: <key> = <value>")?;
+            let value = value.trim();
+            match key.trim() {
+                "mode" => rules.mode = Mode::from_name(value)?,
+                "board-columns" => rules.grid.columns = number(value)?,
+                "board-rows" => rules.grid.rows = number(value)?,
+                "topology" => rules.grid.topology = Topology::from_name(value)?,
+                "growth" => rules.growth_per_food = number(value)?,
+                "poison" => rules.poison_growth = number(value)?,
+                "magnet" => rules.magnet_seconds = number(value)?,
+                "star" => rules.star_seconds = number(value)?,
+                "portals" => rules.portal_count = number(value)?,
+                "foods" => rules.food_count = number(value)?,
+                "enemies" => rules.enemy_count = number(value)?,
+                "enemy-period" => rules.enemy_period = number(value)?,
+                "speed" => rules.ticks_per_second = number(value)?,
+                "rival-speed" => rules.rival_speed = number(value)?,
+                "start-column" => rules.start_column = Some(number(value)?),
+                "start-row" => rules.start_row = Some(number(value)?),
+                "start-direction" => rules.start_direction = Some(Direction::from_name(value)?),
+                "modifiers" => rules.modifiers = Modifiers::from_names(value)?,
+                "mutators" => rules.mutators = Mutators::from_names(value)?,
+                "food-spawn" => rules.food_spawn = FoodSpawn::from_name(value)?,
+                "scoring" => scoring = value,
+                "seed" => seed = Some(number(value)?),
+                "ticks" => ticks = Some(number(value)?),
+                "score" => score = Some(number(value)?),
+                "inputs" => {
+                    // each input is given as <tick>:<direction|partner-direction|stop>
+                    for token in value.split_whitespace() {
+                        let (tick, name) = token.split_once(':').ok_or("Invalid replay input: <tick>:<input>")?;
+                        inputs.push((number(tick)?, Input::from_name(name)?));
+                    }
+                },
+                _ => return Err("Unknown replay key")
+            };
+        }
+        if rules.grid.columns <= 0 || rules.grid.rows <= 0 {
+            return Err("The replay board size must be positive");
+        }
+        if i64::from(rules.grid.columns) * i64::from(rules.grid.rows) > MAX_CELLS {
+            return Err("The replay board is too large");
+        }
+        if rules.food_count == 0 || rules.food_count > MAX_ENTITIES || rules.portal_count > MAX_ENTITIES || rules.enemy_count > MAX_ENTITIES {
+            return Err("The replay has too many (or no) foods, portals or enemies");
+        }
+        if rules.ticks_per_second == 0 || rules.ticks_per_second > MAX_SPEED {
+            return Err("The replay speed must be between 1 and 240");
+        }
+        if rules.rival_speed == 0 || rules.enemy_period == 0 {
+            return Err("The replay rival speed and enemy period must be positive");
+        }
+        if ticks.is_some_and(|ticks| ticks > MAX_TICKS) {
+            return Err("The replay is too long");
+        }
+        if rules.grid.topology == Topology::Hex && rules.grid.rows % 2 != 0 {
+            return Err("The replay hex board must have an even number of rows");
+        }
+        // the points not recorded are the preset of the mode
+        rules.scoring = Scoring::new(rules.mode).with(scoring)?;
+        Ok(Replay {
+            rules,
+            seed: seed.ok_or("The replay seed is missing")?,
+            inputs,
+            ticks: ticks.ok_or("The replay length is missing")?,
+            score: score.ok_or("The replay score is missing")?,
+        })
+    }
+
+}
+
+impl fmt::Display for Replay {
+
+    /// Writes the replay as `key = value` lines.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules = &self.rules;
+        writeln!(f, "mode = {}", rules.mode.name())?;
+        writeln!(f, "board-columns = {}", rules.grid.columns)?;
+        writeln!(f, "board-rows = {}", rules.grid.rows)?;
+        if rules.grid.topology != Topology::Square {
+            writeln!(f, "topology = {}", rules.grid.topology.name())?;
+        }
+        writeln!(f, "growth = {}", rules.growth_per_food)?;
+        writeln!(f, "poison = {}", rules.poison_growth)?;
+        if rules.magnet_seconds != 0 {
+            writeln!(f, "magnet = {}", rules.magnet_seconds)?;
+        }
+        if rules.star_seconds != 0 {
+            writeln!(f, "star = {}", rules.star_seconds)?;
+        }
+        writeln!(f, "portals = {}", rules.portal_count)?;
+        if rules.food_count != 1 {
+            writeln!(f, "foods = {}", rules.food_count)?;
+        }
+        writeln!(f, "enemies = {}", rules.enemy_count)?;
+        writeln!(f, "enemy-period = {}", rules.enemy_period)?;
+        writeln!(f, "speed = {}", rules.ticks_per_second)?;
+        if rules.rival_speed != FULL_SPEED {
+            writeln!(f, "rival-speed = {}", rules.rival_speed)?;
+        }
+        if let Some(column) = rules.start_column {
+            writeln!(f, "start-column = {}", column)?;
+        }
+        if let Some(row) = rules.start_row {
+            writeln!(f, "start-row = {}", row)?;
+        }
+        if let Some(direction) = rules.start_direction {
+            writeln!(f, "start-direction = {}", direction.name())?;
+        }
+        if !rules.mutators.is_empty() {
+            writeln!(f, "mutators = {}", rules.mutators.names().join(" "))?;
+        }
+        if rules.food_spawn != FoodSpawn::Uniform {
+            writeln!(f, "food-spawn = {}", rules.food_spawn.name())?;
+        }
+        let scoring = rules.scoring.overrides(rules.mode);
+        if !scoring.is_empty() {
+            writeln!(f, "scoring = {}", scoring.join(" "))?;
+        }
+        if rules.modifiers.any() {
+            writeln!(f, "modifiers = {}", rules.modifiers.names().join(" "))?;
+        }
+        writeln!(f, "seed = {}", self.seed)?;
+        writeln!(f, "ticks = {}", self.ticks)?;
+        writeln!(f, "score = {}", self.score)?;
+        let inputs: Vec<String> = self.inputs.iter()
+            .map(|(tick, input)| format!("{}:{}", tick, input.name()))
+            .collect();
+        writeln!(f, "inputs = {}", inputs.join(" "))
+    }
+
+}
@@ -0,0 +1,141 @@
+//! Settings remembered between the sessions, saved to a file.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use board::Mode;
+use difficulty::{self, Difficulty};
+use rating::INITIAL_RATING;
+
+
+/// File where the settings are saved between sessions.
+const SETTINGS_FILE: &str = "settings.txt";
+
+
+/// Settings changed while playing, saved on exit and restored on the next
+/// launch (unlike the configuration, given at every launch).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub window_position: Option<(i32, i32)>,    // window position on the desktop (placed by the system if none)
+    pub window_size: Option<(u32, u32)>,        // window size (given on the command line if none)
+    pub fullscreen: bool,                       // whether the game is shown fullscreen
+    pub volume: f32,                            // volume of the sounds (from 0 to 100)
+    pub mode: Option<Mode>,                     // game mode of the last session
+    pub tutorial_done: bool,                    // whether the first run tutorial was completed
+    pub rating: f32,                            // Elo rating of the player in the versus matches
+    pub rival_rating: f32,                      // Elo rating of the rival snake
+    pub difficulty: Difficulty,                 // adaptive difficulty of the runs
+}
+
+impl Default for Settings {
+
+    /// Creates the settings of the first session.
+    fn default() -> Settings {
+        Settings {
+            window_position: None,
+            window_size: None,
+            fullscreen: false,
+            volume: 100.0,
+            mode: None,
+            tutorial_done: false,
+            rating: INITIAL_RATING,
+            rival_rating: INITIAL_RATING,
+            difficulty: Difficulty::default(),
+        }
+    }
+
+}
+
+impl Settings {
+
+    /// Loads the settings of the last session, or the default ones if they
+    /// cannot be read.
+    pub fn load() -> Settings {
+        let content = match fs::read_to_string(SETTINGS_FILE) {
+            Ok(content) => content,
+            Err(_) => return Settings::default()
+        };
+        content.parse().unwrap_or_else(|err| {
+            warn!("unable to restore the settings: {}", err);
+            Settings::default()
+        })
+    }
+
+    /// Saves the settings for the next session.
+    pub fn save(&self) -> io::Result<()> {
+        fs::write(SETTINGS_FILE, self.to_string())
+    }
+
+}
+
+impl FromStr for Settings {
+    type Err = &'static str;
+
+    /// Parses the settings made of `key = value` lines, like the
+    /// configuration files.
+    fn from_str(content: &str) -> Result<Settings, &'static str> {
+        // parse a value, whatever its type
+        fn value<T: FromStr>(value: &str) -> Result<T, &'static str> {
+            value.parse().map_err(|_| "Invalid settings value")
+        }
+        let mut settings = Settings::default();
+        let (mut x, mut y, mut width, mut height) = (None, None, None, None);
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, raw) = line.split_once('=').ok_or("Invalid settings line: <key> = <value>")?;
+            let raw = raw.trim();
+            match key.trim() {
+                "window-x" => x = Some(value(raw)?),
+                "window-y" => y = Some(value(raw)?),
+                "window-width" => width = Some(value(raw)?),
+                "window-height" => height = Some(value(raw)?),
+                "fullscreen" => settings.fullscreen = value(raw)?,
+                "volume" => settings.volume = value::<f32>(raw)?.clamp(0.0, 100.0),
+                "mode" => settings.mode = Some(Mode::from_name(raw)?),
+                "tutorial-done" => settings.tutorial_done = value(raw)?,
+                "rating" => settings.rating = value(raw)?,
+                "rival-rating" => settings.rival_rating = value(raw)?,
+                "difficulty-level" => settings.difficulty.level = value::<i32>(raw)?.clamp(-difficulty::MAX_LEVEL, difficulty::MAX_LEVEL),
+                "difficulty-food-interval" => settings.difficulty.food_interval = value(raw)?,
+                "difficulty-run-seconds" => settings.difficulty.run_seconds = value(raw)?,
+                _ => return Err("Unknown settings key")
+            };
+        }
+        settings.window_position = x.and_then(|x| y.map(|y| (x, y)));
+        settings.window_size = width.and_then(|width| height.map(|height| (width, height)));
+        Ok(settings)
+    }
+
+}
+
+impl fmt::Display for Settings {
+
+    /// Writes the settings as `key = value` lines.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((x, y)) = self.window_position {
+            writeln!(f, "window-x = {}", x)?;
+            writeln!(f, "window-y = {}", y)?;
+        }
+        if let Some((width, height)) = self.window_size {
+            writeln!(f, "window-width = {}", width)?;
+            writeln!(f, "window-height = {}", height)?;
+        }
+        writeln!(f, "fullscreen = {}", self.fullscreen)?;
+        writeln!(f, "volume = {}", self.volume)?;
+        if let Some(mode) = self.mode {
+            writeln!(f, "mode = {}", mode.name())?;
+        }
+        writeln!(f, "tutorial-done = {}", self.tutorial_done)?;
+        writeln!(f, "rating = {}", self.rating)?;
+        writeln!(f, "rival-rating = {}", self.rival_rating)?;
+        writeln!(f, "difficulty-level = {}", self.difficulty.level)?;
+        writeln!(f, "difficulty-food-interval = {}", self.difficulty.food_interval)?;
+        writeln!(f, "difficulty-run-seconds = {}", self.difficulty.run_seconds)?;
+        Ok(())
+    }
+
+}
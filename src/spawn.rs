@@ -0,0 +1,187 @@
+//! Policies choosing the cell where the food spawns.
+
+use rand::Rng;
+
+use board::Board;
+use grid::GridPos;
+
+
+/// Chooses where the next food spawns.
+pub trait SpawnPolicy {
+
+    /// Chooses the cell of the next food among the cells not taken by any
+    /// entity, if there is any left.
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos>;
+
+}
+
+
+/// Any free cell, with the same probability.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Uniform;
+
+impl SpawnPolicy for Uniform {
+
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos> {
+        let free_cells = board.snake.free_cells();
+        if free_cells.is_empty() {
+            return None;
+        }
+        // the cells not occupied by the snake are sampled uniformly, most of
+        // the times the chosen cell is not taken by any other entity
+        let position = free_cells.get(rng.gen_range(0, free_cells.len()));
+        if !board.is_taken(position) {
+            return Some(position);
+        }
+        // otherwise choose among the cells not taken by any entity
+        choose(rng, free_cells.iter().cloned().filter(|cell| !board.is_taken(*cell)).collect())
+    }
+
+}
+
+
+/// The free cells far from the snake head: at least half as far as the
+/// farthest one (going around the edges if shorter).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FarFromHead;
+
+impl SpawnPolicy for FarFromHead {
+
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos> {
+        let grid = board.rules.grid;
+        let head = board.snake.head_position();
+        let distance = |cell: &GridPos| grid.distance(head, *cell);
+        let free: Vec<GridPos> = board.snake.free_cells().iter().cloned().filter(|cell| !board.is_taken(*cell)).collect();
+        let farthest = free.iter().map(distance).max()?;
+        choose(rng, free.into_iter().filter(|cell| 2 * distance(cell) >= farthest).collect())
+    }
+
+}
+
+
+/// The free cells along the edges of the board, or any free cell once the
+/// edges are full.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NearEdges;
+
+impl SpawnPolicy for NearEdges {
+
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos> {
+        let grid = board.rules.grid;
+        let edge = |cell: &GridPos| cell.x == 0 || cell.y == 0 || cell.x == grid.columns - 1 || cell.y == grid.rows - 1;
+        let edges: Vec<GridPos> = board.snake.free_cells().iter().cloned()
+            .filter(|cell| edge(cell) && !board.is_taken(*cell))
+            .collect();
+        if edges.is_empty() {
+            return Uniform.next_spawn(board, rng);
+        }
+        choose(rng, edges)
+    }
+
+}
+
+
+/// The given cells in order, one for each food eaten by the player (the
+/// list starting over once all used). A cell taken is skipped, and any free
+/// cell is chosen if all of them are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scripted<'a> {
+    pub cells: &'a [GridPos],   // cells of the foods, in order
+}
+
+impl<'a> SpawnPolicy for Scripted<'a> {
+
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos> {
+        let grid = board.rules.grid;
+        let count = self.cells.len();
+        let first = board.food_count as usize;
+        let scripted = (first..first + count)
+            .map(|index| grid.wrap(self.cells[index % count]))
+            .find(|cell| !board.is_taken(*cell));
+        scripted.or_else(|| Uniform.next_spawn(board, rng))
+    }
+
+}
+
+
+/// Policy of the food spawns of a run, part of its rules.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FoodSpawn {
+    #[default]
+    Uniform,                // any free cell
+    FarFromHead,            // far from the snake head
+    NearEdges,              // along the edges of the board
+    Scripted(Vec<GridPos>), // the given cells in order
+}
+
+impl FoodSpawn {
+
+    /// Parses the policy from its name (`uniform`, `far-from-head`,
+    /// `near-edges`), or from `scripted` followed by the cells as `x,y`
+    /// (e.g. `scripted 3,4 10,2`).
+    pub fn from_name(name: &str) -> Result<FoodSpawn, &'static str> {
+        let mut tokens = name.split_whitespace();
+        let spawn = match tokens.next() {
+            Some("uniform") => FoodSpawn::Uniform,
+            Some("far-from-head") => FoodSpawn::FarFromHead,
+            Some("near-edges") => FoodSpawn::NearEdges,
+            Some("scripted") => {
+                let cells = tokens.by_ref()
+                    .map(|cell| {
+                        let (x, y) = cell.split_once(',').ok_or("Invalid scripted cell: <x>,<y>")?;
+                        match (x.parse(), y.parse()) {
+                            (Ok(x), Ok(y)) if x >= 0 && y >= 0 => Ok(GridPos::new(x, y)),
+                            _ => Err("Invalid scripted cell: <x>,<y>")
+                        }
+                    })
+                    .collect::<Result<Vec<GridPos>, &'static str>>()?;
+                if cells.is_empty() {
+                    return Err("The scripted food spawn needs at least a cell");
+                }
+                FoodSpawn::Scripted(cells)
+            },
+            _ => return Err("Invalid food spawn: <uniform|far-from-head|near-edges|scripted <x>,<y>...>")
+        };
+        if tokens.next().is_some() {
+            return Err("Only the scripted food spawn takes cells");
+        }
+        Ok(spawn)
+    }
+
+    /// Gets the name of the policy, followed by the cells if scripted.
+    pub fn name(&self) -> String {
+        match self {
+            FoodSpawn::Uniform => String::from("uniform"),
+            FoodSpawn::FarFromHead => String::from("far-from-head"),
+            FoodSpawn::NearEdges => String::from("near-edges"),
+            FoodSpawn::Scripted(cells) => {
+                let cells: Vec<String> = cells.iter().map(|cell| format!("{},{}", cell.x, cell.y)).collect();
+                format!("scripted {}", cells.join(" "))
+            },
+        }
+    }
+
+}
+
+impl SpawnPolicy for FoodSpawn {
+
+    fn next_spawn<R: Rng>(&self, board: &Board, rng: &mut R) -> Option<GridPos> {
+        match self {
+            FoodSpawn::Uniform => Uniform.next_spawn(board, rng),
+            FoodSpawn::FarFromHead => FarFromHead.next_spawn(board, rng),
+            FoodSpawn::NearEdges => NearEdges.next_spawn(board, rng),
+            FoodSpawn::Scripted(cells) => Scripted { cells }.next_spawn(board, rng),
+        }
+    }
+
+}
+
+
+/// Chooses one of the given cells, if any.
+fn choose<R: Rng>(rng: &mut R, cells: Vec<GridPos>) -> Option<GridPos> {
+    if cells.is_empty() {
+        None
+    } else {
+        Some(cells[rng.gen_range(0, cells.len())])
+    }
+}
@@ -0,0 +1,46 @@
+//! Swipes of the mouse (or of a finger), turning the snake.
+
+use sfml::system::Vector2f;
+
+
+/// Detects the swipes of a mouse drag (or of a finger on a touch screen):
+/// every time the pointer, held down, moves far enough from where the
+/// swipe started.
+#[derive(Debug)]
+pub struct Swipe {
+    threshold: f32,             // distance the pointer must move to swipe (in pixels)
+    start: Option<Vector2f>,    // position the current swipe started from (none if not held down)
+}
+
+impl Swipe {
+
+    /// Creates the detector of the swipes at least as long as the given
+    /// distance (in pixels).
+    pub fn new(threshold: f32) -> Swipe {
+        Swipe { threshold: threshold.max(1.0), start: None }
+    }
+
+    /// Starts a swipe from the given position, where the pointer was held
+    /// down.
+    pub fn press(&mut self, position: Vector2f) {
+        self.start = Some(position);
+    }
+
+    /// Moves the pointer to the given position, returning the offset of the
+    /// swipe if it went far enough; the next swipe then starts from there,
+    /// so that a single drag can swipe several times.
+    pub fn drag(&mut self, position: Vector2f) -> Option<Vector2f> {
+        let offset = position - self.start?;
+        if offset.x * offset.x + offset.y * offset.y < self.threshold * self.threshold {
+            return None;
+        }
+        self.start = Some(position);
+        Some(offset)
+    }
+
+    /// Ends the swipe, the pointer being released.
+    pub fn release(&mut self) {
+        self.start = None;
+    }
+
+}
@@ -0,0 +1,224 @@
+//! Variants of the game played in each mode: what the snakes leave behind,
+//! who plays, what the collisions and the food lead to, how the points are
+//! scored and when the run ends, on top of the rules shared by the board
+//! (which only moves the snakes and asks the variant every tick).
+
+use board::{Board, Mutators, Rules, TickEvent};
+
+
+/// Seconds between two enemies joining a survival run.
+const SURVIVAL_ENEMY_SECONDS: u32 = 15;
+
+/// Length of a time attack run (in seconds).
+const TIME_ATTACK_SECONDS: u32 = 60;
+
+
+/// Rules of a variant of the game. Every method has the default of the
+/// classic game, the variants overriding what they change.
+pub trait Variant: Sync {
+
+    /// Returns true if the snakes leave a persistent trail behind their
+    /// head instead of moving their tail.
+    fn trail(&self) -> bool {
+        false
+    }
+
+    /// Returns true if there is food (and poison) to eat.
+    fn has_food(&self) -> bool {
+        true
+    }
+
+    /// Returns true if a rival snake controlled by the computer races the
+    /// player for the food.
+    fn has_rival(&self) -> bool {
+        false
+    }
+
+    /// Returns true if a second player steers another snake.
+    fn has_partner(&self) -> bool {
+        false
+    }
+
+    /// Gets the points scored for each food eaten (before the speed
+    /// multiplier).
+    fn food_points(&self) -> u32 {
+        10
+    }
+
+    /// Gets the points scored for each second survived (before the speed
+    /// multiplier).
+    fn second_points(&self) -> u32 {
+        0
+    }
+
+    /// Gets the number of enemies on the board after the given number of
+    /// seconds.
+    fn enemy_count(&self, rules: &Rules, _seconds: u32) -> u32 {
+        rules.enemy_count
+    }
+
+    /// Gets the number of seconds after which the run ends, if limited.
+    fn time_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Applies what the snakes moving leads to, before checking their
+    /// collisions (and before the star is picked up).
+    fn on_move(&self, _board: &mut Board, _events: &mut Vec<TickEvent>) {}
+
+    /// Checks the collisions of the snakes once moved, returns true if the
+    /// player snake crashed (the run being lost unless it is phasing): the
+    /// snake hitting itself or an enemy.
+    fn on_collision(&self, board: &mut Board) -> bool {
+        board.snake.self_collision() || board.enemy_collision()
+    }
+
+    /// Gets how the run ends once the snakes moved without crashing, before
+    /// anything is scored, if it does: a victory when the player snake
+    /// occupies every cell of the board.
+    fn outcome(&self, board: &Board) -> Option<TickEvent> {
+        if board.snake.free_cells().is_empty() {
+            Some(TickEvent::Victory)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the collisions of the snakes with the food and the poison
+    /// food, growing the snakes and scoring: only the player snake eats.
+    fn on_eat(&self, board: &mut Board, events: &mut Vec<TickEvent>) {
+        board.feed(false, events);
+    }
+
+    /// Returns true if the run ends at the end of the tick, once the time
+    /// limit (if any) is reached.
+    fn is_time_up(&self, board: &Board) -> bool {
+        self.time_limit().is_some_and(|limit| board.seconds() >= limit)
+    }
+
+}
+
+
+/// Eat food to grow and score points.
+pub struct Classic;
+
+impl Variant for Classic {}
+
+
+/// The snake leaves a persistent trail and scores by survival time.
+pub struct Tron;
+
+impl Variant for Tron {
+
+    fn trail(&self) -> bool {
+        true
+    }
+
+    fn has_food(&self) -> bool {
+        false
+    }
+
+    fn second_points(&self) -> u32 {
+        1
+    }
+
+    fn on_eat(&self, _board: &mut Board, _events: &mut Vec<TickEvent>) {}
+
+}
+
+
+/// A rival snake races the player for the food.
+pub struct Versus;
+
+impl Variant for Versus {
+
+    fn has_rival(&self) -> bool {
+        true
+    }
+
+    fn on_move(&self, board: &mut Board, events: &mut Vec<TickEvent>) {
+        if board.rules.mutators.contains(Mutators::TAIL_CUTTING) {
+            board.cut_tails(events);
+        }
+    }
+
+    fn on_collision(&self, board: &mut Board) -> bool {
+        let head = board.snake.head_position();
+        let collision = Classic.on_collision(board) || board.rival.as_ref().is_some_and(|rival| rival.collision(head));
+        // the rival hitting anything starts over somewhere else
+        if board.rival.as_ref().is_some_and(|rival| board.is_rival_crashed(rival)) {
+            board.spawn_rival();
+        }
+        collision
+    }
+
+    fn on_eat(&self, board: &mut Board, events: &mut Vec<TickEvent>) {
+        // the rival scores if it reaches the food first
+        if board.feed(false, events) {
+            board.feed_rival(events);
+        }
+    }
+
+}
+
+
+/// Two players, each with a snake, share the score.
+pub struct Coop;
+
+impl Variant for Coop {
+
+    fn has_partner(&self) -> bool {
+        true
+    }
+
+    fn on_collision(&self, board: &mut Board) -> bool {
+        // either player crashing ends the run
+        let head = board.snake.head_position();
+        let partner_collision = board.partner.as_ref().is_some_and(|partner| {
+            let partner_head = partner.head_position();
+            partner.self_collision()
+                || partner.collision(head)
+                || board.snake.collision(partner_head)
+                || board.enemies.iter().any(|enemy| partner.collision(enemy.position()))
+        });
+        Classic.on_collision(board) || partner_collision
+    }
+
+    fn on_eat(&self, board: &mut Board, events: &mut Vec<TickEvent>) {
+        // both players snakes eat
+        if board.feed(false, events) {
+            board.feed(true, events);
+        }
+    }
+
+}
+
+
+/// Every second survived scores on top of the food, while a new enemy
+/// joins every 15 seconds.
+pub struct Survival;
+
+impl Variant for Survival {
+
+    fn second_points(&self) -> u32 {
+        1
+    }
+
+    fn enemy_count(&self, rules: &Rules, seconds: u32) -> u32 {
+        rules.enemy_count + seconds / SURVIVAL_ENEMY_SECONDS
+    }
+
+}
+
+
+/// Eat as much food as possible in 60 seconds.
+pub struct TimeAttack;
+
+impl Variant for TimeAttack {
+
+    fn time_limit(&self) -> Option<u32> {
+        Some(TIME_ATTACK_SECONDS)
+    }
+
+}
+
@@ -0,0 +1,15 @@
+//! The headless benchmark only runs with snakes fitting its board.
+
+extern crate snake;
+
+use snake::Bench;
+
+
+#[test]
+fn snake_lengths_are_checked() {
+    assert!(Bench::new(10, 0).is_err());
+    assert!(Bench::new(10, u32::MAX).is_err());
+    assert!(Bench::new(10, i32::MAX as u32).is_err());
+    let benchmark = Bench::new(10, 100).unwrap().run();
+    assert_eq!(benchmark.ticks, 10);
+}
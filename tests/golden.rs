@@ -0,0 +1,26 @@
+//! The replays of the `golden` directory, recorded in every game mode, must
+//! all stay valid after any change to the rules.
+
+extern crate snake;
+
+use std::fs;
+use std::path::Path;
+
+use snake::replay::Replay;
+
+
+#[test]
+fn golden_replays_keep_their_scores() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+    let mut count = 0;
+    for entry in fs::read_dir(&directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "replay") {
+            continue;
+        }
+        let replay = Replay::load(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        assert_eq!(replay.verify(), Ok(replay.score), "{}", path.display());
+        count += 1;
+    }
+    assert!(count > 0, "no replays in {}", directory.display());
+}
@@ -0,0 +1,125 @@
+//! Property tests of the rules: runs with generated rules, seeds and inputs
+//! keep the invariants of the board, and play again identically from their
+//! replays (the same checks as the `rules` fuzz target).
+
+extern crate proptest;
+extern crate snake;
+
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use snake::board::{Board, Input, Mode, Mutators, Rules, TickEvent};
+use snake::grid::{Direction, Grid, GridPos, Topology};
+use snake::replay::Replay;
+
+
+/// Directions of both the square and the hex grids.
+const DIRECTIONS: [Direction; 8] = [
+    Direction::Left, Direction::Up, Direction::Right, Direction::Down,
+    Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+];
+
+
+/// Generates the rules of a run, on grids of up to 32 by 32 cells.
+fn rules() -> impl Strategy<Value = Rules> {
+    let mode = prop::sample::select(vec![Mode::Classic, Mode::Tron, Mode::Versus, Mode::Coop, Mode::Survival, Mode::TimeAttack]);
+    let grid = (1..=32i32, 1..=32i32, any::<bool>());
+    let entities = (1..=3u32, 0..4u32, 0..3u32, -2..2i32, any::<bool>());
+    (mode, grid, entities).prop_map(|(mode, (columns, rows, hex), (foods, enemies, portals, poison, cutting))| {
+        let topology = if hex { Topology::Hex } else { Topology::Square };
+        let mut rules = Rules::new(mode, Grid::with_topology(columns, rows, topology));
+        rules.food_count = foods;
+        rules.enemy_count = enemies;
+        rules.portal_count = portals;
+        rules.poison_growth = poison;
+        rules.mutators = if cutting { Mutators::TAIL_CUTTING } else { Mutators::default() };
+        rules
+    })
+}
+
+/// Generates the input of each tick: a turn of the player or of the
+/// partner in every direction (the ones of the other topology being
+/// discarded), a stop or none.
+fn inputs() -> impl Strategy<Value = Vec<Option<Input>>> {
+    let direction = prop::sample::select(DIRECTIONS.to_vec());
+    let input = prop_oneof![
+        direction.clone().prop_map(Input::Turn),
+        direction.prop_map(Input::PartnerTurn),
+        Just(Input::Stop),
+    ];
+    prop::collection::vec(prop::option::weighted(0.3, input), 0..300)
+}
+
+/// Plays a run with the given inputs until it is over, checking the
+/// invariants of the board after every tick. Returns the board and the
+/// inputs applied, by tick.
+fn play(rules: &Rules, seed: u64, inputs: &[Option<Input>]) -> Result<(Board, Vec<(u32, Input)>), TestCaseError> {
+    let mut board = Board::new(rules.clone(), seed);
+    let mut applied = Vec::new();
+    check(&board, false)?;
+    for input in inputs {
+        if board.over {
+            break;
+        }
+        if let Some(input) = *input {
+            if board.apply(input) {
+                applied.push((board.ticks, input));
+            }
+        }
+        let events = board.tick();
+        check(&board, events.contains(&TickEvent::Food))?;
+    }
+    Ok((board, applied))
+}
+
+/// Checks the invariants of the board, after a tick where food was eaten
+/// or not.
+fn check(board: &Board, eaten: bool) -> Result<(), TestCaseError> {
+    let grid = board.rules.grid;
+    let inside = |position: GridPos| position.x >= 0 && position.x < grid.columns && position.y >= 0 && position.y < grid.rows;
+    let snakes = Some(&board.snake).into_iter().chain(board.partner.as_ref()).chain(board.rival.as_ref());
+    for snake in snakes {
+        prop_assert!(snake.segments().all(|segment| inside(*segment)), "segment outside the board:\n{}", board.to_ascii());
+        // the snakes only overlap themselves when crashing
+        if !board.over {
+            let cells: HashSet<_> = snake.segments().collect();
+            prop_assert_eq!(cells.len(), snake.segments().count(), "duplicate occupied cells:\n{}", board.to_ascii());
+        }
+    }
+    prop_assert!(board.foods.iter().all(|food| inside(*food)), "food outside the board:\n{}", board.to_ascii());
+    prop_assert!(board.enemies.iter().all(|enemy| inside(enemy.position())), "enemy outside the board:\n{}", board.to_ascii());
+    // the snake grows at the tail by one segment per food, on the tick
+    // after eating it (only the player eats in classic mode, and neither
+    // poison nor cut tails change its length)
+    let classic = board.rules.mode == Mode::Classic && board.rules.poison_growth == 0;
+    if classic && !board.over {
+        let length = board.snake.segments().count() as u32;
+        prop_assert_eq!(length + eaten as u32, 1 + board.food_count, "snake length does not match the foods eaten:\n{}", board.to_ascii());
+    }
+    Ok(())
+}
+
+
+proptest! {
+
+    #[test]
+    fn runs_keep_the_invariants(rules in rules(), seed in any::<u64>(), inputs in inputs()) {
+        play(&rules, seed, &inputs)?;
+    }
+
+    #[test]
+    fn runs_play_again_from_their_replays(rules in rules(), seed in any::<u64>(), inputs in inputs()) {
+        let (board, inputs) = play(&rules, seed, &inputs)?;
+        let replay = Replay { rules, seed, inputs, ticks: board.ticks, score: board.score };
+        // also once written and parsed back
+        let parsed: Replay = replay.to_string().parse().map_err(TestCaseError::fail)?;
+        prop_assert_eq!(&parsed, &replay);
+        let simulated = parsed.simulate();
+        prop_assert_eq!(simulated.score, board.score, "score differs:\n{}", board.to_ascii());
+        prop_assert_eq!(simulated.snake.head_position(), board.snake.head_position(), "head differs:\n{}", board.to_ascii());
+        prop_assert_eq!(simulated.over, board.over);
+    }
+
+}